@@ -0,0 +1,898 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use rand::rngs::ThreadRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::coord::Coord;
+use crate::errors::Error;
+use crate::ricracroe::{Opening, RRRBoard, RRRCell, RRRGame, RRRRules, StonesPerTurn, SwapAnswer};
+use crate::solver::{SolvedPosition, SolvedValue, SolverTable};
+
+/// Boards with more open cells than this get a depth-limited search instead
+/// of a full one, otherwise a 4x4 board takes forever
+pub const FULL_SEARCH_MAX_CELLS: usize = 9;
+const LIMITED_SEARCH_DEPTH: usize = 4;
+const WIN_SCORE: i32 = 1000;
+// Start over rather than let the transposition table grow without limit
+const MAX_TRANSPOSITIONS: usize = 1 << 20;
+// Longest one search runs before settling for the deepest result it
+// finished, otherwise big boards hang the game for minutes
+const SEARCH_TIME: Duration = Duration::from_secs(1);
+
+// Whether a stored score is exact, or only a bound because of a cutoff
+#[derive(Debug, Clone, Copy)]
+enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+// When a search has to stop
+struct Budget {
+    deadline: Instant,
+    exhausted: bool,
+}
+
+impl Budget {
+    fn new(time: Duration) -> Self {
+        Budget {
+            deadline: Instant::now() + time,
+            exhausted: false,
+        }
+    }
+
+    // Whether there's time to look at another position
+    fn spend(&mut self) -> bool {
+        if !self.exhausted && Instant::now() >= self.deadline {
+            self.exhausted = true;
+        }
+        !self.exhausted
+    }
+
+    fn exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Transposition {
+    depth: usize,
+    score: i32,
+    bound: Bound,
+}
+
+// Who places a stone, and how many more they place after it before the other
+// side's turn
+#[derive(Debug, Clone, Copy)]
+struct Mover {
+    player: RRRCell,
+    after: usize,
+    per_turn: usize,
+    // Can keep the first stone of the game out of some cells
+    opening: Opening,
+}
+
+impl Mover {
+    // The side to move in `game`, placing their next stone
+    fn of(game: &RRRGame) -> Mover {
+        Mover {
+            player: game.get_turn(),
+            after: game.stones_left().saturating_sub(1),
+            per_turn: game.stones.then,
+            opening: game.opening,
+        }
+    }
+
+    // Who places the stone after this one
+    fn next(self) -> Mover {
+        if self.after > 0 {
+            Mover {
+                after: self.after - 1,
+                ..self
+            }
+        } else {
+            Mover {
+                player: self.player.opponent(),
+                after: self.per_turn.saturating_sub(1),
+                ..self
+            }
+        }
+    }
+}
+
+/// The value of playing in each open cell, None where it isn't known
+pub type Evaluations = Vec<(Coord, Option<SolvedPosition>)>;
+
+/// Why a hinted move was suggested
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintReason {
+    Wins,
+    Blocks,
+    /// Best whatever happens from here
+    Best,
+    /// Best as far as the search could see before it had to stop
+    BestEvaluated {
+        moves_ahead: usize,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Hint {
+    pub coord: Coord,
+    /// What to place there, in variants where players choose
+    pub piece: Option<RRRCell>,
+    pub reason: HintReason,
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let placement = match self.piece {
+            Some(piece) => format!("{} at {}", piece, self.coord.algebraic()),
+            None => self.coord.algebraic().to_string(),
+        };
+        match self.reason {
+            HintReason::Wins => write!(f, "Playing {} wins the game.", placement),
+            HintReason::Blocks => write!(f, "Playing {} blocks your opponent's win.", placement),
+            HintReason::Best => write!(f, "{} is the best move.", placement),
+            HintReason::BestEvaluated { moves_ahead } => write!(
+                f,
+                "{} is the best evaluated move, looking {} moves ahead.",
+                placement, moves_ahead
+            ),
+        }
+    }
+}
+
+// Would placing `piece` in `coord` right now win the game for `player`?
+fn wins_immediately(game: &RRRGame, coord: &Coord, piece: RRRCell, player: RRRCell) -> bool {
+    let mut board = game.board.clone();
+    if board.make_move(coord, piece).is_err() {
+        return false;
+    }
+    match board.outcome_after(coord) {
+        Some(outcome) => outcome.winner() == Some(player),
+        None => false,
+    }
+}
+
+// Could `player` win by placing anything they can in `coord` right now?
+fn could_win_at(game: &RRRGame, coord: &Coord, player: RRRCell) -> bool {
+    game.board
+        .pieces(player)
+        .into_iter()
+        .any(|piece| wins_immediately(game, coord, piece, player))
+}
+
+/// Ask `advisor` for the best move for the side to play, and explain it
+pub fn suggest_move(advisor: &mut AiPlayer, game: &RRRGame) -> Option<Hint> {
+    let (coord, piece) = advisor.choose_placement(game)?;
+    let player = game.get_turn();
+    let reason = if wins_immediately(game, &coord, piece, player) {
+        HintReason::Wins
+    } else if could_win_at(game, &coord, player.opponent()) {
+        HintReason::Blocks
+    } else {
+        match advisor.lookahead {
+            Some(moves_ahead) => HintReason::BestEvaluated { moves_ahead },
+            None => HintReason::Best,
+        }
+    };
+    let chooses = game.board.get_rules().variant.chooses_pieces();
+    Some(Hint {
+        coord,
+        piece: if chooses { Some(piece) } else { None },
+        reason,
+    })
+}
+
+/// Anything that can pick a move for the side to play, without a terminal
+pub trait RRRPlayer {
+    fn name(&self) -> String;
+    fn choose_move(&mut self, game: &RRRGame) -> Option<Coord>;
+
+    /// Where to play and what to place there. Unless the variant lets
+    /// players choose, that's always their own symbol.
+    fn choose_placement(&mut self, game: &RRRGame) -> Option<(Coord, RRRCell)> {
+        let coord = self.choose_move(game)?;
+        Some((coord, game.get_turn()))
+    }
+}
+
+// Every open cell the opening rule allows, with each thing `player` could
+// place there
+fn placements(board: &RRRBoard, player: RRRCell, opening: Opening) -> Vec<(Coord, RRRCell)> {
+    let pieces = board.pieces(player);
+    board
+        .empty_cells()
+        .into_iter()
+        .filter(|coord| !opening.forbids(board, coord))
+        .flat_map(|coord| pieces.iter().map(move |piece| (coord, *piece)))
+        .collect()
+}
+
+/// How well a computer player plays, weakest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AiLevel {
+    Random,
+    /// Takes a win when there's one, otherwise plays anywhere
+    Beginner,
+    /// Takes a win, or else blocks one, otherwise plays anywhere
+    Intermediate,
+    /// Searches for the best move: to the end of the game on small boards,
+    /// and as far ahead as it can in a second on big ones
+    Perfect,
+}
+
+impl AiLevel {
+    pub fn all() -> &'static [AiLevel] {
+        &[
+            AiLevel::Random,
+            AiLevel::Beginner,
+            AiLevel::Intermediate,
+            AiLevel::Perfect,
+        ]
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        AiLevel::all().iter().map(|level| level.as_str()).collect()
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AiLevel::Random => "random",
+            AiLevel::Beginner => "beginner",
+            AiLevel::Intermediate => "intermediate",
+            AiLevel::Perfect => "perfect",
+        }
+    }
+}
+
+impl fmt::Display for AiLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for AiLevel {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AiLevel::all()
+            .iter()
+            .find(|level| level.as_str().eq_ignore_ascii_case(s))
+            .cloned()
+            .ok_or_else(|| Error::BadInput(format!("there's no computer player called '{}'", s)))
+    }
+}
+
+pub struct AiPlayer {
+    level: AiLevel,
+    // Percentage of moves played at random instead
+    blunder_percent: u32,
+    rng: ThreadRng,
+    table: Option<Rc<SolverTable>>,
+    transpositions: HashMap<u64, Transposition>,
+    // Rules and stones a turn the transposition table entries were searched
+    // under
+    transposition_rules: Option<(RRRRules, StonesPerTurn)>,
+    // How many moves ahead the last search for a move looked, or None if it
+    // saw every way the game could go
+    lookahead: Option<usize>,
+}
+
+impl AiPlayer {
+    pub fn new(level: AiLevel) -> Self {
+        AiPlayer {
+            level,
+            blunder_percent: 0,
+            rng: rand::thread_rng(),
+            table: None,
+            transpositions: HashMap::new(),
+            transposition_rules: None,
+            lookahead: None,
+        }
+    }
+
+    /// Look moves up in a solved table instead of searching, where it covers
+    /// the game being played
+    pub fn with_table(level: AiLevel, table: Rc<SolverTable>) -> Self {
+        AiPlayer {
+            table: Some(table),
+            ..AiPlayer::new(level)
+        }
+    }
+
+    /// Play `percent` of moves at random, whatever the level, to give people
+    /// a chance
+    pub fn with_blunders(self, percent: u32) -> Self {
+        AiPlayer {
+            blunder_percent: percent.min(100),
+            ..self
+        }
+    }
+
+    fn random_move(&mut self, game: &RRRGame) -> Option<(Coord, RRRCell)> {
+        placements(&game.board, game.get_turn(), game.opening)
+            .choose(&mut self.rng)
+            .cloned()
+    }
+
+    // Something `player` could place to win right now, if anything
+    fn winning_move(&mut self, game: &RRRGame, player: RRRCell) -> Option<(Coord, RRRCell)> {
+        let mut candidates = placements(&game.board, player, game.opening);
+        candidates.shuffle(&mut self.rng);
+        candidates
+            .into_iter()
+            .find(|(coord, piece)| wins_immediately(game, coord, *piece, player))
+    }
+
+    // Somewhere the side to move's opponent could win next, filled with
+    // something that doesn't win it for them
+    fn blocking_move(&mut self, game: &RRRGame) -> Option<(Coord, RRRCell)> {
+        let player = game.get_turn();
+        let (coord, _) = self.winning_move(game, player.opponent())?;
+        game.board
+            .pieces(player)
+            .into_iter()
+            .find(|piece| !wins_immediately(game, &coord, *piece, player.opponent()))
+            .map(|piece| (coord, piece))
+    }
+
+    // Drop transposition table entries that can't be reused for this game
+    fn prepare_search(&mut self, game: &RRRGame) {
+        let rules = (game.board.get_rules(), game.stones);
+        if self.transposition_rules != Some(rules) || self.transpositions.len() > MAX_TRANSPOSITIONS
+        {
+            self.transpositions.clear();
+            self.transposition_rules = Some(rules);
+        }
+    }
+
+    // Score of placing `piece` in `coord` for the side to move. Searches that
+    // run all the way to the end of the game give exact results.
+    fn search_move(
+        &mut self,
+        game: &RRRGame,
+        (coord, piece): (&Coord, RRRCell),
+        depth: usize,
+        budget: &mut Budget,
+    ) -> i32 {
+        let mover = Mover::of(game);
+        let score = score_move(
+            &game.board,
+            (coord, piece),
+            mover,
+            depth,
+            (-WIN_SCORE, WIN_SCORE),
+            &mut self.transpositions,
+            budget,
+        );
+        // Still the same side to move if they have stones left this turn
+        if mover.after > 0 {
+            score
+        } else {
+            -score
+        }
+    }
+
+    // Scores of `candidates` for the side to move, searching a move deeper
+    // each time round until the search reaches its full depth or runs out of
+    // budget. Also says how many moves ahead the scores look, and gives None
+    // if not even one could be searched.
+    fn search_placements(
+        &mut self,
+        game: &RRRGame,
+        candidates: &[(Coord, RRRCell)],
+    ) -> Option<(Vec<i32>, usize)> {
+        self.prepare_search(game);
+        let mut budget = Budget::new(SEARCH_TIME);
+        let mut searched = None;
+        for depth in 1..=search_depth(game.board.empty_cells().len()) {
+            let scores: Vec<i32> = candidates
+                .iter()
+                .map(|(coord, piece)| self.search_move(game, (coord, *piece), depth, &mut budget))
+                .collect();
+            if budget.exhausted() {
+                debug!("Search ran out of time at depth {}", depth);
+                break;
+            }
+            searched = Some((scores, depth));
+        }
+        searched
+    }
+
+    fn best_move(&mut self, game: &RRRGame) -> Option<(Coord, RRRCell)> {
+        if let Some(table) = &self.table {
+            // The table doesn't know about opening rules
+            let allowed = |coord: &Coord| !game.opening.forbids(&game.board, coord);
+            if let Some(coord) = table.best_move(game).filter(allowed) {
+                self.lookahead = None;
+                return Some((coord, game.get_turn()));
+            }
+        }
+        let mut candidates = placements(&game.board, game.get_turn(), game.opening);
+        // Shuffle so that equally good moves don't always resolve the same way
+        candidates.shuffle(&mut self.rng);
+        let open = game.board.empty_cells().len();
+        let scores = match self.search_placements(game, &candidates) {
+            Some((scores, depth)) => {
+                self.lookahead = if depth >= open { None } else { Some(depth) };
+                scores
+            }
+            None => {
+                self.lookahead = Some(0);
+                return candidates.first().cloned();
+            }
+        };
+
+        let mut best: Option<((Coord, RRRCell), i32)> = None;
+        for ((coord, piece), score) in candidates.into_iter().zip(scores) {
+            debug!(
+                "{} evaluates {} in {} as {}",
+                game.get_turn(),
+                piece,
+                coord,
+                score
+            );
+            match best {
+                Some((_, best_score)) if best_score >= score => {}
+                _ => best = Some(((coord, piece), score)),
+            }
+        }
+        best.map(|(placement, _)| placement)
+    }
+
+    /// Pick a side when offered a swap. Perfect play takes whichever side is
+    /// better off, and anything weaker picks at random. Never places two
+    /// more stones instead.
+    pub fn choose_side(&mut self, game: &RRRGame) -> SwapAnswer {
+        let turn = game.get_turn();
+        let side = match self.level {
+            AiLevel::Perfect => {
+                let candidates = placements(&game.board, turn, game.opening);
+                let best = self
+                    .search_placements(game, &candidates)
+                    .and_then(|(scores, _)| scores.into_iter().max())
+                    .unwrap_or(0);
+                debug!("{} to move is worth {}", turn, best);
+                if best < 0 {
+                    turn.opponent()
+                } else {
+                    turn
+                }
+            }
+            _ => *[turn, turn.opponent()]
+                .choose(&mut self.rng)
+                .unwrap_or(&turn),
+        };
+        SwapAnswer::Play(side)
+    }
+
+    /// Whether evaluate_moves() can work out the value of every move in
+    /// `game`, from the table or by searching to the end of the game
+    pub fn can_evaluate(&self, game: &RRRGame) -> bool {
+        let in_table = match &self.table {
+            Some(table) => {
+                game.stones.is_single() && table.lookup(&game.board, game.get_turn()).is_some()
+            }
+            None => false,
+        };
+        in_table || game.board.empty_cells().len() <= FULL_SEARCH_MAX_CELLS
+    }
+
+    /// Value of every open cell for the side to move, placing the best thing
+    /// there, or None where the search couldn't see far enough ahead to be
+    /// sure
+    pub fn evaluate_moves(&mut self, game: &RRRGame) -> Evaluations {
+        let open = game.board.empty_cells().len();
+        let mut candidates = game.board.empty_cells();
+        candidates.retain(|coord| !game.opening.forbids(&game.board, coord));
+        if let Some(table) = &self.table {
+            let evaluations: Evaluations = candidates
+                .iter()
+                .map(|coord| (*coord, table.evaluate_move(game, coord)))
+                .collect();
+            if evaluations.iter().all(|(_, eval)| eval.is_some()) {
+                return evaluations;
+            }
+        }
+        let placements = placements(&game.board, game.get_turn(), game.opening);
+        let searched = self.search_placements(game, &placements);
+        candidates
+            .into_iter()
+            .map(|coord| {
+                let position = searched.as_ref().and_then(|(scores, depth)| {
+                    // The best of whatever can be placed there
+                    let score = placements
+                        .iter()
+                        .zip(scores)
+                        .filter(|((cell, _), _)| *cell == coord)
+                        .map(|(_, score)| *score)
+                        .max()?;
+                    score_to_position(score, open, *depth >= open)
+                });
+                (coord, position)
+            })
+            .collect()
+    }
+}
+
+fn search_depth(open_cells: usize) -> usize {
+    if open_cells > FULL_SEARCH_MAX_CELLS {
+        LIMITED_SEARCH_DEPTH
+    } else {
+        open_cells
+    }
+}
+
+// Undo the scaling score_move() applies to wins and losses, to recover how
+// many moves are left in the game
+fn score_to_position(score: i32, open_cells: usize, exhaustive: bool) -> Option<SolvedPosition> {
+    let decisive = WIN_SCORE / 2;
+    let (value, cells_left_at_end) = if score >= decisive {
+        (SolvedValue::Win, (score - decisive) as usize)
+    } else if score <= -decisive {
+        (SolvedValue::Loss, (-score - decisive) as usize)
+    } else if exhaustive {
+        (SolvedValue::Draw, 0)
+    } else {
+        return None;
+    };
+    Some(SolvedPosition {
+        value,
+        distance: open_cells.saturating_sub(cells_left_at_end) as u8,
+    })
+}
+
+impl RRRPlayer for AiPlayer {
+    fn name(&self) -> String {
+        match self.blunder_percent {
+            0 => self.level.to_string(),
+            percent => format!("{}, {}% blunders", self.level, percent),
+        }
+    }
+
+    fn choose_move(&mut self, game: &RRRGame) -> Option<Coord> {
+        self.choose_placement(game).map(|(coord, _)| coord)
+    }
+
+    fn choose_placement(&mut self, game: &RRRGame) -> Option<(Coord, RRRCell)> {
+        if game.over() {
+            return None;
+        }
+        if self.blunder_percent > 0 && self.rng.gen_ratio(self.blunder_percent, 100) {
+            debug!("{} blunders", self.name());
+            return self.random_move(game);
+        }
+        let player = game.get_turn();
+        match self.level {
+            AiLevel::Random => self.random_move(game),
+            AiLevel::Beginner => self
+                .winning_move(game, player)
+                .or_else(|| self.random_move(game)),
+            AiLevel::Intermediate => self
+                .winning_move(game, player)
+                .or_else(|| self.blocking_move(game))
+                .or_else(|| self.random_move(game)),
+            AiLevel::Perfect => self.best_move(game),
+        }
+    }
+}
+
+// Negamax with alpha-beta pruning. Has `mover` place `piece` in `coord` and
+// returns the score of the resulting position from the point of view of the
+// side to move next: the opponent, unless `mover` has more stones to place
+// this turn. Once `budget` runs out the scores mean nothing, and nothing
+// more goes in the transposition table.
+fn score_move(
+    board: &RRRBoard,
+    (coord, piece): (&Coord, RRRCell),
+    mover: Mover,
+    depth: usize,
+    (mut alpha, mut beta): (i32, i32),
+    transpositions: &mut HashMap<u64, Transposition>,
+    budget: &mut Budget,
+) -> i32 {
+    if !budget.spend() {
+        return 0;
+    }
+    let mut board = board.clone();
+    let next = mover.next();
+    if board.make_move(coord, piece).is_err() {
+        // As bad as it gets for `mover`
+        return if next.player == mover.player {
+            -WIN_SCORE
+        } else {
+            WIN_SCORE
+        };
+    }
+    let remaining = board.empty_cells().len();
+
+    if let Some(outcome) = board.outcome_after(coord) {
+        // Scaling by the open cells left prefers quick wins and slow losses
+        let win = WIN_SCORE / 2 + remaining as i32;
+        return match outcome.winner() {
+            Some(winner) if winner == next.player => win,
+            Some(_) => -win,
+            None => 0,
+        };
+    }
+    if depth <= 1 {
+        return 0;
+    }
+
+    // Rotations and reflections of a position are worth the same, so they can
+    // all share an entry
+    let key = board.canonical_hash();
+    if let Some(entry) = transpositions.get(&key) {
+        if entry.depth >= depth {
+            match entry.bound {
+                Bound::Exact => return entry.score,
+                Bound::Lower => alpha = alpha.max(entry.score),
+                Bound::Upper => beta = beta.min(entry.score),
+            }
+            if alpha >= beta {
+                return entry.score;
+            }
+        }
+    }
+    let alpha_orig = alpha;
+
+    // Either `next` places another stone after this one, or the turn passes
+    let keeps_turn = next.after > 0;
+    let mut best = -WIN_SCORE;
+    for (cell, piece) in placements(&board, next.player, next.opening) {
+        let score = if keeps_turn {
+            score_move(
+                &board,
+                (&cell, piece),
+                next,
+                depth - 1,
+                (alpha, beta),
+                transpositions,
+                budget,
+            )
+        } else {
+            -score_move(
+                &board,
+                (&cell, piece),
+                next,
+                depth - 1,
+                (-beta, -alpha),
+                transpositions,
+                budget,
+            )
+        };
+        best = best.max(score);
+        alpha = alpha.max(score);
+        if alpha >= beta || budget.exhausted() {
+            break;
+        }
+    }
+    if budget.exhausted() {
+        return best;
+    }
+
+    let bound = if best <= alpha_orig {
+        Bound::Upper
+    } else if best >= beta {
+        Bound::Lower
+    } else {
+        Bound::Exact
+    };
+    transpositions.insert(
+        key,
+        Transposition {
+            depth,
+            score: best,
+            bound,
+        },
+    );
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ricracroe::{Opening, RRROutcome, RRRRules};
+    use crate::tournament::play_headless;
+
+    fn play(moves: &[(usize, usize)]) -> RRRGame {
+        let mut game = RRRGame::new();
+        for &(x, y) in moves {
+            game.take_turn(&Coord { x, y }).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn hints_explain_wins_and_blocks() {
+        let mut advisor = AiPlayer::new(AiLevel::Perfect);
+
+        // X to play, with two in the top row
+        let hint = suggest_move(&mut advisor, &play(&[(0, 0), (0, 1), (1, 0), (1, 1)])).unwrap();
+        assert_eq!(hint.reason, HintReason::Wins);
+        assert_eq!(hint.coord, Coord { x: 2, y: 0 });
+
+        // O to play, and X threatens the top row
+        let hint = suggest_move(&mut advisor, &play(&[(0, 0), (1, 1), (1, 0)])).unwrap();
+        assert_eq!(hint.reason, HintReason::Blocks);
+        assert_eq!(hint.coord, Coord { x: 2, y: 0 });
+
+        assert!(suggest_move(
+            &mut advisor,
+            &play(&[(0, 0), (1, 1), (1, 0), (2, 2), (2, 0)])
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn hints_say_when_the_search_had_to_stop() {
+        let mut advisor = AiPlayer::new(AiLevel::Perfect);
+        let hint = suggest_move(&mut advisor, &play(&[(1, 1)])).unwrap();
+        assert_eq!(hint.reason, HintReason::Best);
+
+        let rules = RRRRules {
+            win_length: 5,
+            ..RRRRules::standard(15)
+        };
+        let started = Instant::now();
+        let hint = suggest_move(&mut advisor, &RRRGame::new_with_rules(rules)).unwrap();
+        assert!(started.elapsed() < SEARCH_TIME * 3);
+        match hint.reason {
+            HintReason::BestEvaluated { moves_ahead } => assert!(moves_ahead >= 1),
+            reason => panic!("expected a limited search, got {:?}", reason),
+        }
+        assert!(hint
+            .to_string()
+            .contains("is the best evaluated move, looking"));
+    }
+
+    #[test]
+    fn evaluations_are_exact_near_the_end() {
+        let mut advisor = AiPlayer::new(AiLevel::Perfect);
+        // O to play, and X threatens the top row
+        let game = play(&[(0, 0), (1, 1), (1, 0)]);
+        let evaluations = advisor.evaluate_moves(&game);
+        assert_eq!(evaluations.len(), 6);
+        for (coord, eval) in evaluations {
+            let eval = eval.unwrap();
+            if coord == (Coord { x: 2, y: 0 }) {
+                assert_eq!(eval.value, SolvedValue::Draw);
+            } else {
+                // X finishes the top row next
+                assert_eq!(eval.value, SolvedValue::Loss);
+                assert_eq!(eval.distance, 2);
+            }
+        }
+    }
+
+    #[test]
+    fn only_positions_searched_to_the_end_or_solved_can_be_evaluated() {
+        let advisor = AiPlayer::new(AiLevel::Perfect);
+        assert!(advisor.can_evaluate(&RRRGame::new()));
+        let big = RRRGame::new_with_rules(RRRRules::standard(4));
+        assert!(!advisor.can_evaluate(&big));
+
+        let table = Rc::new(SolverTable::solve(RRRRules::default()).unwrap());
+        let advisor = AiPlayer::with_table(AiLevel::Perfect, table);
+        assert!(advisor.can_evaluate(&RRRGame::new()));
+        assert!(!advisor.can_evaluate(&big));
+    }
+
+    #[test]
+    fn everyone_but_random_takes_a_win() {
+        let game = play(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        for &level in &AiLevel::all()[1..] {
+            let mut ai = AiPlayer::new(level);
+            assert_eq!(
+                ai.choose_move(&game),
+                Some(Coord { x: 2, y: 0 }),
+                "{}",
+                level
+            );
+        }
+    }
+
+    #[test]
+    fn intermediate_and_perfect_block() {
+        let game = play(&[(0, 0), (1, 1), (1, 0)]);
+        for &level in &[AiLevel::Intermediate, AiLevel::Perfect] {
+            let mut ai = AiPlayer::new(level);
+            assert_eq!(
+                ai.choose_move(&game),
+                Some(Coord { x: 2, y: 0 }),
+                "{}",
+                level
+            );
+        }
+    }
+
+    #[test]
+    fn perfect_play_never_loses() {
+        let rules = RRRRules::default();
+        let mut perfect = AiPlayer::new(AiLevel::Perfect);
+        let mut random = AiPlayer::new(AiLevel::Random);
+        for _ in 0..10 {
+            let outcome = play_headless(rules, &mut perfect, &mut random).unwrap();
+            assert_ne!(outcome.winner(), Some(RRRCell::O));
+            let outcome = play_headless(rules, &mut random, &mut perfect).unwrap();
+            assert_ne!(outcome.winner(), Some(RRRCell::X));
+        }
+        let mut other = AiPlayer::new(AiLevel::Perfect);
+        let outcome = play_headless(rules, &mut perfect, &mut other).unwrap();
+        assert_eq!(outcome, RRROutcome::Draw);
+    }
+
+    #[test]
+    fn big_boards_are_searched_within_the_time_limit() {
+        let rules = RRRRules {
+            win_length: 5,
+            ..RRRRules::standard(15)
+        };
+        let mut game = RRRGame::new_with_rules(rules);
+        let mut ai = AiPlayer::new(AiLevel::Perfect);
+        let started = Instant::now();
+        assert!(ai.choose_move(&game).is_some());
+        assert!(started.elapsed() < SEARCH_TIME * 3);
+
+        // X has four in the top row, and still sees the win in time
+        for x in 0..4 {
+            game.take_turn(&Coord { x, y: 0 }).unwrap();
+            game.take_turn(&Coord { x, y: 14 }).unwrap();
+        }
+        let started = Instant::now();
+        assert_eq!(ai.choose_move(&game), Some(Coord { x: 4, y: 0 }));
+        assert!(started.elapsed() < SEARCH_TIME * 3);
+    }
+
+    #[test]
+    fn blunders_replace_every_move_at_full_rate() {
+        // Nothing but random moves can't always find the win
+        let game = play(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        let mut ai = AiPlayer::new(AiLevel::Perfect).with_blunders(100);
+        let moves: Vec<Option<Coord>> = (0..50).map(|_| ai.choose_move(&game)).collect();
+        assert!(moves.iter().all(Option::is_some));
+        assert!(moves
+            .iter()
+            .any(|coord| *coord != Some(Coord { x: 2, y: 0 })));
+    }
+
+    #[test]
+    fn first_moves_stay_out_of_a_forbidden_middle() {
+        let game =
+            RRRGame::new_with_rules(RRRRules::standard(5)).with_opening(Opening::OffCentre(3));
+        for &level in AiLevel::all() {
+            let mut ai = AiPlayer::new(level);
+            for _ in 0..10 {
+                let coord = ai.choose_move(&game).unwrap();
+                assert!(
+                    !game.opening.forbids(&game.board, &coord),
+                    "{} played {}",
+                    level,
+                    coord
+                );
+            }
+        }
+        let mut ai = AiPlayer::new(AiLevel::Perfect);
+        assert!(ai
+            .evaluate_moves(&game)
+            .iter()
+            .all(|(coord, _)| !game.opening.forbids(&game.board, coord)));
+    }
+
+    #[test]
+    fn levels_parse_from_their_names() {
+        for &level in AiLevel::all() {
+            assert_eq!(level.as_str().parse::<AiLevel>().unwrap(), level);
+        }
+        assert_eq!("Perfect".parse::<AiLevel>().unwrap(), AiLevel::Perfect);
+        assert!("grandmaster".parse::<AiLevel>().is_err());
+    }
+}
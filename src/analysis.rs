@@ -0,0 +1,61 @@
+use crate::coord::{column_name, Coord};
+use crate::ricracroe::{RRRCell, RRRGame};
+use crate::solver::{SolvedPosition, SolvedValue};
+
+pub const LEGEND: &str = "W/D/L: win, draw, or loss for the side to move, \
+                          then moves until the game ends. ? means unclear.";
+
+/// Single character annotation for a board cell: the number of moves until
+/// the game ends, '+' if that's more than 9, or '?' if the value isn't known
+pub fn cell_glyph(eval: &Option<SolvedPosition>) -> char {
+    match eval {
+        Some(solved) if solved.distance <= 9 => (b'0' + solved.distance) as char,
+        Some(_) => '+',
+        None => '?',
+    }
+}
+
+/// Short label such as "W3", "D9", or "L2"
+pub fn cell_label(eval: &Option<SolvedPosition>) -> String {
+    match eval {
+        Some(solved) => {
+            let value = match solved.value {
+                SolvedValue::Win => 'W',
+                SolvedValue::Draw => 'D',
+                SolvedValue::Loss => 'L',
+            };
+            format!("{}{}", value, solved.distance)
+        }
+        None => "?".to_string(),
+    }
+}
+
+/// Grid of the board with every open cell replaced by its evaluation
+pub fn format_grid(game: &RRRGame, evaluations: &[(Coord, Option<SolvedPosition>)]) -> String {
+    let size = game.board.get_size();
+    let cell_width = 4;
+    let mut grid = String::from("   ");
+    for x in 0..size {
+        grid.push_str(&format!("{:^width$}", column_name(x), width = cell_width));
+    }
+    grid.push('\n');
+
+    for y in 0..size {
+        grid.push_str(&format!("{: >2} ", y + 1));
+        for x in 0..size {
+            let coord = Coord { x, y };
+            let label = match game.board.fetch(&coord) {
+                Ok(RRRCell::Clear) => evaluations
+                    .iter()
+                    .find(|(eval_coord, _)| *eval_coord == coord)
+                    .map(|(_, eval)| cell_label(eval))
+                    .unwrap_or_else(|| "·".to_string()),
+                Ok(cell) => cell.to_string(),
+                Err(_) => "!".to_string(),
+            };
+            grid.push_str(&format!("{:^width$}", label, width = cell_width));
+        }
+        grid.push('\n');
+    }
+    grid
+}
@@ -0,0 +1,260 @@
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use log::{debug, error};
+
+use crate::ai::RRRPlayer;
+use crate::coord::Coord;
+use crate::errors::{Error, Result};
+use crate::ricracroe::{RRRBoard, RRRCell, RRRGame};
+
+/// A computer player that's a separate program, so bots written in any
+/// language can be entered in tournaments. It's asked for one stone at a
+/// time with a line on its stdin, `move <size> <win length> <variant>
+/// <player> <board>`, where the board is its rows from the top separated by
+/// '/' with '.' for empty cells, like `move 3 3 standard O X../.O./..X`. It
+/// answers with a line holding the cell to play, like `b2` or `1,1`, or where
+/// the variant lets players choose what to place, that first, like `Ob2` or
+/// `5b2`. It gets a `quit` line when it isn't needed any more.
+///
+/// A bot that doesn't answer within its time limit is stopped, so that a
+/// late answer can't be taken for the next one, and gives no more moves.
+pub struct ExternalPlayer {
+    command: String,
+    child: Child,
+    input: ChildStdin,
+    // Lines from the bot's stdout, read on their own thread so that waiting
+    // for them can time out
+    answers: Receiver<io::Result<String>>,
+    timeout: Duration,
+}
+
+impl ExternalPlayer {
+    /// Start `command`, split on whitespace into the program and its
+    /// arguments, giving it `timeout` to answer each request
+    pub fn spawn(command: &str, timeout: Duration) -> Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| Error::BadInput("a bot needs a command to run".to_string()))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(Error::talking_to(command))?;
+        let no_pipe = || Error::talking_to(command)(io::Error::from(ErrorKind::BrokenPipe));
+        let input = child.stdin.take().ok_or_else(no_pipe)?;
+        let output = BufReader::new(child.stdout.take().ok_or_else(no_pipe)?);
+        let (sender, answers) = mpsc::channel();
+        // Ends when the bot closes its stdout, or nobody's listening
+        thread::spawn(move || {
+            for line in output.lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(ExternalPlayer {
+            command: command.to_string(),
+            child,
+            input,
+            answers,
+            timeout,
+        })
+    }
+
+    // Send the position and read back the bot's answer
+    fn ask(&mut self, game: &RRRGame) -> Result<(Coord, RRRCell)> {
+        let request = move_request(game);
+        debug!("To {}: {}", self.command, request);
+        let sent = writeln!(self.input, "{}", request).and_then(|_| self.input.flush());
+        sent.map_err(Error::talking_to(&self.command))?;
+        let line = match self.answers.recv_timeout(self.timeout) {
+            Ok(line) => line,
+            Err(RecvTimeoutError::Timeout) => {
+                let _ = self.child.kill();
+                Err(io::Error::new(
+                    ErrorKind::TimedOut,
+                    format!("no answer within {} seconds", self.timeout.as_secs_f64()),
+                ))
+            }
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "it stopped answering",
+            )),
+        }
+        .map_err(Error::talking_to(&self.command))?;
+        debug!("From {}: {}", self.command, line.trim());
+        read_answer(game, line.trim())
+    }
+}
+
+impl RRRPlayer for ExternalPlayer {
+    fn name(&self) -> String {
+        self.command.clone()
+    }
+
+    fn choose_move(&mut self, game: &RRRGame) -> Option<Coord> {
+        self.choose_placement(game).map(|(coord, _)| coord)
+    }
+
+    fn choose_placement(&mut self, game: &RRRGame) -> Option<(Coord, RRRCell)> {
+        match self.ask(game) {
+            Ok(placement) => Some(placement),
+            Err(e) => {
+                error!("{} didn't give a move: {}", self.command, e);
+                None
+            }
+        }
+    }
+}
+
+impl Drop for ExternalPlayer {
+    fn drop(&mut self) {
+        // Ask nicely first, but don't wait on a bot that ignores it
+        let _ = writeln!(self.input, "quit");
+        let _ = self.input.flush();
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// The line asking for a move in `game`, e.g.
+// `move 3 3 standard O X../.O./..X`
+fn move_request(game: &RRRGame) -> String {
+    let rules = game.board.get_rules();
+    format!(
+        "move {} {} {} {} {}",
+        rules.size,
+        rules.win_length,
+        rules.variant,
+        game.get_turn(),
+        board_text(&game.board)
+    )
+}
+
+// The board's rows from the top, separated by '/'
+fn board_text(board: &RRRBoard) -> String {
+    let size = board.get_size();
+    (0..size)
+        .map(|y| {
+            (0..size)
+                .map(|x| match board.fetch(&Coord { x, y }) {
+                    Ok(RRRCell::Clear) | Err(_) => ".".to_string(),
+                    Ok(cell) => cell.to_string(),
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// What a bot's answer places where, checking it says what to place if the
+// variant needs that
+fn read_answer(game: &RRRGame, answer: &str) -> Result<(Coord, RRRCell)> {
+    let variant = game.board.get_rules().variant;
+    let (piece, cell) = if variant.chooses_pieces() {
+        variant
+            .read_piece(answer)
+            .ok_or_else(|| Error::BadInput(format!("'{}' doesn't say what to place", answer)))?
+    } else {
+        (game.get_turn(), answer)
+    };
+    Ok((cell.parse()?, piece))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    use crate::ricracroe::{RRRRules, Variant};
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    #[test]
+    fn requests_show_the_board_row_by_row() {
+        let mut game = RRRGame::new();
+        for &(x, y) in &[(0, 0), (1, 1), (2, 2)] {
+            game.take_turn(&Coord { x, y }).unwrap();
+        }
+        assert_eq!(move_request(&game), "move 3 3 standard O X../.O./..X");
+
+        let mut game = RRRGame::new_with_rules(RRRRules::numerical());
+        game.place(&Coord { x: 1, y: 1 }, RRRCell::Number(5))
+            .unwrap();
+        assert_eq!(move_request(&game), "move 3 3 numerical O .../.5./...");
+
+        let misere = RRRRules {
+            variant: Variant::Misere,
+            ..RRRRules::standard(4)
+        };
+        assert_eq!(
+            move_request(&RRRGame::new_with_rules(misere)),
+            "move 4 4 misere X ..../..../..../...."
+        );
+    }
+
+    #[test]
+    fn answers_say_what_to_place_where_the_variant_needs_it() {
+        let game = RRRGame::new();
+        assert_eq!(
+            read_answer(&game, "b2").unwrap(),
+            (Coord { x: 1, y: 1 }, RRRCell::X)
+        );
+        assert_eq!(
+            read_answer(&game, "0,2").unwrap(),
+            (Coord { x: 0, y: 2 }, RRRCell::X)
+        );
+        assert!(read_answer(&game, "pass").is_err());
+
+        let game = RRRGame::new_with_rules(RRRRules::numerical());
+        assert_eq!(
+            read_answer(&game, "7c1").unwrap(),
+            (Coord { x: 2, y: 0 }, RRRCell::Number(7))
+        );
+        assert!(read_answer(&game, "c1").is_err());
+
+        let game = RRRGame::new_with_rules(RRRRules::order_and_chaos());
+        assert_eq!(
+            read_answer(&game, "o f6").unwrap(),
+            (Coord { x: 5, y: 5 }, RRRCell::O)
+        );
+        assert!(read_answer(&game, "f6").is_err());
+    }
+
+    #[test]
+    fn bots_that_dont_answer_give_no_move() {
+        assert!(ExternalPlayer::spawn("  ", TIMEOUT).is_err());
+        assert!(matches!(
+            ExternalPlayer::spawn("/nonexistent/ricracroe-bot", TIMEOUT),
+            Err(Error::Bot(..))
+        ));
+        // Echoes the request back, which isn't a move
+        let mut bot = ExternalPlayer::spawn("cat", TIMEOUT).unwrap();
+        assert_eq!(bot.name(), "cat");
+        assert_eq!(bot.choose_placement(&RRRGame::new()), None);
+        let mut bot = ExternalPlayer::spawn("true", TIMEOUT).unwrap();
+        assert!(matches!(bot.ask(&RRRGame::new()), Err(Error::Bot(..))));
+        assert_eq!(bot.choose_placement(&RRRGame::new()), None);
+    }
+
+    #[test]
+    fn slow_bots_are_stopped() {
+        let mut bot = ExternalPlayer::spawn("sleep 30", Duration::from_millis(100)).unwrap();
+        let started = Instant::now();
+        match bot.ask(&RRRGame::new()) {
+            Err(Error::Bot(command, err)) => {
+                assert_eq!(command, "sleep 30");
+                assert_eq!(err.kind(), ErrorKind::TimedOut);
+            }
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert_eq!(bot.choose_placement(&RRRGame::new()), None);
+        assert!(started.elapsed() < TIMEOUT);
+    }
+}
@@ -1,21 +1,84 @@
 use std::fmt;
 use std::ops::{Add, Sub};
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+use crate::errors::{Error, Result};
+
+#[derive(Debug, Default, Clone, Copy, Hash, Eq, PartialEq)]
 pub struct Coord {
     pub x: usize,
     pub y: usize,
 }
 
-impl Default for Coord {
-    fn default() -> Self {
-        Self { x: 0, y: 0 }
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+impl Coord {
+    /// Algebraic notation: a column letter and a row number counting from 1,
+    /// so 1,1 is "b2". Columns past 'z' carry on as "aa", "ab", and so on.
+    pub fn algebraic(&self) -> Algebraic {
+        Algebraic(*self)
     }
 }
 
-impl fmt::Display for Coord {
+/// The letters for column `x` in algebraic notation, like the board's labels
+pub fn column_name(x: usize) -> String {
+    let mut column = Vec::new();
+    let mut x = x + 1;
+    while x > 0 {
+        column.push((b'a' + ((x - 1) % 26) as u8) as char);
+        x = (x - 1) / 26;
+    }
+    column.iter().rev().collect()
+}
+
+pub struct Algebraic(Coord);
+
+impl fmt::Display for Algebraic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{},{}", self.x, self.y)
+        write!(f, "{}{}", column_name(self.0.x), self.0.y + 1)
+    }
+}
+
+// Inverse of the column letters in `Algebraic`
+fn parse_column(letters: &str) -> Option<usize> {
+    let mut column: usize = 0;
+    for ch in letters.chars() {
+        let digit = (ch.to_ascii_lowercase() as usize)
+            .checked_sub('a' as usize)
+            .filter(|digit| *digit < 26)?;
+        column = column.checked_mul(26)?.checked_add(digit + 1)?;
+    }
+    column.checked_sub(1)
+}
+
+fn parse_coord(text: &str) -> Option<Coord> {
+    if let Some((x, y)) = text.split_once(',') {
+        return Some(Coord {
+            x: x.trim().parse().ok()?,
+            y: y.trim().parse().ok()?,
+        });
+    }
+    let split = text
+        .find(|ch: char| !ch.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (letters, row) = text.split_at(split);
+    Some(Coord {
+        x: parse_column(letters)?,
+        y: row.parse::<usize>().ok()?.checked_sub(1)?,
+    })
+}
+
+/// Either x,y counting from 0, or algebraic notation such as "b2"
+impl FromStr for Coord {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let text = text.trim();
+        parse_coord(text).ok_or_else(|| Error::BadInput(format!("'{}' isn't a cell", text)))
     }
 }
 
@@ -38,3 +101,29 @@ impl Sub for Coord {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_parse_either_way() {
+        assert_eq!("b2".parse::<Coord>().unwrap(), Coord { x: 1, y: 1 });
+        assert_eq!(" C10 ".parse::<Coord>().unwrap(), Coord { x: 2, y: 9 });
+        assert_eq!("3, 4".parse::<Coord>().unwrap(), Coord { x: 3, y: 4 });
+        assert_eq!("aa1".parse::<Coord>().unwrap(), Coord { x: 26, y: 0 });
+        for bad in &["", "b", "2", "b0", "?2", "1,", "-1,2"] {
+            assert!(matches!(bad.parse::<Coord>(), Err(Error::BadInput(_))));
+        }
+    }
+
+    #[test]
+    fn algebraic_round_trips() {
+        for &(x, y) in &[(0, 0), (1, 1), (25, 3), (26, 0), (701, 9), (702, 99)] {
+            let coord = Coord { x, y };
+            let text = coord.algebraic().to_string();
+            assert_eq!(text.parse::<Coord>().unwrap(), coord, "{}", text);
+        }
+        assert_eq!(Coord { x: 27, y: 11 }.algebraic().to_string(), "ab12");
+    }
+}
@@ -1,22 +1,72 @@
 use std::fmt;
 use std::io;
 use std::num;
+use std::path::{Path, PathBuf};
 
 use std::error;
 
+use crate::ricracroe::RRRError;
+
 #[derive(Debug)]
 pub enum Error {
     InvalidGameInput,
+    EndOfInput,
+    /// A move the game wouldn't allow
+    Game(RRRError),
+    /// Text that doesn't say what it should, like a cell or a time limit
+    BadInput(String),
+    BadConfig(String),
+    BadStats(String),
+    BadRecord(String),
+    /// Couldn't read the file at the path
+    Load(PathBuf, io::Error),
+    /// Couldn't write the file at the path
+    Save(PathBuf, io::Error),
+    /// Couldn't talk to the bot run as the command, e.g. it stopped or took
+    /// too long to answer
+    Bot(String, io::Error),
     Logger(flexi_logger::FlexiLoggerError),
     Terminal(crossterm::ErrorKind),
     Io(io::Error),
     BadParse(num::ParseIntError),
 }
 
+impl Error {
+    /// For `map_err`, to say which file couldn't be read
+    pub fn loading(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |err| Error::Load(path.to_path_buf(), err)
+    }
+
+    /// For `map_err`, to say which file couldn't be written
+    pub fn saving(path: &Path) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |err| Error::Save(path.to_path_buf(), err)
+    }
+
+    /// For `map_err`, to say which bot couldn't be talked to
+    pub fn talking_to(command: &str) -> impl FnOnce(io::Error) -> Error + '_ {
+        move |err| Error::Bot(command.to_string(), err)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::InvalidGameInput => write!(f, "Invalid game input."),
+            Error::EndOfInput => write!(f, "Ran out of input."),
+            Error::Game(ref err) => write!(f, "Illegal move: {}", err),
+            Error::BadInput(ref reason) => write!(f, "Bad input, {}", reason),
+            Error::BadConfig(ref reason) => write!(f, "Bad config file, {}", reason),
+            Error::BadStats(ref reason) => write!(f, "Bad player stats, {}", reason),
+            Error::BadRecord(ref reason) => write!(f, "Bad game record, {}", reason),
+            Error::Load(ref path, ref err) => {
+                write!(f, "Couldn't read {}: {}", path.display(), err)
+            }
+            Error::Save(ref path, ref err) => {
+                write!(f, "Couldn't write {}: {}", path.display(), err)
+            }
+            Error::Bot(ref command, ref err) => {
+                write!(f, "Couldn't talk to bot '{}': {}", command, err)
+            }
             Error::Logger(ref err) => write!(f, "Logger error: {}", err),
             Error::Terminal(ref err) => write!(f, "Terminal error: {}", err),
             Error::Io(ref err) => write!(f, "IO error: {}", err),
@@ -26,19 +76,18 @@ impl fmt::Display for Error {
 }
 
 impl error::Error for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::InvalidGameInput => "Invalid game input.",
-            Error::Logger(ref err) => err.description(),
-            Error::Terminal(ref err) => err.description(),
-            Error::Io(ref err) => err.description(),
-            Error::BadParse(ref err) => err.description(),
-        }
-    }
-
-    fn cause(&self) -> Option<&dyn error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Error::InvalidGameInput => None,
+            Error::EndOfInput => None,
+            Error::Game(ref err) => Some(err),
+            Error::BadInput(_) => None,
+            Error::BadConfig(_) => None,
+            Error::BadStats(_) => None,
+            Error::BadRecord(_) => None,
+            Error::Load(_, ref err) => Some(err),
+            Error::Save(_, ref err) => Some(err),
+            Error::Bot(_, ref err) => Some(err),
             Error::Logger(ref err) => Some(err),
             Error::Terminal(ref err) => Some(err),
             Error::Io(ref err) => Some(err),
@@ -47,6 +96,12 @@ impl error::Error for Error {
     }
 }
 
+impl From<RRRError> for Error {
+    fn from(err: RRRError) -> Error {
+        Error::Game(err)
+    }
+}
+
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Error {
         Error::Io(err)
@@ -72,3 +127,26 @@ impl From<flexi_logger::FlexiLoggerError> for Error {
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn file_errors_say_which_file() {
+        let path = Path::new("/nowhere/stats.tsv");
+        let err = Error::loading(path)(io::Error::new(io::ErrorKind::NotFound, "not found"));
+        assert_eq!(
+            err.to_string(),
+            "Couldn't read /nowhere/stats.tsv: not found"
+        );
+        assert!(err.source().is_some());
+        let err = Error::saving(path)(io::Error::other("disk full"));
+        assert_eq!(
+            err.to_string(),
+            "Couldn't write /nowhere/stats.tsv: disk full"
+        );
+    }
+}
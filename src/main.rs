@@ -1,19 +1,441 @@
-use clap::Arg;
 use clap::{app_from_crate, crate_authors, crate_description, crate_name, crate_version};
+use clap::{Arg, ArgMatches, SubCommand};
 use flexi_logger::Logger;
 use log::debug;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::rc::Rc;
+use std::time::Duration;
 use tempdir::TempDir;
 
 mod errors;
 use crate::errors::{Error, Result};
 
+mod ai;
+mod analysis;
+mod bot;
 mod coord;
+mod paths;
+mod plain;
+mod record;
+mod render;
 mod ricracroe;
+mod solver;
+mod stats;
+mod symmetry;
 mod terminal;
+mod tournament;
 
-fn main() -> Result<()> {
+use crate::ricracroe::{Opening, RRRGame, RRRRules, StonesPerTurn, TimeControl, Variant};
+
+const KEYMAP_FILE: &str = "keys.conf";
+const STATS_FILE: &str = "stats.txt";
+const RECORD_DIR: &str = "games";
+
+fn rules_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("size")
+            .short("s")
+            .long("size")
+            .takes_value(true)
+            .help("Board size (default: 3, or 6 for Order and Chaos)"),
+        Arg::with_name("win-length")
+            .short("k")
+            .long("win-length")
+            .takes_value(true)
+            .help("Number in a row needed to win (default: the board size, or 5 for Order and Chaos)"),
+        Arg::with_name("misere")
+            .long("misere")
+            .conflicts_with("variant")
+            .help("Play misère: whoever completes a line loses"),
+        Arg::with_name("variant")
+            .long("variant")
+            .takes_value(true)
+            .possible_values(&["standard", "misere", "order-and-chaos", "numerical"])
+            .help("What the players are after: standard, misere, order-and-chaos (either player places X or O, X plays Order and wins with a line of either, O plays Chaos and wins by filling the board without one), or numerical (3x3 only, the first player places odd numbers and the second even ones, and a line adding up to 15 wins)"),
+    ]
+}
+
+fn rules_from_matches(matches: &ArgMatches) -> Result<RRRRules> {
+    let variant: Variant = match matches.value_of("variant") {
+        Some(variant) => variant.parse()?,
+        None if matches.is_present("misere") => Variant::Misere,
+        None => Variant::Standard,
+    };
+    let defaults = match variant {
+        Variant::OrderAndChaos => RRRRules::order_and_chaos(),
+        Variant::Numerical => RRRRules::numerical(),
+        _ => RRRRules::default(),
+    };
+    let size: usize = match matches.value_of("size") {
+        Some(size) => size.parse()?,
+        None => defaults.size,
+    };
+    let win_length: usize = match matches.value_of("win-length") {
+        Some(win_length) => win_length.parse()?,
+        None if variant == Variant::OrderAndChaos => defaults.win_length.min(size),
+        None => size,
+    };
+    if size == 0 || win_length == 0 || win_length > size {
+        return Err(Error::BadInput(format!(
+            "a {}x{} board can't have {} in a row",
+            size, size, win_length
+        )));
+    }
+    if variant == Variant::Numerical && (size, win_length) != (3, 3) {
+        return Err(Error::BadInput(
+            "numerical tic-tac-toe is only played on a 3x3 board, three in a row".to_string(),
+        ));
+    }
+    Ok(RRRRules {
+        size,
+        win_length,
+        variant,
+    })
+}
+
+fn run_solver(matches: &ArgMatches) -> Result<()> {
+    let rules = rules_from_matches(matches)?;
+    if rules.variant.chooses_pieces() {
+        return Err(Error::BadInput(format!(
+            "{} can't be solved, only games where each side places their own symbol",
+            rules
+        )));
+    }
+    if !solver::SolverTable::can_solve(&rules) {
+        return Err(Error::BadInput(format!(
+            "{} is too big to solve, at most {} cells are supported",
+            rules,
+            solver::MAX_SOLVABLE_CELLS
+        )));
+    }
+    let table = solver::SolverTable::solve(rules)?;
+    let root = ricracroe::RRRBoard::new_with_rules(rules);
+    if let Some(solved) = table.lookup(&root, ricracroe::RRRCell::X) {
+        println!("{}: {} for X", rules, solved);
+    }
+    println!("{} reachable positions", table.len());
+    if let Some(output) = matches.value_of("output") {
+        table.save(Path::new(output))?;
+        println!("Wrote table to {}", output);
+    }
+    Ok(())
+}
+
+fn run_analysis(matches: &ArgMatches) -> Result<()> {
+    let rules = rules_from_matches(matches)?;
+    let mut game = ricracroe::RRRGame::new_with_rules(rules);
+    for text in matches.values_of("moves").into_iter().flatten() {
+        let coord: coord::Coord = text.parse()?;
+        game.take_turn(&coord)?;
+    }
+
+    let open = game.board.empty_cells().len();
+    let table = match matches.value_of("table") {
+        Some(path) => Some(solver::SolverTable::load(Path::new(path))?),
+        // Too many open cells to search to the end, but few enough to solve
+        None if open > ai::FULL_SEARCH_MAX_CELLS && solver::SolverTable::can_solve(&rules) => {
+            eprintln!("Solving {} first, which can take a minute...", rules);
+            Some(solver::SolverTable::solve(rules)?)
+        }
+        None => None,
+    };
+    let mut advisor = match table {
+        Some(table) => ai::AiPlayer::with_table(ai::AiLevel::Perfect, Rc::new(table)),
+        None => ai::AiPlayer::new(ai::AiLevel::Perfect),
+    };
+    if !game.over() && !advisor.can_evaluate(&game) {
+        return Err(Error::BadInput(format!(
+            "{} open cells are too many to analyze {}, which can't be solved \
+             ahead. Searching to the end works with at most {}.",
+            open,
+            rules,
+            ai::FULL_SEARCH_MAX_CELLS
+        )));
+    }
+    print!("{}", game.board);
+    match game.outcome {
+        Some(ref outcome) => println!("Game over: {}", outcome),
+        None => {
+            println!("{} to move:", game.get_turn());
+            print!(
+                "{}",
+                analysis::format_grid(&game, &advisor.evaluate_moves(&game))
+            );
+            println!("{}", analysis::LEGEND);
+        }
+    }
+    Ok(())
+}
+
+fn run_tournament(matches: &ArgMatches) -> Result<()> {
+    let table = match matches.value_of("table") {
+        Some(path) => Some(Rc::new(solver::SolverTable::load(Path::new(path))?)),
+        None => None,
+    };
+    let settings = tournament::TournamentSettings {
+        rules: rules_from_matches(matches)?,
+        table,
+        games_per_pairing: matches.value_of("games").unwrap_or("10").parse()?,
+        engines: engines_from_matches(matches)?,
+        move_timeout: Duration::from_secs(
+            matches.value_of("move-timeout").unwrap_or("10").parse()?,
+        ),
+    };
+    let standings = tournament::run(&settings)?;
+    print!("{}", tournament::format_results(&standings));
+    Ok(())
+}
+
+// The built-in engines asked for, or one of each level, and then any bots
+fn engines_from_matches(matches: &ArgMatches) -> Result<Vec<tournament::Entrant>> {
+    let levels: Vec<ai::AiLevel> = matches
+        .values_of("engines")
+        .map(|engines| engines.map(str::parse).collect())
+        .unwrap_or_else(|| Ok(ai::AiLevel::all().to_vec()))?;
+    let mut engines: Vec<tournament::Entrant> = levels
+        .into_iter()
+        .map(tournament::Entrant::Engine)
+        .collect();
+    engines.extend(
+        matches
+            .values_of("bot")
+            .into_iter()
+            .flatten()
+            .map(|command| tournament::Entrant::Bot(command.to_string())),
+    );
+    Ok(engines)
+}
+
+fn renderer_from_matches(matches: &ArgMatches) -> Result<Box<dyn render::BoardRenderer>> {
+    let mut renderer = render::renderer_by_name(matches.value_of("renderer").unwrap_or("unicode"))
+        .ok_or(Error::InvalidGameInput)?;
+    if let Some(cell_size) = matches.value_of("cell-size") {
+        renderer.set_cell_size(render::parse_cell_size(cell_size).ok_or_else(|| {
+            Error::BadInput(format!("'{}' isn't a cell size, try e.g. 3x1", cell_size))
+        })?);
+    }
+    Ok(renderer)
+}
+
+// The keymap given on the command line, or else the one in the config
+// directory if there is one, or else the defaults
+fn keymap_from_matches(matches: &ArgMatches) -> Result<terminal::keymap::Keymap> {
+    let path = match matches.value_of("keymap") {
+        Some(path) => PathBuf::from(path),
+        None => match paths::config_dir() {
+            Some(dir) if dir.join(KEYMAP_FILE).exists() => dir.join(KEYMAP_FILE),
+            _ => return Ok(terminal::keymap::Keymap::default()),
+        },
+    };
+    debug!("Loading keymap from {}", path.display());
+    terminal::keymap::Keymap::load(&path)
+}
+
+// Player stats from the file given on the command line, or else the one in
+// the data directory. Without either, nothing is kept.
+fn stats_from_matches(matches: &ArgMatches) -> Result<stats::Stats> {
+    let path = match matches.value_of("stats-file") {
+        Some(path) => PathBuf::from(path),
+        None => match paths::data_dir() {
+            Some(dir) => dir.join(STATS_FILE),
+            None => return Ok(stats::Stats::default()),
+        },
+    };
+    debug!("Loading player stats from {}", path.display());
+    stats::Stats::load(&path)
+}
+
+// Where to save a record of every game: the directory given on the command
+// line, or else one in the data directory
+fn record_dir_from_matches(matches: &ArgMatches) -> Option<PathBuf> {
+    if matches.is_present("no-record") {
+        return None;
+    }
+    match matches.value_of("record-dir") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => paths::data_dir().map(|dir| dir.join(RECORD_DIR)),
+    }
+}
+
+// The time limit given on the command line, if any
+fn time_control_from_matches(matches: &ArgMatches) -> Result<Option<TimeControl>> {
+    matches.value_of("time").map(str::parse).transpose()
+}
+
+// The opening rule given on the command line, which has to leave somewhere
+// to move under `rules`
+fn opening_from_matches(matches: &ArgMatches, rules: &RRRRules) -> Result<Opening> {
+    let opening: Opening = match matches.value_of("opening") {
+        Some(opening) => opening.parse()?,
+        None => return Ok(Opening::Standard),
+    };
+    if !opening.fits(rules) {
+        return Err(Error::BadInput(format!(
+            "{} leaves nowhere for the first move on a {}x{} board",
+            opening, rules.size, rules.size
+        )));
+    }
+    Ok(opening)
+}
+
+// How many stones a turn, from the command line
+fn stones_from_matches(matches: &ArgMatches) -> Result<StonesPerTurn> {
+    match matches.value_of("stones") {
+        Some(stones) => stones.parse(),
+        None => Ok(StonesPerTurn::default()),
+    }
+}
+
+fn game_options_from_matches(
+    matches: &ArgMatches,
+    rules: RRRRules,
+) -> Result<terminal::GameOptions> {
+    Ok(terminal::GameOptions {
+        rules,
+        time_control: time_control_from_matches(matches)?,
+        blunder_percent: blunder_percent_from_matches(matches)?,
+        opening: opening_from_matches(matches, &rules)?,
+        stones: stones_from_matches(matches)?,
+        theme: terminal::theme::Theme::select(matches.value_of("theme")),
+        keymap: keymap_from_matches(matches)?,
+        renderer: renderer_from_matches(matches)?,
+        stats: stats_from_matches(matches)?,
+        record_dir: record_dir_from_matches(matches),
+    })
+}
+
+fn run_stats(matches: &ArgMatches, sub_matches: &ArgMatches) -> Result<()> {
+    let mut stats = stats_from_matches(matches)?;
+    if let Some(name) = sub_matches.value_of("add-player") {
+        stats.add_profile(name)?;
+        println!("Added {}", name.trim());
+        return Ok(());
+    }
+    let players: Vec<String> = match sub_matches.values_of("players") {
+        Some(players) => players.map(str::to_string).collect(),
+        None => stats.profiles().to_vec(),
+    };
+    if players.is_empty() {
+        println!("No players yet, add one with --add-player.");
+        return Ok(());
+    }
+    if sub_matches.values_of("players").is_none() {
+        for line in stats.overview() {
+            println!("{}", line);
+        }
+        println!();
+    }
+    for name in players {
+        println!("{}", stats.report(&name));
+    }
+    Ok(())
+}
+
+// How often computer players pick a random move, as a percentage
+fn blunder_percent_from_matches(matches: &ArgMatches) -> Result<u32> {
+    let rate = matches.value_of("blunder-rate").unwrap_or("0");
+    match rate.parse() {
+        Ok(percent) if percent <= 100 => Ok(percent),
+        _ => Err(Error::BadInput(format!(
+            "'{}' isn't a percentage from 0 to 100",
+            rate
+        ))),
+    }
+}
+
+// A player for the plain text game: a person, or else a computer
+fn plain_player(matches: &ArgMatches, side: &str) -> Result<Option<ai::AiPlayer>> {
+    match matches.value_of(side) {
+        None | Some("human") => Ok(None),
+        Some(level) => Ok(Some(
+            ai::AiPlayer::new(level.parse()?).with_blunders(blunder_percent_from_matches(matches)?),
+        )),
+    }
+}
+
+fn run_plain(matches: &ArgMatches) -> Result<()> {
+    let stdin = std::io::stdin();
+    let rules = rules_from_matches(matches)?;
+    let players = [plain_player(matches, "x")?, plain_player(matches, "o")?];
+    let mut game = RRRGame::new_with_rules(rules)
+        .with_opening(opening_from_matches(matches, &rules)?)
+        .with_stones(stones_from_matches(matches)?);
+    // Last, so the clock doesn't start until everything else is ready
+    if let Some(control) = time_control_from_matches(matches)? {
+        game = game.with_time_control(control);
+    }
+    plain::play_game(
+        stdin.lock(),
+        std::io::stdout(),
+        game,
+        players,
+        record_dir_from_matches(matches).as_deref(),
+    )
+}
+
+fn run_replay(matches: &ArgMatches, sub_matches: &ArgMatches) -> Result<()> {
+    let path = sub_matches
+        .value_of("file")
+        .ok_or(Error::InvalidGameInput)?;
+    terminal::replay(
+        record::GameRecord::load(Path::new(path))?,
+        terminal::theme::Theme::select(matches.value_of("theme")),
+        keymap_from_matches(matches)?,
+        renderer_from_matches(matches)?,
+    )
+}
+
+fn run_headless(matches: &ArgMatches, sub_matches: &ArgMatches) -> Result<()> {
+    let screen = sub_matches.value_of("screen").unwrap_or("80x24");
+    let screen_size = render::parse_cell_size(screen).ok_or_else(|| {
+        Error::BadInput(format!("'{}' isn't a screen size, try e.g. 80x24", screen))
+    })?;
+    let events = sub_matches
+        .values_of("events")
+        .into_iter()
+        .flatten()
+        .map(terminal::backend::parse_input_event)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut screen = terminal::backend::VirtualScreen::new(screen_size);
+    let input = terminal::backend::ScriptedInput::new(events);
+    let result = match sub_matches.value_of("replay") {
+        Some(path) => terminal::replay_on(
+            &mut screen,
+            input,
+            record::GameRecord::load(Path::new(path))?,
+            terminal::theme::Theme::select(matches.value_of("theme")),
+            keymap_from_matches(matches)?,
+            renderer_from_matches(matches)?,
+        ),
+        None => terminal::play_game_on(
+            &mut screen,
+            input,
+            game_options_from_matches(matches, rules_from_matches(sub_matches)?)?,
+        ),
+    };
+    print!("{}", screen.contents());
+    match result {
+        // Scripts are allowed to stop partway through a game
+        Err(Error::EndOfInput) => Ok(()),
+        result => result,
+    }
+}
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<()> {
     let td = TempDir::new(crate_name!()).map_err(Error::from)?;
-    println!("Logging to {}", td.path().display());
+    // Not on stdout, which might be a script reading the game in plain mode
+    eprintln!("Logging to {}", td.path().display());
+    let mut player_names = vec!["human"];
+    player_names.extend(ai::AiLevel::names());
     let matches = app_from_crate!()
         .arg(
             Arg::with_name("debug")
@@ -21,6 +443,228 @@ fn main() -> Result<()> {
                 .multiple(true)
                 .help("Write extended debug log information to a temp file."),
         )
+        .arg(
+            Arg::with_name("theme")
+                .long("theme")
+                .takes_value(true)
+                .possible_values(terminal::theme::THEME_NAMES)
+                .help("Color theme for the board (default: 'default', or 'monochrome' if NO_COLOR is set)"),
+        )
+        .arg(
+            Arg::with_name("renderer")
+                .long("renderer")
+                .takes_value(true)
+                .possible_values(render::RENDERER_NAMES)
+                .help("How to draw the board (default: unicode)"),
+        )
+        .arg(
+            Arg::with_name("cell-size")
+                .long("cell-size")
+                .takes_value(true)
+                .help("Width and height of each board cell, e.g. 3x1 or 5x3 (default: 1x1)"),
+        )
+        .arg(
+            Arg::with_name("keymap")
+                .long("keymap")
+                .takes_value(true)
+                .help("Key bindings file (default: keys.conf in the config directory, if it's there)"),
+        )
+        .arg(
+            Arg::with_name("plain")
+                .long("plain")
+                .help("Play with plain lines of text on stdin and stdout instead of the full screen"),
+        )
+        .arg(
+            Arg::with_name("x")
+                .long("x")
+                .takes_value(true)
+                .possible_values(&player_names)
+                .requires("plain")
+                .help("Who plays X with --plain (default: human)"),
+        )
+        .arg(
+            Arg::with_name("o")
+                .long("o")
+                .takes_value(true)
+                .possible_values(&player_names)
+                .requires("plain")
+                .help("Who plays O with --plain (default: human)"),
+        )
+        .arg(
+            Arg::with_name("time")
+                .long("time")
+                .takes_value(true)
+                .value_name("LIMIT")
+                .help("Time limit for each player, in seconds: total plus increment like 300+5, or per move like 10/move"),
+        )
+        .arg(
+            Arg::with_name("opening")
+                .long("opening")
+                .takes_value(true)
+                .value_name("RULE")
+                .help("Opening rule: standard, swap (the second player picks a side after the first move), swap2, or off-centre-N (the first move can't be in the middle NxN)"),
+        )
+        .arg(
+            Arg::with_name("stones")
+                .long("stones")
+                .takes_value(true)
+                .value_name("PER_TURN")
+                .help("Stones each player places a turn: a number, the first turn's and the rest's like 1,2, or connect6 (default: 1)"),
+        )
+        .arg(
+            Arg::with_name("blunder-rate")
+                .long("blunder-rate")
+                .takes_value(true)
+                .value_name("PERCENT")
+                .help("How often computer players make a random move instead of their own choice (default: 0)"),
+        )
+        .arg(
+            Arg::with_name("stats-file")
+                .long("stats-file")
+                .takes_value(true)
+                .help("Player stats file (default: stats.txt in the data directory)"),
+        )
+        .arg(
+            Arg::with_name("record-dir")
+                .long("record-dir")
+                .takes_value(true)
+                .help("Where to save a record of every game (default: games in the data directory)"),
+        )
+        .arg(
+            Arg::with_name("no-record")
+                .long("no-record")
+                .conflicts_with("record-dir")
+                .help("Don't save records of games"),
+        )
+        .args(&rules_args())
+        .subcommand(
+            SubCommand::with_name("tournament")
+                .about("Run a headless round-robin tournament between computer players")
+                .arg(
+                    Arg::with_name("engines")
+                        .short("e")
+                        .long("engines")
+                        .takes_value(true)
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .possible_values(&ai::AiLevel::names())
+                        .help("Engines to enter, comma separated (default: one of each level)"),
+                )
+                .arg(
+                    Arg::with_name("games")
+                        .short("n")
+                        .long("games")
+                        .takes_value(true)
+                        .help("Games to play between each pair of engines (default: 10)"),
+                )
+                .arg(
+                    Arg::with_name("bot")
+                        .short("b")
+                        .long("bot")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("COMMAND")
+                        .help("Enter a program as a player, which can be given more than once. It's sent lines like 'move 3 3 standard O X../.O./..X' (size, win length, variant, who's to move, and the rows with '.' for empty cells) and answers each with a cell like 'b2' or '1,1', or where the variant lets players choose what to place, that first, like 'Ob2'"),
+                )
+                .arg(
+                    Arg::with_name("move-timeout")
+                        .long("move-timeout")
+                        .takes_value(true)
+                        .value_name("SECONDS")
+                        .help("How long a bot gets to answer each move before it forfeits the game (default: 10)"),
+                )
+                .arg(
+                    Arg::with_name("table")
+                        .short("t")
+                        .long("table")
+                        .takes_value(true)
+                        .help("Solver table for the perfect engine to look moves up in"),
+                )
+                .args(&rules_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("analyze")
+                .about("Print the evaluation of every open cell in a position")
+                .arg(
+                    Arg::with_name("moves")
+                        .multiple(true)
+                        .help("Moves played so far, as x,y pairs starting from 0,0 or in algebraic notation like b2"),
+                )
+                .arg(
+                    Arg::with_name("table")
+                        .short("t")
+                        .long("table")
+                        .takes_value(true)
+                        .help("Solver table to look evaluations up in. Without one, boards of up to 16 cells are solved first"),
+                )
+                .args(&rules_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("headless")
+                .about("Play the game on a virtual screen with scripted input, then print the screen")
+                .arg(
+                    Arg::with_name("events")
+                        .multiple(true)
+                        .help("Input events in order: left, right, up, down, enter, space, esc, backspace, tab, a single character, or click:X,Y"),
+                )
+                .arg(
+                    Arg::with_name("replay")
+                        .long("replay")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Replay a game record instead of playing"),
+                )
+                .arg(
+                    Arg::with_name("screen")
+                        .long("screen")
+                        .takes_value(true)
+                        .help("Width and height of the virtual screen (default: 80x24)"),
+                )
+                .args(&rules_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("keys")
+                .about("Print the key bindings in the keymap file format, to start a new one from"),
+        )
+        .subcommand(
+            SubCommand::with_name("replay")
+                .about("Step through a saved game record move by move")
+                .arg(
+                    Arg::with_name("file")
+                        .required(true)
+                        .help("Game record to replay, like the ones saved in the games directory"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("stats")
+                .about("Print player profiles' results, or add a new player")
+                .arg(
+                    Arg::with_name("players")
+                        .multiple(true)
+                        .help("Players to show (default: everyone)"),
+                )
+                .arg(
+                    Arg::with_name("add-player")
+                        .long("add-player")
+                        .takes_value(true)
+                        .value_name("NAME")
+                        .conflicts_with("players")
+                        .help("Add a player profile, to pick when starting a game"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("solve")
+                .about("Solve every reachable position of a small board")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .takes_value(true)
+                        .help("File to write the solved position table to"),
+                )
+                .args(&rules_args()),
+        )
         .get_matches();
 
     // Initialize logging
@@ -41,6 +685,24 @@ fn main() -> Result<()> {
 
     log_builder.start().map_err(Error::from)?;
 
-    debug!("Starting game...");
-    terminal::play_game()
+    match matches.subcommand() {
+        ("tournament", Some(sub_matches)) => run_tournament(sub_matches),
+        ("solve", Some(sub_matches)) => run_solver(sub_matches),
+        ("analyze", Some(sub_matches)) => run_analysis(sub_matches),
+        ("headless", Some(sub_matches)) => run_headless(&matches, sub_matches),
+        ("stats", Some(sub_matches)) => run_stats(&matches, sub_matches),
+        ("replay", Some(sub_matches)) => run_replay(&matches, sub_matches),
+        ("keys", _) => {
+            print!("{}", keymap_from_matches(&matches)?.to_config());
+            Ok(())
+        }
+        _ if matches.is_present("plain") => run_plain(&matches),
+        _ => {
+            debug!("Starting game...");
+            terminal::play_game(game_options_from_matches(
+                &matches,
+                rules_from_matches(&matches)?,
+            )?)
+        }
+    }
 }
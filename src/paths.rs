@@ -0,0 +1,24 @@
+use std::env;
+use std::path::PathBuf;
+
+use clap::crate_name;
+
+// $`var`/ricracroe, or ~/`fallback`/ricracroe if that isn't set, as in the
+// XDG base directory spec
+fn xdg_dir(var: &str, fallback: &str) -> Option<PathBuf> {
+    let base = match env::var_os(var) {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(fallback),
+    };
+    Some(base.join(crate_name!()))
+}
+
+/// Where config files are looked for
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// Where the game keeps what it saves, like player stats
+pub fn data_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", ".local/share")
+}
@@ -0,0 +1,321 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use log::{debug, error};
+
+use crate::ai::{self, AiLevel, AiPlayer, RRRPlayer};
+use crate::coord::Coord;
+use crate::errors::Result;
+use crate::record::GameRecord;
+use crate::ricracroe::{format_clock, Opening, RRRCell, RRRError, RRRGame, SwapAnswer, Variant};
+
+const HELP: &str = "Type a cell to play there by its column and row, like b2 as on\n\
+                    the board's labels, or as x,y counting from 0,0 at the top\n\
+                    left. 'hint' suggests a move, and 'quit' stops the game.";
+
+const PIECES_HELP: &str = "Either player can place X or O, so say which before the cell,\n\
+                           like Ob2.";
+const NUMBERS_HELP: &str = "The first player places odd numbers and the second even ones,\n\
+                            each only once. Say which before the cell, like 5b2. A line\n\
+                            adding up to 15 wins.";
+
+// The numbers `player` still has in numerical tic-tac-toe, like "1 3 5"
+fn numbers_left(game: &RRRGame, player: RRRCell) -> String {
+    game.board
+        .pieces(player)
+        .iter()
+        .map(RRRCell::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn pieces_help(variant: Variant) -> &'static str {
+    match variant {
+        Variant::Numerical => NUMBERS_HELP,
+        _ => PIECES_HELP,
+    }
+}
+
+/// Play `game` over lines of text: the board and messages are written to
+/// `output`, and moves read a line at a time from `input`, so it works over
+/// anything from a dumb terminal to a pipe. `players` are X then O, None for
+/// a person typing moves in or else a computer player. Running out
+/// of input ends the game. Under an opening rule with swaps, whoever is
+/// offered one types the side they want. In a timed game, taking too long
+/// to type a move in loses, though that's only noticed once it's typed.
+/// A record of the game is saved in `record_dir`.
+pub fn play_game<R: BufRead, W: Write>(
+    input: R,
+    mut output: W,
+    mut game: RRRGame,
+    mut players: [Option<AiPlayer>; 2],
+    record_dir: Option<&Path>,
+) -> Result<()> {
+    let result = play_moves(input, &mut output, &mut game, &mut players);
+    if let (Some(dir), false) = (record_dir, game.moves.is_empty()) {
+        // Named for the sides they ended up on, after any swap
+        let player = |side| match game.seat_of(side) {
+            RRRCell::O => &players[1],
+            _ => &players[0],
+        };
+        let record = GameRecord::new(
+            &game,
+            &player_name(player(RRRCell::X)),
+            &player_name(player(RRRCell::O)),
+        );
+        match record.save_to_dir(dir) {
+            Ok(path) => debug!("Saved the game to {}", path.display()),
+            Err(e) => error!("Couldn't save the game: {}", e),
+        }
+    }
+    result
+}
+
+fn player_name(player: &Option<AiPlayer>) -> String {
+    match player {
+        Some(computer) => format!("Computer ({})", computer.name()),
+        None => "Human".to_string(),
+    }
+}
+
+fn play_moves<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    game: &mut RRRGame,
+    computers: &mut [Option<AiPlayer>; 2],
+) -> Result<()> {
+    let rules = game.board.get_rules();
+    let mut advisor = AiPlayer::new(AiLevel::Perfect);
+
+    writeln!(output, "Ric Rac Roe: {}", rules)?;
+    if game.opening != Opening::Standard {
+        writeln!(output, "Opening rule: {}", game.opening)?;
+    }
+    if !game.stones.is_single() {
+        writeln!(output, "Stones a turn: {}", game.stones)?;
+    }
+    writeln!(output, "{}", HELP)?;
+    let chooses_pieces = rules.variant.chooses_pieces();
+    if chooses_pieces {
+        writeln!(output, "{}", pieces_help(rules.variant))?;
+    }
+    loop {
+        write!(output, "\n{}", game.board)?;
+        if let Some(ref outcome) = game.outcome {
+            writeln!(output, "{}", outcome)?;
+            return Ok(());
+        }
+
+        let player = game.get_turn();
+        let seat = game.seat_to_move();
+        let computer = match seat {
+            RRRCell::O => &mut computers[1],
+            _ => &mut computers[0],
+        };
+        if let Some(offer) = game.swap_offer() {
+            let answer = match computer {
+                Some(computer) => computer.choose_side(game),
+                None => match read_swap_answer(&mut input, &mut output, game, offer.can_place_two)?
+                {
+                    Some(answer) => answer,
+                    None => return Ok(()),
+                },
+            };
+            game.answer_swap(answer)?;
+            if let SwapAnswer::Play(_) = answer {
+                writeln!(
+                    output,
+                    "The first player plays {} and the second plays {}.",
+                    game.side_of(game.first),
+                    game.side_of(game.first.opponent())
+                )?;
+            }
+            continue;
+        }
+        if let Some(computer) = computer {
+            if let Some((coord, piece)) = computer.choose_placement(game) {
+                let played = match game.place(&coord, piece) {
+                    Ok(_) if chooses_pieces => {
+                        writeln!(
+                            output,
+                            "{} places {} at {}.",
+                            rules.variant.player_label(player),
+                            piece,
+                            coord.algebraic()
+                        )?;
+                        true
+                    }
+                    Ok(_) => {
+                        writeln!(output, "{} plays {}.", player, coord.algebraic())?;
+                        true
+                    }
+                    Err(RRRError::OutOfTime(_)) => true,
+                    // Not worth ending the game over, so it's left to whoever
+                    // is at the keyboard
+                    Err(e) => {
+                        error!(
+                            "{} chose {}, which was refused: {}",
+                            computer.name(),
+                            coord,
+                            e
+                        );
+                        writeln!(
+                            output,
+                            "The computer can't play {} ({}), so it's up to you this turn.",
+                            coord.algebraic(),
+                            e
+                        )?;
+                        false
+                    }
+                };
+                if played {
+                    continue;
+                }
+            }
+        }
+
+        // Ask until there's a move that can be played
+        loop {
+            let prompt = if game.placing_opening() {
+                format!("The {} player places {}", game.seat_name(seat), player)
+            } else if game.mid_turn() {
+                format!("{}: place {} more", player, game.stones_left())
+            } else if !game.stones.is_single() {
+                format!("{} to place {}", player, game.stones_left())
+            } else if rules.variant == Variant::Numerical {
+                format!("{} to move, with {}", player, numbers_left(game, player))
+            } else {
+                format!("{} to move", rules.variant.player_label(player))
+            };
+            match game.time_left(player) {
+                Some(left) => write!(output, "{} ({})> ", prompt, format_clock(left))?,
+                None => write!(output, "{}> ", prompt)?,
+            }
+            output.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                debug!("Input ended before the game did");
+                return Ok(());
+            }
+            let text = line.trim();
+            match text {
+                "" => continue,
+                "quit" | "q" => return Ok(()),
+                "help" | "?" => {
+                    writeln!(output, "{}", HELP)?;
+                    if chooses_pieces {
+                        writeln!(output, "{}", pieces_help(rules.variant))?;
+                    }
+                }
+                "hint" => match ai::suggest_move(&mut advisor, game) {
+                    Some(hint) => writeln!(output, "Hint for {}: {}", player, hint)?,
+                    None => writeln!(output, "No hint available.")?,
+                },
+                _ => {
+                    let (piece, text) = if chooses_pieces {
+                        match rules.variant.read_piece(text) {
+                            Some(split) => split,
+                            None => {
+                                writeln!(output, "{}", pieces_help(rules.variant))?;
+                                continue;
+                            }
+                        }
+                    } else {
+                        (player, text)
+                    };
+                    let coord = match text.parse::<Coord>() {
+                        Ok(coord) if coord.x < rules.size && coord.y < rules.size => coord,
+                        _ => {
+                            writeln!(output, "'{}' isn't on the board, try e.g. b2 or 1,1.", text)?;
+                            continue;
+                        }
+                    };
+                    match game.place(&coord, piece) {
+                        Ok(_) | Err(RRRError::OutOfTime(_)) => break,
+                        Err(e) => writeln!(
+                            output,
+                            "{} cannot play in {} ({})",
+                            player,
+                            coord.algebraic(),
+                            e
+                        )?,
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Ask whoever is offered a swap which side they want, until they give an
+// answer. None if they quit or the input runs out.
+fn read_swap_answer<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    game: &RRRGame,
+    can_place_two: bool,
+) -> Result<Option<SwapAnswer>> {
+    let choices = if can_place_two {
+        "x, o, or 'two' to place two more stones, one of each, and let the other player pick"
+    } else {
+        "x or o"
+    };
+    let seat = game.seat_name(game.seat_to_move());
+    writeln!(output, "The {} player picks a side: {}.", seat, choices)?;
+    loop {
+        write!(output, "Side for the {} player> ", seat)?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(None);
+        }
+        match line.trim().to_ascii_lowercase().as_str() {
+            "" => continue,
+            "quit" | "q" => return Ok(None),
+            "x" => return Ok(Some(SwapAnswer::Play(RRRCell::X))),
+            "o" => return Ok(Some(SwapAnswer::Play(RRRCell::O))),
+            "two" if can_place_two => return Ok(Some(SwapAnswer::PlaceTwo)),
+            _ => writeln!(output, "Type {}.", choices)?,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(lines: &str, players: [Option<AiPlayer>; 2]) -> String {
+        let mut output = Vec::new();
+        play_game(lines.as_bytes(), &mut output, RRRGame::new(), players, None).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn moves_are_typed_a_line_at_a_time() {
+        let text = transcript("b2\na1\n1,1\nz9\nb1\nc3\nb3\n", [None, None]);
+        assert!(text.contains("X cannot play in b2"), "{}", text);
+        assert!(text.contains("'z9' isn't on the board"), "{}", text);
+        assert!(text.contains("X Wins!"), "{}", text);
+    }
+
+    #[test]
+    fn input_can_end_early() {
+        let text = transcript("hint\n", [None, None]);
+        assert!(text.contains("Hint for X: "), "{}", text);
+        assert!(text.trim_end().ends_with("X to move>"), "{}", text);
+    }
+
+    #[test]
+    fn computers_play_themselves() {
+        let text = transcript(
+            "",
+            [
+                Some(AiPlayer::new(AiLevel::Perfect)),
+                Some(AiPlayer::new(AiLevel::Perfect)),
+            ],
+        );
+        assert!(text.contains("X plays "), "{}", text);
+        assert!(text.contains("It's a draw!"), "{}", text);
+    }
+}
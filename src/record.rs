@@ -0,0 +1,755 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::debug;
+
+use crate::coord::Coord;
+use crate::errors::{Error, Result};
+use crate::ricracroe::{
+    Opening, RRRCell, RRRGame, RRROutcome, RRRRules, StonesPerTurn, SwapAnswer, TimeControl,
+    Variant,
+};
+
+/// File extension for saved game records
+pub const RECORD_EXTENSION: &str = "rrg";
+
+// Longest line of moves, like PGN's export format
+const MOVE_LINE_WIDTH: usize = 79;
+
+/// A game written down so it can be kept, passed around, and replayed. The
+/// format is modeled on chess's PGN: tag pairs like `[X "alice"]` for the
+/// rules, players, date, who moved first, and result, then a blank line and
+/// the moves in algebraic notation, numbered a turn each for X and O and
+/// ending with the result: "1-0" if X won, "0-1" if O won, "1/2-1/2" for a draw, or "*" if
+/// the game wasn't finished. Timed games have a TimeControl tag, and a
+/// Termination tag of "time forfeit" if someone ran out of time. Games with
+/// an opening rule have an Opening tag, and name the players for the sides
+/// they ended up on after any swap. Games of more than one stone a turn have
+/// a Stones tag, like "1,2" for Connect6's turns. A Swap tag lists the
+/// answers to any swap offers, like "place-two,O", and timed games have a
+/// TimeLeft tag of the seconds left on X's and O's clocks, like "271.5,130".
+/// Variants other than standard and misère have a Variant tag, and where
+/// players choose what to place, each move starts with what was placed, like
+/// "Ob2".
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub rules: RRRRules,
+    pub x: String,
+    pub o: String,
+    /// When the game was played, as YYYY.MM.DD
+    pub date: String,
+    pub first: RRRCell,
+    pub moves: Vec<Coord>,
+    /// What each move placed, in variants where players choose, or else
+    /// empty
+    pub pieces: Vec<RRRCell>,
+    pub time_control: Option<TimeControl>,
+    /// Who ran out of time, if the game ended that way
+    pub lost_on_time: Option<RRRCell>,
+    pub opening: Opening,
+    /// How each swap offer was answered, in order
+    pub swap_answers: Vec<SwapAnswer>,
+    pub stones: StonesPerTurn,
+    /// Time left on X's and O's clocks, in timed games
+    pub time_left: Option<[Duration; 2]>,
+}
+
+fn result_token(outcome: Option<&RRROutcome>) -> &'static str {
+    match outcome.map(RRROutcome::winner) {
+        Some(Some(RRRCell::X)) => "1-0",
+        Some(Some(_)) => "0-1",
+        Some(None) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+const TIME_FORFEIT: &str = "time forfeit";
+
+const RESULT_TOKENS: &[&str] = &["1-0", "0-1", "1/2-1/2", "*"];
+
+// Year, month and day of a count of days since 1970-01-01, from Howard
+// Hinnant's civil_from_days
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+// The UTC date, and seconds into the day
+fn now() -> ((i64, i64, i64), u64) {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    (civil_from_days((seconds / 86_400) as i64), seconds % 86_400)
+}
+
+// Seconds, to the millisecond, without trailing zeros
+fn format_seconds(time: Duration) -> String {
+    let text = format!("{}.{:03}", time.as_secs(), time.subsec_millis());
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn parse_seconds(text: &str) -> Option<Duration> {
+    match text.trim().parse::<f64>() {
+        Ok(secs) if secs.is_finite() && secs >= 0.0 => Some(Duration::from_secs_f64(secs)),
+        _ => None,
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// A `[Name "value"]` line, or None if it isn't one
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, quoted) = inner.split_once(' ')?;
+    let quoted = quoted.trim().strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => value.push(chars.next()?),
+            ch => value.push(ch),
+        }
+    }
+    Some((name.to_string(), value))
+}
+
+impl GameRecord {
+    /// A record of `game`, so far, between `x` and `o`, played today
+    pub fn new(game: &RRRGame, x: &str, o: &str) -> Self {
+        let ((year, month, day), _) = now();
+        GameRecord {
+            rules: game.board.get_rules(),
+            x: x.to_string(),
+            o: o.to_string(),
+            date: format!("{:04}.{:02}.{:02}", year, month, day),
+            first: game.first,
+            moves: game.moves.clone(),
+            pieces: if game.board.get_rules().variant.chooses_pieces() {
+                game.moves
+                    .iter()
+                    .filter_map(|coord| game.board.fetch(coord).ok())
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            time_control: game.clocks.as_ref().map(|clocks| clocks.control),
+            lost_on_time: match game.outcome {
+                Some(RRROutcome::LossOnTime { loser }) => Some(loser),
+                _ => None,
+            },
+            opening: game.opening,
+            swap_answers: game.swap_answers.clone(),
+            stones: game.stones,
+            time_left: game
+                .time_left(RRRCell::X)
+                .zip(game.time_left(RRRCell::O))
+                .map(|(x, o)| [x, o]),
+        }
+    }
+
+    /// The game after its first `count` moves, and any swap offers answered
+    /// by then, without clocks
+    pub fn game_after(&self, count: usize) -> Result<RRRGame> {
+        let mut game = RRRGame::new_with_rules(self.rules)
+            .starting_with(self.first)
+            .with_opening(self.opening)
+            .with_stones(self.stones);
+        let mut answers = self.swap_answers.iter();
+        let answer = |game: &mut RRRGame, answer: SwapAnswer| {
+            game.answer_swap(answer)
+                .map_err(|_| Error::BadRecord(format!("'{}' doesn't answer a swap offer", answer)))
+        };
+        for (num, coord) in self.moves.iter().take(count).enumerate() {
+            if let Some(offer) = game.swap_offer() {
+                // Records from before the Swap tag don't say, so take it the
+                // sides were kept
+                let next = answers.next().copied();
+                answer(&mut game, next.unwrap_or(SwapAnswer::Play(offer.chooser)))?;
+            }
+            let played = !game.over()
+                && match self.pieces.get(num) {
+                    Some(piece) => game.place(coord, *piece).is_ok(),
+                    None => game.take_turn(coord).is_ok(),
+                };
+            if !played {
+                return Err(Error::BadRecord(format!(
+                    "move {} ({}) can't be played",
+                    num + 1,
+                    coord.algebraic()
+                )));
+            }
+        }
+        if game.swap_offer().is_some() {
+            if let Some(&next) = answers.next() {
+                answer(&mut game, next)?;
+            }
+        }
+        if let Some(loser) = self.lost_on_time {
+            if count == self.moves.len() && !game.over() {
+                game.outcome = Some(RRROutcome::LossOnTime { loser });
+            }
+        }
+        Ok(game)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        fs::read_to_string(path)
+            .map_err(Error::loading(path))?
+            .parse()
+    }
+
+    /// Write the record over the file at `path`, e.g. the one a resumed game
+    /// came from
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string()).map_err(Error::saving(path))
+    }
+
+    /// The most recently saved record in `dir` of a game that isn't over, and
+    /// the path it was loaded from
+    pub fn newest_unfinished(dir: &Path) -> Result<Option<(PathBuf, GameRecord)>> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::Load(dir.to_path_buf(), err)),
+        };
+        let mut paths: Vec<(SystemTime, PathBuf)> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension() == Some(RECORD_EXTENSION.as_ref()))
+            .map(|path| {
+                let modified = fs::metadata(&path)
+                    .and_then(|meta| meta.modified())
+                    .unwrap_or(UNIX_EPOCH);
+                (modified, path)
+            })
+            .collect();
+        paths.sort();
+        for (_, path) in paths.into_iter().rev() {
+            // Anything that isn't a good record isn't worth resuming
+            let unfinished = GameRecord::load(&path).and_then(|record| {
+                let over = record.game_after(record.moves.len())?.over();
+                Ok((record, over))
+            });
+            match unfinished {
+                Ok((record, false)) => return Ok(Some((path, record))),
+                Ok(_) => (),
+                Err(e) => debug!("Not resuming {}: {}", path.display(), e),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write the record to a new file in `dir`, named for when it was saved,
+    /// and return the file's path
+    pub fn save_to_dir(&self, dir: &Path) -> Result<PathBuf> {
+        fs::create_dir_all(dir).map_err(Error::saving(dir))?;
+        let ((year, month, day), time) = now();
+        let stem = format!(
+            "{:04}-{:02}-{:02}-{:02}{:02}{:02}",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60
+        );
+        // Games can finish within the same second
+        for num in 1.. {
+            let name = match num {
+                1 => format!("{}.{}", stem, RECORD_EXTENSION),
+                num => format!("{}-{}.{}", stem, num, RECORD_EXTENSION),
+            };
+            let path = dir.join(name);
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(mut file) => {
+                    file.write_all(self.to_string().as_bytes())
+                        .map_err(Error::saving(&path))?;
+                    return Ok(path);
+                }
+                Err(ref err) if err.kind() == ErrorKind::AlreadyExists => continue,
+                Err(err) => return Err(Error::Save(path, err)),
+            }
+        }
+        unreachable!()
+    }
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = self
+            .game_after(self.moves.len())
+            .ok()
+            .and_then(|game| game.outcome);
+        let result = result_token(outcome.as_ref());
+        let mut tags = vec![
+            ("Game", "Ric Rac Roe".to_string()),
+            ("Date", self.date.clone()),
+            ("Size", self.rules.size.to_string()),
+            ("WinLength", self.rules.win_length.to_string()),
+            (
+                "Misere",
+                if self.rules.variant == Variant::Misere {
+                    "yes"
+                } else {
+                    "no"
+                }
+                .to_string(),
+            ),
+            ("X", self.x.clone()),
+            ("O", self.o.clone()),
+            ("First", self.first.to_string()),
+            ("Result", result.to_string()),
+        ];
+        if self.rules.variant.chooses_pieces() {
+            tags.push(("Variant", self.rules.variant.to_string()));
+        }
+        if self.opening != Opening::Standard {
+            tags.push(("Opening", self.opening.to_string()));
+        }
+        if !self.stones.is_single() {
+            tags.push(("Stones", self.stones.to_string()));
+        }
+        if let Some(control) = self.time_control {
+            tags.push(("TimeControl", control.to_string()));
+        }
+        if !self.swap_answers.is_empty() {
+            let answers: Vec<String> = self.swap_answers.iter().map(|a| a.to_string()).collect();
+            tags.push(("Swap", answers.join(",")));
+        }
+        if let Some([x, o]) = self.time_left {
+            tags.push((
+                "TimeLeft",
+                format!("{},{}", format_seconds(x), format_seconds(o)),
+            ));
+        }
+        if self.lost_on_time.is_some() {
+            tags.push(("Termination", TIME_FORFEIT.to_string()));
+        }
+        for (name, value) in tags.iter() {
+            writeln!(f, "[{} \"{}\"]", name, escape(value))?;
+        }
+        writeln!(f)?;
+
+        let moves: Vec<String> = self
+            .moves
+            .iter()
+            .enumerate()
+            .map(|(num, coord)| match self.pieces.get(num) {
+                Some(piece) => format!("{}{}", piece, coord.algebraic()),
+                None => coord.algebraic().to_string(),
+            })
+            .collect();
+        // Numbered by X's and O's turns together, however many stones a turn
+        let mut turns = Vec::new();
+        let mut rest = &moves[..];
+        let mut stones = self.stones.first;
+        while !rest.is_empty() {
+            let (turn, after) = rest.split_at(stones.min(rest.len()));
+            turns.push(turn);
+            rest = after;
+            stones = self.stones.then;
+        }
+        let mut tokens = Vec::new();
+        for (num, pair) in turns.chunks(2).enumerate() {
+            tokens.push(format!("{}.", num + 1));
+            tokens.extend(pair.iter().flat_map(|turn| turn.iter().cloned()));
+        }
+        tokens.push(result.to_string());
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > MOVE_LINE_WIDTH {
+                writeln!(f, "{}", line)?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{}", line)
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut record = GameRecord {
+            rules: RRRRules::default(),
+            x: String::new(),
+            o: String::new(),
+            date: String::new(),
+            first: RRRCell::X,
+            moves: Vec::new(),
+            pieces: Vec::new(),
+            time_control: None,
+            lost_on_time: None,
+            opening: Opening::Standard,
+            swap_answers: Vec::new(),
+            stones: StonesPerTurn::default(),
+            time_left: None,
+        };
+        let mut result = String::new();
+        let mut time_forfeit = false;
+        let bad = |reason: String| Error::BadRecord(reason);
+        let number = |name: &str, value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| bad(format!("{} should be a number, not '{}'", name, value)))
+        };
+        let mut body = String::new();
+        for line in text.lines() {
+            let line = line.trim();
+            if !line.starts_with('[') {
+                body.push_str(line);
+                body.push(' ');
+                continue;
+            }
+            let (name, value) = parse_tag(line).ok_or_else(|| bad(format!("bad tag {}", line)))?;
+            // Tags this doesn't know about are allowed, and skipped
+            match name.as_str() {
+                "Size" => record.rules.size = number(&name, &value)?,
+                "WinLength" => record.rules.win_length = number(&name, &value)?,
+                "Misere" if value == "yes" => record.rules.variant = Variant::Misere,
+                "Variant" => {
+                    record.rules.variant = value
+                        .parse()
+                        .map_err(|_| bad(format!("bad variant '{}'", value)))?
+                }
+                "X" => record.x = value,
+                "O" => record.o = value,
+                "Date" => record.date = value,
+                "Result" => result = value,
+                "Termination" => time_forfeit = value == TIME_FORFEIT,
+                "Opening" => {
+                    record.opening = value
+                        .parse()
+                        .map_err(|_| bad(format!("bad opening rule '{}'", value)))?
+                }
+                "Stones" => {
+                    record.stones = value
+                        .parse()
+                        .map_err(|_| bad(format!("bad stones a turn '{}'", value)))?
+                }
+                "TimeControl" if value != "-" => {
+                    record.time_control = Some(
+                        value
+                            .parse()
+                            .map_err(|_| bad(format!("bad time control '{}'", value)))?,
+                    )
+                }
+                "Swap" => {
+                    record.swap_answers = value
+                        .split(',')
+                        .map(|answer| {
+                            answer
+                                .parse()
+                                .map_err(|_| bad(format!("bad swap answer '{}'", answer)))
+                        })
+                        .collect::<Result<_>>()?
+                }
+                "TimeLeft" => {
+                    let times = value
+                        .split_once(',')
+                        .and_then(|(x, o)| Some([parse_seconds(x)?, parse_seconds(o)?]));
+                    record.time_left =
+                        Some(times.ok_or_else(|| bad(format!("bad time left '{}'", value)))?)
+                }
+                "First" => {
+                    record.first = match value.as_str() {
+                        "X" => RRRCell::X,
+                        "O" => RRRCell::O,
+                        _ => return Err(bad(format!("'{}' can't move first", value))),
+                    }
+                }
+                _ => (),
+            }
+        }
+        let rules = record.rules;
+        if rules.size == 0 || rules.win_length == 0 || rules.win_length > rules.size {
+            return Err(bad(format!("{} isn't a game that can be played", rules)));
+        }
+
+        for token in body.split_whitespace() {
+            if RESULT_TOKENS.contains(&token) {
+                break;
+            }
+            // Move numbers, possibly run into the move after them like "1.b2"
+            let token = match token.split_once('.') {
+                Some((num, rest)) if num.chars().all(|ch| ch.is_ascii_digit()) => {
+                    rest.trim_start_matches('.')
+                }
+                _ => token,
+            };
+            if token.is_empty() {
+                continue;
+            }
+            let token = if rules.variant.chooses_pieces() {
+                let (piece, cell) = rules
+                    .variant
+                    .read_piece(token)
+                    .ok_or_else(|| bad(format!("'{}' doesn't say what was placed", token)))?;
+                record.pieces.push(piece);
+                cell
+            } else {
+                token
+            };
+            let coord: Coord = token
+                .parse()
+                .map_err(|_| bad(format!("'{}' isn't a move", token)))?;
+            record.moves.push(coord);
+        }
+        if time_forfeit {
+            record.lost_on_time = match result.as_str() {
+                "1-0" => Some(RRRCell::O),
+                "0-1" => Some(RRRCell::X),
+                _ => return Err(bad(format!("a time forfeit can't end in '{}'", result))),
+            };
+        }
+        // Make sure every move can be played
+        record.game_after(record.moves.len())?;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn cell(text: &str) -> Coord {
+        text.parse().unwrap()
+    }
+
+    // A record of `moves` played in a fresh game with `rules`
+    fn record_of(rules: RRRRules, moves: &[&str]) -> GameRecord {
+        let mut game = RRRGame::new_with_rules(rules);
+        for text in moves {
+            match rules.variant.read_piece(text) {
+                Some((piece, coord)) if rules.variant.chooses_pieces() => {
+                    game.place(&cell(coord), piece).unwrap()
+                }
+                _ => game.take_turn(&cell(text)).unwrap(),
+            };
+        }
+        GameRecord::new(&game, "alice", "bob")
+    }
+
+    // Write `record` out and read it back, checking nothing was lost
+    fn round_trip(record: &GameRecord) -> GameRecord {
+        let text = record.to_string();
+        let read: GameRecord = text.parse().unwrap();
+        assert_eq!(read.rules, record.rules);
+        assert_eq!(read.x, record.x);
+        assert_eq!(read.o, record.o);
+        assert_eq!(read.date, record.date);
+        assert_eq!(read.first, record.first);
+        assert_eq!(read.moves, record.moves);
+        assert_eq!(read.pieces, record.pieces);
+        assert_eq!(read.time_control, record.time_control);
+        assert_eq!(read.lost_on_time, record.lost_on_time);
+        assert_eq!(read.opening, record.opening);
+        assert_eq!(read.swap_answers, record.swap_answers);
+        assert_eq!(read.stones, record.stones);
+        assert_eq!(read.time_left, record.time_left);
+        assert_eq!(read.to_string(), text);
+        read
+    }
+
+    #[test]
+    fn standard_games_round_trip() {
+        let mut record = record_of(RRRRules::default(), &["b2", "a1", "c3", "c1", "a3"]);
+        record.x = "Robert \"Bobby\" \\ Tables".to_string();
+        let text = record.to_string();
+        assert!(text.contains("[Result \"*\"]"));
+        assert!(text.contains("1. b2 a1 2. c3 c1 3. a3 *"));
+        round_trip(&record);
+
+        let record = record_of(RRRRules::default(), &["a1", "a2", "b1", "b2", "c1"]);
+        assert!(record.to_string().ends_with("3. c1 1-0\n"));
+        round_trip(&record);
+    }
+
+    #[test]
+    fn placed_pieces_round_trip() {
+        let record = record_of(RRRRules::numerical(), &["5b2", "2a1", "1c1", "8c3"]);
+        assert_eq!(
+            record.pieces,
+            vec![
+                RRRCell::Number(5),
+                RRRCell::Number(2),
+                RRRCell::Number(1),
+                RRRCell::Number(8),
+            ]
+        );
+        let text = record.to_string();
+        assert!(text.contains("[Variant \"numerical\"]"));
+        assert!(text.contains("1. 5b2 2a1 2. 1c1 8c3 0-1"));
+        round_trip(&record);
+
+        let record = record_of(RRRRules::order_and_chaos(), &["Oa1", "Xf6", "Ob1"]);
+        assert_eq!(record.pieces, vec![RRRCell::O, RRRCell::X, RRRCell::O]);
+        let text = record.to_string();
+        assert!(text.contains("[Variant \"order-and-chaos\"]"));
+        assert!(text.contains("1. Oa1 Xf6 2. Ob1 *"));
+        round_trip(&record);
+    }
+
+    #[test]
+    fn rules_and_clocks_round_trip() {
+        let mut record = record_of(
+            RRRRules {
+                variant: Variant::Misere,
+                ..RRRRules::standard(5)
+            },
+            &[],
+        );
+        record.stones = StonesPerTurn::connect6();
+        record.opening = Opening::OffCentre(2);
+        record.time_control = Some("300+5".parse().unwrap());
+        record.first = RRRCell::O;
+        record.moves = ["a1", "b1", "c1", "a2", "b2"]
+            .iter()
+            .map(|text| cell(text))
+            .collect();
+        let text = record.to_string();
+        assert!(text.contains("[Stones \"1,2\"]"));
+        assert!(text.contains("1. a1 b1 c1 2. a2 b2 *"));
+        record.swap_answers = vec![SwapAnswer::PlaceTwo, SwapAnswer::Play(RRRCell::O)];
+        record.time_left = Some([Duration::from_millis(271_500), Duration::from_secs(130)]);
+        let text = record.to_string();
+        assert!(text.contains("[Swap \"place-two,O\"]"));
+        assert!(text.contains("[TimeLeft \"271.5,130\"]"));
+        let read = round_trip(&record);
+        let game = read.game_after(3).unwrap();
+        assert_eq!(game.board.fetch(&cell("c1")).unwrap(), RRRCell::X);
+        assert_eq!(game.get_turn(), RRRCell::O);
+
+        record.lost_on_time = Some(RRRCell::X);
+        assert!(record
+            .to_string()
+            .contains("[Termination \"time forfeit\"]"));
+        let read = round_trip(&record);
+        assert_eq!(
+            read.game_after(read.moves.len()).unwrap().outcome,
+            Some(RRROutcome::LossOnTime { loser: RRRCell::X })
+        );
+    }
+
+    #[test]
+    fn bad_records_are_refused() {
+        let numerical = "[Size \"3\"]\n[WinLength \"3\"]\n[Variant \"numerical\"]\n\n1. b2 *\n";
+        match numerical.parse::<GameRecord>() {
+            Err(Error::BadRecord(reason)) => {
+                assert_eq!(reason, "'b2' doesn't say what was placed")
+            }
+            other => panic!("expected a bad record, got {:?}", other),
+        }
+        let replayed = "[Size \"3\"]\n[WinLength \"3\"]\n\n1. b2 b2 *\n";
+        assert!(matches!(
+            replayed.parse::<GameRecord>(),
+            Err(Error::BadRecord(_))
+        ));
+        let too_long = "[Size \"3\"]\n[WinLength \"4\"]\n\n*\n";
+        assert!(matches!(
+            too_long.parse::<GameRecord>(),
+            Err(Error::BadRecord(_))
+        ));
+        let swap = "[Size \"3\"]\n[WinLength \"3\"]\n[Swap \"maybe\"]\n\n*\n";
+        assert!(matches!(
+            swap.parse::<GameRecord>(),
+            Err(Error::BadRecord(_))
+        ));
+        let clocks = "[Size \"3\"]\n[WinLength \"3\"]\n[TimeLeft \"-1,5\"]\n\n*\n";
+        assert!(matches!(
+            clocks.parse::<GameRecord>(),
+            Err(Error::BadRecord(_))
+        ));
+        let nonsense = "[Size \"3\"]\n\n1. b2 zz *\n";
+        assert!(matches!(
+            nonsense.parse::<GameRecord>(),
+            Err(Error::BadRecord(_))
+        ));
+    }
+
+    #[test]
+    fn game_after_replays_part_of_a_game() {
+        let record = record_of(RRRRules::default(), &["a1", "a2", "b1", "b2", "c1"]);
+        let start = record.game_after(0).unwrap();
+        assert!(start.moves.is_empty());
+        let partway = record.game_after(3).unwrap();
+        assert_eq!(partway.board.fetch(&cell("b1")).unwrap(), RRRCell::X);
+        assert_eq!(partway.board.fetch(&cell("b2")).unwrap(), RRRCell::Clear);
+        assert_eq!(partway.get_turn(), RRRCell::O);
+        assert!(!partway.over());
+        assert!(record.game_after(5).unwrap().over());
+    }
+
+    #[test]
+    fn newest_unfinished_skips_finished_games() {
+        let dir = TempDir::new("ricracroe-records").unwrap();
+        assert!(GameRecord::newest_unfinished(&dir.path().join("missing"))
+            .unwrap()
+            .is_none());
+
+        let unfinished = record_of(RRRRules::default(), &["b2", "a1"]);
+        let path = unfinished.save_to_dir(dir.path()).unwrap();
+        let finished = record_of(RRRRules::default(), &["a1", "a2", "b1", "b2", "c1"]);
+        let other = finished.save_to_dir(dir.path()).unwrap();
+        assert_ne!(path, other);
+        fs::write(dir.path().join("junk.rrg"), "not a record").unwrap();
+
+        let (found, record) = GameRecord::newest_unfinished(dir.path()).unwrap().unwrap();
+        assert_eq!(found, path);
+        assert_eq!(record.moves, unfinished.moves);
+    }
+
+    #[test]
+    fn swaps_are_replayed() {
+        let mut game = RRRGame::new_with_rules(RRRRules::standard(5)).with_opening(Opening::Swap2);
+        for text in &["a1", "b2", "c3"] {
+            game.take_turn(&cell(text)).unwrap();
+        }
+        game.answer_swap(SwapAnswer::PlaceTwo).unwrap();
+        for text in &["d4", "e5"] {
+            game.take_turn(&cell(text)).unwrap();
+        }
+        game.answer_swap(SwapAnswer::Play(RRRCell::O)).unwrap();
+        let record = round_trip(&GameRecord::new(&game, "alice", "bob"));
+        assert!(record.to_string().contains("[Swap \"place-two,O\"]"));
+
+        let replayed = record.game_after(record.moves.len()).unwrap();
+        assert_eq!(replayed.swap_offer(), None);
+        assert_eq!(replayed.side_of(RRRCell::X), RRRCell::O);
+        assert_eq!(replayed.seat_to_move(), game.seat_to_move());
+        assert!(record.game_after(3).unwrap().placing_opening());
+
+        let mut wrong = record.clone();
+        wrong.swap_answers = vec![SwapAnswer::PlaceTwo, SwapAnswer::PlaceTwo];
+        assert!(matches!(
+            wrong.game_after(wrong.moves.len()),
+            Err(Error::BadRecord(_))
+        ));
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::coord::{column_name, Coord};
+use crate::ricracroe::{RRRBoard, RRRCell};
+use crate::solver::SolvedValue;
+
+pub const RENDERER_NAMES: &[&str] = &["unicode", "ascii", "compact"];
+
+/// What a rendered character represents, so frontends can style it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphRole {
+    Grid,
+    Empty,
+    Piece(RRRCell),
+    /// A number in numerical tic-tac-toe, and who placed it
+    Number(RRRCell),
+    Annotation(Option<SolvedValue>),
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Highlights {
+    pub cursor: bool,
+    pub last_move: bool,
+    pub winning: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyph {
+    pub ch: char,
+    pub role: GlyphRole,
+    pub highlights: Highlights,
+}
+
+impl Glyph {
+    fn grid(ch: char) -> Self {
+        Glyph {
+            ch,
+            role: GlyphRole::Grid,
+            highlights: Highlights::default(),
+        }
+    }
+}
+
+/// Everything about the board's presentation that isn't part of the game
+#[derive(Debug, Default, Clone)]
+pub struct UiState {
+    pub cursor: Option<Coord>,
+    pub last_move: Option<Coord>,
+    pub winning: Vec<Coord>,
+    /// Replaces the glyph of an empty cell, e.g. with its analysis
+    pub annotations: HashMap<Coord, (char, Option<SolvedValue>)>,
+}
+
+impl UiState {
+    fn cell_glyph(&self, board: &RRRBoard, cell: &Coord, empty: char) -> Glyph {
+        let (ch, role) = match board.fetch(cell) {
+            Ok(RRRCell::X) => ('X', GlyphRole::Piece(RRRCell::X)),
+            Ok(RRRCell::O) => ('O', GlyphRole::Piece(RRRCell::O)),
+            Ok(piece @ RRRCell::Number(num)) => (
+                std::char::from_digit(num.into(), 10).unwrap_or('#'),
+                GlyphRole::Number(board.owner(piece)),
+            ),
+            Ok(RRRCell::Clear) => match self.annotations.get(cell) {
+                Some(&(ch, value)) => (ch, GlyphRole::Annotation(value)),
+                None => (empty, GlyphRole::Empty),
+            },
+            Err(_) => ('!', GlyphRole::Empty),
+        };
+        Glyph {
+            ch,
+            role,
+            highlights: Highlights {
+                cursor: self.cursor == Some(*cell),
+                last_move: self.last_move == Some(*cell),
+                winning: self.winning.contains(cell),
+            },
+        }
+    }
+}
+
+pub type RenderedBoard = Vec<Vec<Glyph>>;
+
+/// Turns a board into rows of glyphs. Positions are relative to the top
+/// left corner of the rendered board.
+pub trait BoardRenderer {
+    /// Change the width and height of each cell, in characters
+    fn set_cell_size(&mut self, cell_size: Coord);
+
+    /// Width and height of the rendered board, in characters
+    fn rendered_size(&self, board_size: usize) -> Coord;
+
+    /// Where a cell's glyph is drawn
+    fn cell_offset(&self, cell: &Coord) -> Coord;
+
+    /// Which cell, if any, is drawn at `offset`
+    fn cell_at(&self, offset: &Coord, board_size: usize) -> Option<Coord>;
+
+    fn render(&self, board: &RRRBoard, ui: &UiState) -> RenderedBoard;
+}
+
+pub fn renderer_by_name(name: &str) -> Option<Box<dyn BoardRenderer>> {
+    match name {
+        "unicode" => Some(Box::new(BoxRenderer::unicode())),
+        "ascii" => Some(Box::new(BoxRenderer::ascii())),
+        "compact" => Some(Box::new(CompactRenderer::default())),
+        _ => None,
+    }
+}
+
+// Characters for drawing the grid, named for where they sit, and big pieces
+// for cells with room for them
+struct LineSet {
+    top: [char; 3],
+    middle: [char; 3],
+    bottom: [char; 3],
+    horizontal: char,
+    vertical: char,
+    big_x: [&'static str; 3],
+    big_o: [&'static str; 3],
+}
+
+// Box drawing chars from https://en.wikipedia.org/wiki/Box-drawing_character
+const UNICODE_LINES: LineSet = LineSet {
+    top: ['╭', '┬', '╮'],
+    middle: ['├', '┼', '┤'],
+    bottom: ['╰', '┴', '╯'],
+    horizontal: '─',
+    vertical: '│',
+    big_x: ["▀▄ ▄▀", "  █  ", "▄▀ ▀▄"],
+    big_o: ["▄▀▀▀▄", "█   █", "▀▄▄▄▀"],
+};
+
+const ASCII_LINES: LineSet = LineSet {
+    top: ['+', '+', '+'],
+    middle: ['+', '+', '+'],
+    bottom: ['+', '+', '+'],
+    horizontal: '-',
+    vertical: '|',
+    big_x: ["\\   /", "  X  ", "/   \\"],
+    big_o: ["/---\\", "|   |", "\\---/"],
+};
+
+const BIG_PIECE_SIZE: Coord = Coord { x: 5, y: 3 };
+
+/// Width and height of a single cell, in characters
+pub fn parse_cell_size(text: &str) -> Option<Coord> {
+    let mut parts = text.splitn(2, 'x');
+    let x = parts.next()?.trim().parse().ok()?;
+    let y = parts.next()?.trim().parse().ok()?;
+    if x == 0 || y == 0 {
+        return None;
+    }
+    Some(Coord { x, y })
+}
+
+// Row `dy` of a cell `cell_size` big. Pieces are drawn big if there's room,
+// anything else is a single character in the middle of the cell.
+fn cell_row(glyph: Glyph, lines: &LineSet, cell_size: &Coord, dy: usize) -> Vec<Glyph> {
+    let blank = Glyph { ch: ' ', ..glyph };
+    let mut row = vec![blank; cell_size.x];
+
+    let art = match glyph.role {
+        GlyphRole::Piece(RRRCell::X) => Some(&lines.big_x),
+        GlyphRole::Piece(RRRCell::O) => Some(&lines.big_o),
+        _ => None,
+    };
+    match art {
+        Some(art) if cell_size.x >= BIG_PIECE_SIZE.x && cell_size.y >= BIG_PIECE_SIZE.y => {
+            let pad = Coord {
+                x: (cell_size.x - BIG_PIECE_SIZE.x) / 2,
+                y: (cell_size.y - BIG_PIECE_SIZE.y) / 2,
+            };
+            if dy >= pad.y && dy < pad.y + BIG_PIECE_SIZE.y {
+                for (dx, ch) in art[dy - pad.y].chars().enumerate() {
+                    row[pad.x + dx] = Glyph { ch, ..glyph };
+                }
+            }
+        }
+        _ => {
+            if dy == cell_size.y / 2 {
+                row[cell_size.x / 2] = glyph;
+            }
+        }
+    }
+    row
+}
+
+/// Cells surrounded by grid lines on every side
+pub struct BoxRenderer {
+    lines: &'static LineSet,
+    cell_size: Coord,
+}
+
+impl BoxRenderer {
+    pub fn unicode() -> Self {
+        BoxRenderer {
+            lines: &UNICODE_LINES,
+            cell_size: Coord { x: 1, y: 1 },
+        }
+    }
+
+    pub fn ascii() -> Self {
+        BoxRenderer {
+            lines: &ASCII_LINES,
+            cell_size: Coord { x: 1, y: 1 },
+        }
+    }
+
+    // Distance from one cell to the next, including the grid line
+    fn stride(&self) -> Coord {
+        self.cell_size + Coord { x: 1, y: 1 }
+    }
+
+    fn render_line(&self, ends: &[char; 3], size: usize) -> Vec<Glyph> {
+        // starting and ending char, the width of each board cell, and a char separating
+        // each board cell
+        let mut line = Vec::with_capacity(size * self.stride().x + 1);
+        for x in 0..size {
+            line.push(Glyph::grid(if x == 0 { ends[0] } else { ends[1] }));
+            for _ in 0..self.cell_size.x {
+                line.push(Glyph::grid(self.lines.horizontal));
+            }
+        }
+        line.push(Glyph::grid(ends[2]));
+        line
+    }
+}
+
+impl BoardRenderer for BoxRenderer {
+    fn set_cell_size(&mut self, cell_size: Coord) {
+        self.cell_size = cell_size;
+    }
+
+    fn rendered_size(&self, board_size: usize) -> Coord {
+        Coord {
+            x: board_size * self.stride().x + 1,
+            y: board_size * self.stride().y + 1,
+        }
+    }
+
+    fn cell_offset(&self, cell: &Coord) -> Coord {
+        let stride = self.stride();
+        Coord {
+            x: cell.x * stride.x + 1 + self.cell_size.x / 2,
+            y: cell.y * stride.y + 1 + self.cell_size.y / 2,
+        }
+    }
+
+    fn cell_at(&self, offset: &Coord, board_size: usize) -> Option<Coord> {
+        let stride = self.stride();
+        // Grid lines don't belong to any cell
+        if offset.x.is_multiple_of(stride.x) || offset.y.is_multiple_of(stride.y) {
+            return None;
+        }
+        let cell = Coord {
+            x: offset.x / stride.x,
+            y: offset.y / stride.y,
+        };
+        if cell.x < board_size && cell.y < board_size {
+            Some(cell)
+        } else {
+            None
+        }
+    }
+
+    fn render(&self, board: &RRRBoard, ui: &UiState) -> RenderedBoard {
+        let size = board.get_size();
+        let mut rows = Vec::with_capacity(self.rendered_size(size).y);
+        for y in 0..size {
+            if y == 0 {
+                rows.push(self.render_line(&self.lines.top, size));
+            } else {
+                rows.push(self.render_line(&self.lines.middle, size));
+            }
+            for dy in 0..self.cell_size.y {
+                let mut row = Vec::with_capacity(self.rendered_size(size).x);
+                for x in 0..size {
+                    row.push(Glyph::grid(self.lines.vertical));
+                    let glyph = ui.cell_glyph(board, &Coord { x, y }, ' ');
+                    row.extend(cell_row(glyph, self.lines, &self.cell_size, dy));
+                }
+                row.push(Glyph::grid(self.lines.vertical));
+                rows.push(row);
+            }
+        }
+        rows.push(self.render_line(&self.lines.bottom, size));
+        rows
+    }
+}
+
+/// No grid lines, just the cells separated by spaces
+pub struct CompactRenderer {
+    cell_size: Coord,
+}
+
+impl Default for CompactRenderer {
+    fn default() -> Self {
+        CompactRenderer {
+            cell_size: Coord { x: 1, y: 1 },
+        }
+    }
+}
+
+impl CompactRenderer {
+    // Tall cells get a blank row between them, otherwise they run together
+    fn stride(&self) -> Coord {
+        Coord {
+            x: self.cell_size.x + 1,
+            y: if self.cell_size.y > 1 {
+                self.cell_size.y + 1
+            } else {
+                1
+            },
+        }
+    }
+}
+
+impl BoardRenderer for CompactRenderer {
+    fn set_cell_size(&mut self, cell_size: Coord) {
+        self.cell_size = cell_size;
+    }
+
+    fn rendered_size(&self, board_size: usize) -> Coord {
+        let stride = self.stride();
+        Coord {
+            x: (board_size * stride.x).saturating_sub(1),
+            y: board_size * stride.y - (stride.y - self.cell_size.y),
+        }
+    }
+
+    fn cell_offset(&self, cell: &Coord) -> Coord {
+        let stride = self.stride();
+        Coord {
+            x: cell.x * stride.x + self.cell_size.x / 2,
+            y: cell.y * stride.y + self.cell_size.y / 2,
+        }
+    }
+
+    fn cell_at(&self, offset: &Coord, board_size: usize) -> Option<Coord> {
+        let stride = self.stride();
+        // The gaps between cells don't belong to any cell
+        if offset.x % stride.x >= self.cell_size.x || offset.y % stride.y >= self.cell_size.y {
+            return None;
+        }
+        let cell = Coord {
+            x: offset.x / stride.x,
+            y: offset.y / stride.y,
+        };
+        if cell.x < board_size && cell.y < board_size {
+            Some(cell)
+        } else {
+            None
+        }
+    }
+
+    fn render(&self, board: &RRRBoard, ui: &UiState) -> RenderedBoard {
+        let size = board.get_size();
+        let stride = self.stride();
+        let mut rows = Vec::with_capacity(self.rendered_size(size).y);
+        for y in 0..size {
+            if y > 0 && stride.y > self.cell_size.y {
+                rows.push(vec![Glyph::grid(' '); self.rendered_size(size).x]);
+            }
+            for dy in 0..self.cell_size.y {
+                let mut row = Vec::with_capacity(self.rendered_size(size).x);
+                for x in 0..size {
+                    if x > 0 {
+                        row.push(Glyph::grid(' '));
+                    }
+                    let glyph = ui.cell_glyph(board, &Coord { x, y }, '·');
+                    row.extend(cell_row(glyph, &UNICODE_LINES, &self.cell_size, dy));
+                }
+                rows.push(row);
+            }
+        }
+        rows
+    }
+}
+
+pub fn glyphs_to_string(row: &[Glyph]) -> String {
+    row.iter().map(|glyph| glyph.ch).collect()
+}
+
+impl fmt::Display for RRRBoard {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let size = self.get_size();
+        let indent: String = "    ".to_string();
+
+        // Write column letters, as in algebraic notation
+        let mut line = indent.clone();
+        for x in 0..size {
+            line.push(' ');
+            line.push_str(&column_name(x));
+        }
+        writeln!(f, "{}", line)?;
+
+        let renderer = BoxRenderer::unicode();
+        for (num, row) in renderer
+            .render(self, &UiState::default())
+            .iter()
+            .enumerate()
+        {
+            // Cell rows get their row number, grid lines are just indented
+            if num % 2 == 1 {
+                line = format!("{: >3} ", num / 2 + 1);
+            } else {
+                line = indent.clone();
+            }
+            line.push_str(&glyphs_to_string(row));
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ricracroe::RRRGame;
+
+    #[test]
+    fn cells_are_found_where_they_are_drawn() {
+        for name in RENDERER_NAMES {
+            for &cell_size in &[
+                Coord { x: 1, y: 1 },
+                Coord { x: 3, y: 1 },
+                Coord { x: 5, y: 3 },
+            ] {
+                let mut renderer = renderer_by_name(name).unwrap();
+                renderer.set_cell_size(cell_size);
+                let rendered = renderer.rendered_size(4);
+                for y in 0..4 {
+                    for x in 0..4 {
+                        let cell = Coord { x, y };
+                        let offset = renderer.cell_offset(&cell);
+                        assert!(offset.x < rendered.x && offset.y < rendered.y, "{}", name);
+                        assert_eq!(renderer.cell_at(&offset, 4), Some(cell), "{}", name);
+                    }
+                }
+                assert_eq!(renderer.cell_at(&rendered, 4), None, "{}", name);
+            }
+        }
+    }
+
+    #[test]
+    fn cell_sizes_parse() {
+        assert_eq!(parse_cell_size("3x1"), Some(Coord { x: 3, y: 1 }));
+        assert_eq!(parse_cell_size("5x3"), Some(Coord { x: 5, y: 3 }));
+        assert_eq!(parse_cell_size("0x3"), None);
+        assert_eq!(parse_cell_size("5"), None);
+    }
+
+    #[test]
+    fn clicks_on_grid_lines_miss() {
+        let mut renderer = BoxRenderer::ascii();
+        renderer.set_cell_size(Coord { x: 3, y: 1 });
+        assert_eq!(renderer.cell_at(&Coord { x: 4, y: 1 }, 3), None);
+        assert_eq!(
+            renderer.cell_at(&Coord { x: 5, y: 1 }, 3),
+            Some(Coord { x: 1, y: 0 })
+        );
+        assert_eq!(
+            renderer.cell_at(&Coord { x: 7, y: 1 }, 3),
+            Some(Coord { x: 1, y: 0 })
+        );
+        assert_eq!(renderer.cell_at(&Coord { x: 5, y: 2 }, 3), None);
+    }
+
+    #[test]
+    fn big_cells_get_big_pieces() {
+        let mut game = RRRGame::new();
+        game.take_turn(&Coord { x: 0, y: 0 }).unwrap();
+        let mut renderer = BoxRenderer::ascii();
+        renderer.set_cell_size(Coord { x: 5, y: 3 });
+        let rows: Vec<String> = renderer
+            .render(&game.board, &UiState::default())
+            .iter()
+            .map(|row| glyphs_to_string(row))
+            .collect();
+        assert_eq!(rows[1], "|\\   /|     |     |");
+        assert_eq!(rows[2], "|  X  |     |     |");
+        assert_eq!(rows[3], "|/   \\|     |     |");
+    }
+
+    #[test]
+    fn boards_render_with_their_pieces() {
+        let mut game = RRRGame::new();
+        game.take_turn(&Coord { x: 0, y: 0 }).unwrap();
+        game.take_turn(&Coord { x: 1, y: 1 }).unwrap();
+        let ui = UiState {
+            cursor: Some(Coord { x: 2, y: 2 }),
+            ..UiState::default()
+        };
+
+        let rows: Vec<String> = BoxRenderer::ascii()
+            .render(&game.board, &ui)
+            .iter()
+            .map(|row| glyphs_to_string(row))
+            .collect();
+        assert_eq!(
+            rows,
+            vec!["+-+-+-+", "|X| | |", "+-+-+-+", "| |O| |", "+-+-+-+", "| | | |", "+-+-+-+"]
+        );
+
+        let compact = CompactRenderer::default().render(&game.board, &ui);
+        assert_eq!(glyphs_to_string(&compact[1]), "· O ·");
+        assert_eq!(compact[1][2].role, GlyphRole::Piece(RRRCell::O));
+        assert!(compact[2][4].highlights.cursor);
+    }
+
+    #[test]
+    fn boards_are_labeled_like_algebraic_notation() {
+        let mut game = RRRGame::new();
+        game.take_turn(&"b2".parse().unwrap()).unwrap();
+        let text = game.board.to_string();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "     a b c");
+        assert_eq!(lines[1], "    ╭─┬─┬─╮");
+        assert_eq!(lines[2], "  1 │ │ │ │");
+        assert_eq!(lines[4], "  2 │ │X│ │");
+    }
+}
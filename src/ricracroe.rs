@@ -1,18 +1,30 @@
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::error;
 use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::vec::Vec;
 
 use log::error;
 
 use crate::coord::Coord;
+use crate::errors::Error;
+use crate::symmetry::Symmetry;
 
 #[derive(Debug)]
 pub enum RRRError {
     InvalidCellPosition(Coord),
     CellAlreadySet(Coord, RRRCell),
     NoActivePlayer,
+    OutOfTime(RRRCell),
+    /// Someone has to pick a side before the game goes on
+    SwapPending,
+    /// A swap answer that wasn't one of the choices
+    NotOffered,
+    /// The first move was in the middle NxN of the board
+    RestrictedOpening(usize),
+    /// A player tried to place something that isn't theirs to place
+    WrongPiece(RRRCell, RRRCell),
 }
 
 impl fmt::Display for RRRError {
@@ -25,27 +37,20 @@ impl fmt::Display for RRRError {
                 write!(f, "{} has already been played in by {}.", coord, plyr)
             }
             RRRError::NoActivePlayer => write!(f, "The current game has no active player."),
+            RRRError::OutOfTime(plyr) => write!(f, "{} has run out of time.", plyr),
+            RRRError::SwapPending => write!(f, "A side has to be picked first."),
+            RRRError::NotOffered => write!(f, "That isn't one of the choices."),
+            RRRError::RestrictedOpening(middle) => write!(
+                f,
+                "The first move can't be in the middle {}x{}.",
+                middle, middle
+            ),
+            RRRError::WrongPiece(plyr, piece) => write!(f, "{} can't place {}.", plyr, piece),
         }
     }
 }
 
-impl error::Error for RRRError {
-    fn description(&self) -> &str {
-        match *self {
-            RRRError::InvalidCellPosition(_) => "invalid cell position",
-            RRRError::CellAlreadySet(_, _) => "cell already played in",
-            RRRError::NoActivePlayer => "no active player",
-        }
-    }
-
-    fn cause(&self) -> Option<&dyn error::Error> {
-        match *self {
-            RRRError::InvalidCellPosition(_) => None,
-            RRRError::CellAlreadySet(_, _) => None,
-            RRRError::NoActivePlayer => None,
-        }
-    }
-}
+impl error::Error for RRRError {}
 
 // Allow default debug output display
 #[derive(Debug)]
@@ -59,6 +64,18 @@ pub enum RRRCell {
     Clear,
     X,
     O,
+    /// A number placed in numerical tic-tac-toe
+    Number(u8),
+}
+
+impl RRRCell {
+    pub fn opponent(self) -> RRRCell {
+        match self {
+            RRRCell::X => RRRCell::O,
+            RRRCell::O => RRRCell::X,
+            other => other,
+        }
+    }
 }
 
 impl fmt::Display for RRRCell {
@@ -67,6 +84,7 @@ impl fmt::Display for RRRCell {
             RRRCell::Clear => write!(f, "·"),
             RRRCell::X => write!(f, "X"),
             RRRCell::O => write!(f, "O"),
+            RRRCell::Number(num) => write!(f, "{}", num),
         }
     }
 }
@@ -79,8 +97,50 @@ impl fmt::Display for RRRCell {
 #[derive(Clone)]
 pub enum RRROutcome {
     Draw,
-    XWins { winning_cells: Vec<Coord> },
-    OWins { winning_cells: Vec<Coord> },
+    XWins {
+        winning_cells: Vec<Coord>,
+    },
+    OWins {
+        winning_cells: Vec<Coord>,
+    },
+    /// `loser`'s clock ran out, so the other player wins
+    LossOnTime {
+        loser: RRRCell,
+    },
+    /// In Order and Chaos, Order (X) made a line of either symbol
+    OrderWins {
+        winning_cells: Vec<Coord>,
+    },
+    /// In Order and Chaos, Chaos (O) filled the board without a line
+    ChaosWins,
+    /// `loser` didn't make a legal move in time, so the other player wins
+    Forfeit {
+        loser: RRRCell,
+    },
+}
+
+impl RRROutcome {
+    pub fn winner(&self) -> Option<RRRCell> {
+        match *self {
+            RRROutcome::Draw => None,
+            RRROutcome::XWins { .. } => Some(RRRCell::X),
+            RRROutcome::OWins { .. } => Some(RRRCell::O),
+            RRROutcome::LossOnTime { loser } => Some(loser.opponent()),
+            RRROutcome::OrderWins { .. } => Some(RRRCell::X),
+            RRROutcome::ChaosWins => Some(RRRCell::O),
+            RRROutcome::Forfeit { loser } => Some(loser.opponent()),
+        }
+    }
+
+    /// The line that won the game, if it was won with one
+    pub fn winning_cells(&self) -> Option<&[Coord]> {
+        match self {
+            RRROutcome::XWins { winning_cells }
+            | RRROutcome::OWins { winning_cells }
+            | RRROutcome::OrderWins { winning_cells } => Some(winning_cells),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for RRROutcome {
@@ -89,126 +149,234 @@ impl fmt::Display for RRROutcome {
             RRROutcome::Draw => write!(f, "It's a draw!"),
             RRROutcome::XWins { .. } => write!(f, "X Wins!"),
             RRROutcome::OWins { .. } => write!(f, "O Wins!"),
+            RRROutcome::LossOnTime { loser } => {
+                write!(f, "{} ran out of time, {} Wins!", loser, loser.opponent())
+            }
+            RRROutcome::OrderWins { .. } => write!(f, "Order Wins!"),
+            RRROutcome::ChaosWins => write!(f, "Chaos Wins!"),
+            RRROutcome::Forfeit { loser } => {
+                write!(f, "{} forfeits, {} Wins!", loser, loser.opponent())
+            }
         }
     }
 }
 
-#[derive(Debug)]
-pub struct RRRBoard {
-    cells: HashMap<Coord, RRRCell>,
-    size: usize,
+/// What the players are trying to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+    /// Whoever completes a line loses
+    Misere,
+    /// Either player places X or O. Order, who plays X, wins by making a line
+    /// of either symbol, and Chaos, who plays O, by filling the board without
+    /// one.
+    OrderAndChaos,
+    /// The first player places odd numbers and the second even ones, each
+    /// only once, and whoever completes a line adding up to 15 wins
+    Numerical,
 }
 
-// Box drawing chars from https://en.wikipedia.org/wiki/Box-drawing_character
-impl fmt::Display for RRRBoard {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let size = self.get_size();
-        let indent: String = "    ".to_string();
+impl Variant {
+    pub fn all() -> &'static [Variant] {
+        &[
+            Variant::Standard,
+            Variant::Misere,
+            Variant::OrderAndChaos,
+            Variant::Numerical,
+        ]
+    }
 
-        // Write column numbers
-        let mut line = indent.clone();
-        for x in 0..size {
-            line.push(' ');
-            line.push_str(x.to_string().as_str());
-        }
-        writeln!(f, "{}", line)?;
-
-        for y in 0..size {
-            if y == 0 {
-                // Draw the top of the board
-                line = indent.clone();
-                line.push_str(self.render_board_top().as_str());
-                writeln!(f, "{}", line)?;
-            } else {
-                // Draw a row separator
-                line = indent.clone();
-                line.push_str(self.render_board_row_sep().as_str());
-                writeln!(f, "{}", line)?;
+    /// Number used for the variant in stats and solver table files
+    pub fn code(self) -> u8 {
+        match self {
+            Variant::Standard => 0,
+            Variant::Misere => 1,
+            Variant::OrderAndChaos => 2,
+            Variant::Numerical => 3,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Variant> {
+        Variant::all()
+            .iter()
+            .cloned()
+            .find(|variant| variant.code() == code)
+    }
+
+    /// Whether players choose what to place, rather than always placing
+    /// their own symbol
+    pub fn chooses_pieces(self) -> bool {
+        self == Variant::OrderAndChaos || self == Variant::Numerical
+    }
+
+    /// What `player` is called in this variant, if they have a role besides
+    /// their symbol
+    pub fn role(self, player: RRRCell) -> Option<&'static str> {
+        match (self, player) {
+            (Variant::OrderAndChaos, RRRCell::X) => Some("Order"),
+            (Variant::OrderAndChaos, RRRCell::O) => Some("Chaos"),
+            _ => None,
+        }
+    }
+
+    /// `player`'s role as well as their symbol, like "Order (X)", or just
+    /// their symbol where there aren't roles
+    pub fn player_label(self, player: RRRCell) -> String {
+        match self.role(player) {
+            Some(role) => format!("{} ({})", role, player),
+            None => player.to_string(),
+        }
+    }
+
+    /// Split a move like "Ob2", or "5b2" in numerical tic-tac-toe, into what
+    /// it places and the cell that's left
+    pub fn read_piece(self, text: &str) -> Option<(RRRCell, &str)> {
+        let piece = match (self, text.get(..1)?) {
+            (Variant::Numerical, digit) => {
+                RRRCell::Number(digit.parse().ok().filter(|&num| num > 0)?)
             }
+            (_, "X") | (_, "x") => RRRCell::X,
+            (_, "O") | (_, "o") => RRRCell::O,
+            _ => return None,
+        };
+        Some((piece, text[1..].trim_start()))
+    }
+}
 
-            // print row of board cells
-            // indent + row number
-            line = format!("{: >3} ", y);
-            line.push_str(self.render_board_row(y).as_str());
-            writeln!(f, "{}", line)?;
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variant::Standard => write!(f, "standard"),
+            Variant::Misere => write!(f, "misere"),
+            Variant::OrderAndChaos => write!(f, "order-and-chaos"),
+            Variant::Numerical => write!(f, "numerical"),
         }
+    }
+}
+
+impl FromStr for Variant {
+    type Err = Error;
 
-        // Draw the bottom of the board
-        line = indent.clone();
-        line.push_str(self.render_board_bottom().as_str());
-        writeln!(f, "{}", line)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_ascii_lowercase();
+        Variant::all()
+            .iter()
+            .cloned()
+            .find(|variant| variant.to_string() == name)
+            .ok_or_else(|| {
+                Error::BadInput(format!(
+                    "'{}' isn't a variant, try standard, misere, order-and-chaos, or numerical",
+                    s
+                ))
+            })
     }
 }
 
-impl RRRBoard {
-    pub fn render_board_top(&self) -> String {
-        // starting and ending char, a char for each board cell, and a char separating each board
-        // cell
-        let mut line = String::with_capacity(2 + (self.get_size() * 2) - 1);
-        for x in 0..self.get_size() {
-            if x == 0 {
-                line.push('╭');
-            } else {
-                line.push('┬');
-            }
-            line.push('─');
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RRRRules {
+    pub size: usize,
+    pub win_length: usize,
+    pub variant: Variant,
+}
+
+impl RRRRules {
+    /// Classic rules: a full row, column, or diagonal wins
+    pub fn standard(size: usize) -> Self {
+        RRRRules {
+            size,
+            win_length: size,
+            variant: Variant::Standard,
         }
-        line.push('╮');
-        line
     }
 
-    pub fn render_board_bottom(&self) -> String {
-        // starting and ending char, a char for each board cell, and a char separating each board
-        // cell
-        let mut line = String::with_capacity(2 + (self.get_size() * 2) - 1);
-        for x in 0..self.get_size() {
-            if x == 0 {
-                line.push('╰');
-            } else {
-                line.push('┴');
-            }
-            line.push('─');
+    /// Order and Chaos as it's usually played, five in a row on a 6x6 board
+    pub fn order_and_chaos() -> Self {
+        RRRRules {
+            size: 6,
+            win_length: 5,
+            variant: Variant::OrderAndChaos,
         }
-        line.push('╯');
-        line
     }
 
-    pub fn render_board_row(&self, y: usize) -> String {
-        // starting and ending char, a char for each board cell, and a char separating each board
-        // cell
-        let mut line = String::with_capacity(2 + (self.get_size() * 2) - 1);
-        for x in 0..self.get_size() {
-            line.push('│');
-            match self.fetch(&Coord { x, y }) {
-                Ok(RRRCell::X) => line.push('X'),
-                Ok(RRRCell::O) => line.push('O'),
-                Ok(RRRCell::Clear) => line.push(' '),
-                Err(_) => line.push('!'),
-            }
+    /// Numerical tic-tac-toe, which is only played on a 3x3 board
+    pub fn numerical() -> Self {
+        RRRRules {
+            variant: Variant::Numerical,
+            ..RRRRules::standard(3)
         }
-        line.push('│');
-        line
     }
 
-    pub fn render_board_row_sep(&self) -> String {
-        let mut line = String::with_capacity(2 + (self.get_size() * 2) - 1);
-        for x in 0..self.get_size() {
-            if x == 0 {
-                line.push('├');
-            } else {
-                line.push('┼');
-            }
-            line.push('─');
+    /// What a line has to add up to in numerical tic-tac-toe: 15 with the
+    /// numbers 1 to 9, as in a magic square
+    pub fn line_total(&self) -> u32 {
+        (self.win_length * (self.size * self.size + 1) / 2) as u32
+    }
+}
+
+impl Default for RRRRules {
+    fn default() -> Self {
+        RRRRules::standard(3)
+    }
+}
+
+impl fmt::Display for RRRRules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}x{}, {} in a row",
+            self.size, self.size, self.win_length
+        )?;
+        match self.variant {
+            Variant::Standard => Ok(()),
+            Variant::Misere => write!(f, ", misère"),
+            Variant::OrderAndChaos => write!(f, ", Order and Chaos"),
+            Variant::Numerical => write!(f, ", numerical"),
         }
-        line.push('┤');
-        line
     }
+}
 
+// SplitMix64 of the cell position and symbol, so the Zobrist keys are the same
+// from run to run without having to store a table of them
+fn zobrist_key(coord: &Coord, cell: RRRCell) -> u64 {
+    let symbol: u64 = match cell {
+        RRRCell::Clear => return 0,
+        RRRCell::X => 1,
+        RRRCell::O => 2,
+        RRRCell::Number(num) => 3 + num as u64,
+    };
+    // The symbol gets its own byte, so that numbers don't collide with the
+    // next cell along
+    let mut z = (((coord.y as u64) << 40) | ((coord.x as u64) << 8) | symbol)
+        .wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Clone)]
+pub struct RRRBoard {
+    cells: HashMap<Coord, RRRCell>,
+    rules: RRRRules,
+    // Zobrist hash of the current cells, kept up to date by set()
+    hash: u64,
+    // Who places the odd numbers in numerical tic-tac-toe: the first player
+    odds: RRRCell,
+}
+
+impl RRRBoard {
     pub fn get_size(&self) -> usize {
-        self.size
+        self.rules.size
+    }
+
+    pub fn on_board(&self, coord: &Coord) -> bool {
+        coord.x < self.get_size() && coord.y < self.get_size()
     }
 
     pub fn fetch(&self, coord: &Coord) -> Result<RRRCell, RRRError> {
+        if !self.on_board(coord) {
+            return Err(RRRError::InvalidCellPosition(*coord));
+        }
         match self.cells.get(coord) {
             Some(cell) => Ok(*cell),
             None => Err(RRRError::InvalidCellPosition(*coord)),
@@ -216,101 +384,228 @@ impl RRRBoard {
     }
 
     pub fn set(&mut self, coord: &Coord, new_state: RRRCell) -> Result<RRRCell, RRRError> {
-        if coord.x <= self.size && coord.y <= self.size {
-            self.cells.insert(*coord, new_state);
+        if self.on_board(coord) {
+            let old_state = self
+                .cells
+                .insert(*coord, new_state)
+                .unwrap_or(RRRCell::Clear);
+            self.hash ^= zobrist_key(coord, old_state) ^ zobrist_key(coord, new_state);
             Ok(new_state)
         } else {
             Err(RRRError::InvalidCellPosition(*coord))
         }
     }
 
+    pub fn empty_cells(&self) -> Vec<Coord> {
+        let mut cells: Vec<Coord> = self
+            .cells
+            .iter()
+            .filter(|(_, &cell)| cell == RRRCell::Clear)
+            .map(|(&coord, _)| coord)
+            .collect();
+        // HashMap iteration order isn't stable, and callers want repeatable results
+        cells.sort_by_key(|coord| (coord.y, coord.x));
+        cells
+    }
+
     pub fn make_move(&mut self, coord: &Coord, new_state: RRRCell) -> Result<RRRCell, RRRError> {
         // Block moves to cells that have already been used
         let cur_val = self.fetch(coord)?;
-        if cur_val == RRRCell::X || cur_val == RRRCell::O {
+        if cur_val != RRRCell::Clear {
             Err(RRRError::CellAlreadySet(*coord, cur_val))
         } else {
             self.set(coord, new_state)
         }
     }
 
-    // maybe_winning_cells is the sub-hashmap of the cells we should be considering
-    fn test_winner(&self, maybe_winning_cells: &HashMap<&Coord, &RRRCell>) -> Option<RRROutcome> {
-        assert!(maybe_winning_cells.len() == self.get_size());
-        let vals: HashSet<&RRRCell> = maybe_winning_cells.values().cloned().collect();
+    pub fn get_rules(&self) -> RRRRules {
+        self.rules
+    }
 
-        // If the set of all values being considered is exactly one, and it's not Clear
-        // then we have a winner
-        if vals.len() == 1 && !vals.contains(&RRRCell::Clear) {
-            match vals.iter().next() {
-                Some(&&RRRCell::X) => Some(RRROutcome::XWins {
-                    winning_cells: maybe_winning_cells.keys().cloned().cloned().collect(),
-                }),
-                Some(&&RRRCell::O) => Some(RRROutcome::OWins {
-                    winning_cells: maybe_winning_cells.keys().cloned().cloned().collect(),
+    /// What `player` can place on their turn
+    pub fn pieces(&self, player: RRRCell) -> Vec<RRRCell> {
+        match self.rules.variant {
+            Variant::OrderAndChaos => vec![RRRCell::X, RRRCell::O],
+            Variant::Numerical => {
+                let odd = player == self.odds;
+                (1..=self.get_size() * self.get_size())
+                    .filter(|num| (num % 2 == 1) == odd)
+                    .map(|num| RRRCell::Number(num as u8))
+                    .filter(|piece| !self.cells.values().any(|cell| cell == piece))
+                    .collect()
+            }
+            Variant::Standard | Variant::Misere => vec![player],
+        }
+    }
+
+    /// Who placed `piece`: X or O themselves, or for a number, whoever has
+    /// odd or even ones
+    pub fn owner(&self, piece: RRRCell) -> RRRCell {
+        match piece {
+            RRRCell::Number(num) if num % 2 == 1 => self.odds,
+            RRRCell::Number(_) => self.odds.opponent(),
+            other => other,
+        }
+    }
+
+    /// Hash that's the same for every rotation and reflection of this board,
+    /// so equivalent positions can share transposition table entries
+    pub fn canonical_hash(&self) -> u64 {
+        let size = self.get_size();
+        Symmetry::all()
+            .iter()
+            .map(|&sym| match sym {
+                Symmetry::Identity => self.hash,
+                _ => self.cells.iter().fold(0, |hash, (coord, &cell)| {
+                    hash ^ zobrist_key(&sym.apply(coord, size), cell)
                 }),
-                Some(&&RRRCell::Clear) => None,
-                None => None,
+            })
+            .min()
+            .unwrap_or(self.hash)
+    }
+
+    // Collect the run of cells matching `coord`'s cell that passes through `coord`
+    // in the direction (dx, dy), in both senses
+    fn run_through(&self, coord: &Coord, dx: isize, dy: isize) -> Vec<Coord> {
+        let cell = match self.fetch(coord) {
+            Ok(RRRCell::Clear) | Err(_) => return Vec::new(),
+            Ok(cell) => cell,
+        };
+        let step = |from: &Coord, sign: isize| -> Option<Coord> {
+            let x = from.x as isize + dx * sign;
+            let y = from.y as isize + dy * sign;
+            if x < 0 || y < 0 {
+                return None;
+            }
+            let next = Coord {
+                x: x as usize,
+                y: y as usize,
+            };
+            match self.fetch(&next) {
+                Ok(next_cell) if next_cell == cell => Some(next),
+                _ => None,
+            }
+        };
+
+        let mut run = vec![*coord];
+        for &sign in &[-1, 1] {
+            let mut cur = *coord;
+            while let Some(next) = step(&cur, sign) {
+                run.push(next);
+                cur = next;
             }
-        } else {
-            None
         }
+        run
     }
 
-    #[allow(dead_code)]
-    pub fn outcome(&self) -> Option<RRROutcome> {
-        // look for winners
-        let mut maybe_winning_cells;
-
-        // TODO: Recognize when there's more than winning path
-        // start with the rows and columns
-        for boardslice in 0..self.get_size() {
-            maybe_winning_cells = self
-                .cells
+    // Every cell on the board in line with `coord` in the direction (dx, dy),
+    // in order
+    fn line_through(&self, coord: &Coord, dx: isize, dy: isize) -> Vec<Coord> {
+        let size = self.get_size() as isize;
+        let (mut x, mut y) = (coord.x as isize, coord.y as isize);
+        while (0..size).contains(&(x - dx)) && (0..size).contains(&(y - dy)) {
+            x -= dx;
+            y -= dy;
+        }
+        let mut line = Vec::new();
+        while (0..size).contains(&x) && (0..size).contains(&y) {
+            line.push(Coord {
+                x: x as usize,
+                y: y as usize,
+            });
+            x += dx;
+            y += dy;
+        }
+        line
+    }
+
+    // The win_length numbers in a row through `coord` in the direction
+    // (dx, dy) that add up to the line total, if there are any
+    fn sum_run_through(&self, coord: &Coord, dx: isize, dy: isize) -> Vec<Coord> {
+        let line = self.line_through(coord, dx, dy);
+        let length = self.rules.win_length;
+        let at = match line.iter().position(|cell| cell == coord) {
+            Some(at) => at,
+            None => return Vec::new(),
+        };
+        for start in at.saturating_sub(length - 1)..=at {
+            let window = match line.get(start..start + length) {
+                Some(window) => window,
+                None => break,
+            };
+            let total: Option<u32> = window
                 .iter()
-                .filter(|(&coord, &_)| coord.x == boardslice)
-                .collect();
-            if let Some(winning_column) = self.test_winner(&maybe_winning_cells) {
-                return Some(winning_column);
+                .map(|cell| match self.fetch(cell) {
+                    Ok(RRRCell::Number(num)) => Some(num as u32),
+                    _ => None,
+                })
+                .sum();
+            if total == Some(self.rules.line_total()) {
+                return window.to_vec();
             }
-            maybe_winning_cells.clear();
+        }
+        Vec::new()
+    }
 
-            maybe_winning_cells = self
-                .cells
-                .iter()
-                .filter(|&(&coord, &_)| coord.y == boardslice)
-                .collect();
-            if let Some(winning_row) = self.test_winner(&maybe_winning_cells) {
-                return Some(winning_row);
+    // Find a winning line running through `coord`: at least win_length
+    // matching cells, or in numerical tic-tac-toe, numbers with the right total
+    fn winning_line_through(&self, coord: &Coord) -> Option<Vec<Coord>> {
+        for &(dx, dy) in &[(1, 0), (0, 1), (1, 1), (1, -1)] {
+            let run = match self.rules.variant {
+                Variant::Numerical => self.sum_run_through(coord, dx, dy),
+                _ => self.run_through(coord, dx, dy),
+            };
+            if run.len() >= self.rules.win_length {
+                return Some(run);
             }
-            maybe_winning_cells.clear()
         }
-        // positive slope diagonal
-        maybe_winning_cells = self
-            .cells
-            .iter()
-            .filter(|&(&coord, &_)| coord.x == coord.y)
-            .collect();
-        if let Some(winning_positive_diagonal) = self.test_winner(&maybe_winning_cells) {
-            return Some(winning_positive_diagonal);
+        None
+    }
+
+    // Turn a line completed by playing in `coord` into an outcome,
+    // respecting the variant
+    fn line_outcome(&self, coord: &Coord, line: Vec<Coord>) -> Option<RRROutcome> {
+        let mut maker = self.owner(self.fetch(coord).ok()?);
+        match self.rules.variant {
+            Variant::Standard | Variant::Numerical => (),
+            Variant::Misere => maker = maker.opponent(),
+            Variant::OrderAndChaos => {
+                return Some(RRROutcome::OrderWins {
+                    winning_cells: line,
+                })
+            }
+        }
+        match maker {
+            RRRCell::X => Some(RRROutcome::XWins {
+                winning_cells: line,
+            }),
+            RRRCell::O => Some(RRROutcome::OWins {
+                winning_cells: line,
+            }),
+            RRRCell::Clear | RRRCell::Number(_) => None,
         }
-        maybe_winning_cells.clear();
+    }
 
-        // negative slope diagonal
-        maybe_winning_cells = self
-            .cells
-            .iter()
-            .filter(|&(&coord, &_)| coord.x == self.get_size() - coord.y - 1)
-            .collect();
-        if let Some(winning_negative_diagonal) = self.test_winner(&maybe_winning_cells) {
-            return Some(winning_negative_diagonal);
+    fn is_full(&self) -> bool {
+        !self.cells.values().any(|&x| x == RRRCell::Clear)
+    }
+
+    // How the game ends when the board fills up without a line
+    fn full_outcome(&self) -> RRROutcome {
+        match self.rules.variant {
+            Variant::OrderAndChaos => RRROutcome::ChaosWins,
+            _ => RRROutcome::Draw,
         }
-        maybe_winning_cells.clear();
+    }
 
-        // no winners, look for draw
-        // If no cell is RRRCell::Clear, it's not a draw yet
-        if !self.cells.values().any(|&x| x == RRRCell::Clear) {
-            Some(RRROutcome::Draw)
+    /// How the game has ended, if it has, when only `coord` has changed since
+    /// the board was last known to be undecided. Only the player who moved
+    /// there can have completed a line.
+    pub fn outcome_after(&self, coord: &Coord) -> Option<RRROutcome> {
+        if let Some(line) = self.winning_line_through(coord) {
+            self.line_outcome(coord, line)
+        } else if self.is_full() {
+            Some(self.full_outcome())
         } else {
             None
         }
@@ -319,35 +614,366 @@ impl RRRBoard {
     pub fn init(&mut self) {
         // reset the board
         self.cells.clear();
-        self.cells.reserve((self.size * self.size) as usize);
+        self.hash = 0;
+        let size = self.get_size();
+        self.cells.reserve(size * size);
 
-        for x in 0..self.size {
-            for y in 0..self.size {
+        for x in 0..size {
+            for y in 0..size {
                 // Sure, go ahead and panic - I can't see how this could possibly fail
                 self.set(&Coord { x, y }, RRRCell::Clear).unwrap();
             }
         }
     }
 
-    pub fn new_anysize(size: usize) -> Self {
+    pub fn new_with_rules(rules: RRRRules) -> Self {
         let mut _self = RRRBoard {
             cells: HashMap::new(),
-            size,
+            rules,
+            hash: 0,
+            odds: RRRCell::X,
         };
         _self.init();
         _self
     }
 
+    pub fn new_anysize(size: usize) -> Self {
+        RRRBoard::new_with_rules(RRRRules::standard(size))
+    }
+
     #[allow(dead_code)]
     pub fn new() -> Self {
         RRRBoard::new_anysize(3)
     }
 }
 
+/// How long each player gets to think
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeControl {
+    /// `total` for the whole game, with `increment` added after each move
+    Fischer {
+        total: Duration,
+        increment: Duration,
+    },
+    /// The same time for every move, with nothing carried over
+    PerMove(Duration),
+}
+
+impl TimeControl {
+    // Time on a player's clock before their first move
+    fn starting_time(self) -> Duration {
+        match self {
+            TimeControl::Fischer { total, .. } => total,
+            TimeControl::PerMove(per_move) => per_move,
+        }
+    }
+}
+
+impl fmt::Display for TimeControl {
+    /// In seconds, like PGN: "300+5" for 5 minutes plus 5 seconds a move, or
+    /// "10/move"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TimeControl::Fischer { total, increment } => {
+                write!(f, "{}+{}", total.as_secs(), increment.as_secs())
+            }
+            TimeControl::PerMove(per_move) => write!(f, "{}/move", per_move.as_secs()),
+        }
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || {
+            Error::BadInput(format!(
+                "'{}' isn't a time limit, try e.g. 300+5 or 10/move",
+                s
+            ))
+        };
+        let seconds = |text: &str| match text.trim().parse() {
+            Ok(0) | Err(_) => Err(bad()),
+            Ok(secs) => Ok(Duration::from_secs(secs)),
+        };
+        if let Some(per_move) = s.strip_suffix("/move") {
+            return Ok(TimeControl::PerMove(seconds(per_move)?));
+        }
+        let (total, increment) = s.split_once('+').unwrap_or((s, "0"));
+        Ok(TimeControl::Fischer {
+            total: seconds(total)?,
+            increment: Duration::from_secs(increment.trim().parse().map_err(|_| bad())?),
+        })
+    }
+}
+
+/// Rules for the start of a game, to make up for the advantage of moving
+/// first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Opening {
+    #[default]
+    Standard,
+    /// After the first move, the second player picks which side to play
+    Swap,
+    /// The first player places three stones, X, O, X if X starts. The second
+    /// player then picks a side, or places two more, one of each, and lets
+    /// the first player pick.
+    Swap2,
+    /// The first move can't be in the middle NxN of the board
+    OffCentre(usize),
+}
+
+impl Opening {
+    /// Whether there's anywhere left to move under these rules
+    pub fn fits(self, rules: &RRRRules) -> bool {
+        match self {
+            Opening::OffCentre(middle) => middle > 0 && middle < rules.size,
+            _ => true,
+        }
+    }
+
+    /// Whether the rule keeps a stone out of `coord` on `board`: only the
+    /// first of the game, under the off-centre rule
+    pub fn forbids(self, board: &RRRBoard, coord: &Coord) -> bool {
+        match self {
+            Opening::OffCentre(middle) => {
+                let size = board.get_size();
+                board.empty_cells().len() == size * size && in_middle(coord, size, middle)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Opening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Opening::Standard => write!(f, "standard"),
+            Opening::Swap => write!(f, "swap"),
+            Opening::Swap2 => write!(f, "swap2"),
+            Opening::OffCentre(middle) => write!(f, "off-centre-{}", middle),
+        }
+    }
+}
+
+impl FromStr for Opening {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || {
+            Error::BadInput(format!(
+                "'{}' isn't an opening rule, try standard, swap, swap2, or off-centre-3",
+                s
+            ))
+        };
+        match s.trim().to_ascii_lowercase().as_str() {
+            "standard" => Ok(Opening::Standard),
+            "swap" => Ok(Opening::Swap),
+            "swap2" => Ok(Opening::Swap2),
+            other => {
+                let middle = other
+                    .strip_prefix("off-centre-")
+                    .or_else(|| other.strip_prefix("off-center-"))
+                    .ok_or_else(bad)?;
+                match middle.parse() {
+                    Ok(0) | Err(_) => Err(bad()),
+                    Ok(middle) => Ok(Opening::OffCentre(middle)),
+                }
+            }
+        }
+    }
+}
+
+/// How many stones a player places each turn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StonesPerTurn {
+    /// On the first turn of the game
+    pub first: usize,
+    /// On every turn after that
+    pub then: usize,
+}
+
+impl StonesPerTurn {
+    /// One stone on the first turn and two on every one after, as in
+    /// Connect6, so neither side is ever a stone ahead for long
+    pub fn connect6() -> Self {
+        StonesPerTurn { first: 1, then: 2 }
+    }
+
+    /// Whether it's the usual one stone a turn
+    pub fn is_single(self) -> bool {
+        self.first == 1 && self.then == 1
+    }
+}
+
+impl Default for StonesPerTurn {
+    fn default() -> Self {
+        StonesPerTurn { first: 1, then: 1 }
+    }
+}
+
+impl fmt::Display for StonesPerTurn {
+    /// "2" for two a turn, or "1,2" for one on the first turn and two after
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.first == self.then {
+            write!(f, "{}", self.then)
+        } else {
+            write!(f, "{},{}", self.first, self.then)
+        }
+    }
+}
+
+impl FromStr for StonesPerTurn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || {
+            Error::BadInput(format!(
+                "'{}' isn't a number of stones a turn, try e.g. 2, 1,2 or connect6",
+                s
+            ))
+        };
+        let stones = |text: &str| match text.trim().parse() {
+            Ok(0) | Err(_) => Err(bad()),
+            Ok(stones) => Ok(stones),
+        };
+        if s.trim().eq_ignore_ascii_case("connect6") {
+            return Ok(StonesPerTurn::connect6());
+        }
+        match s.split_once(',') {
+            Some((first, then)) => Ok(StonesPerTurn {
+                first: stones(first)?,
+                then: stones(then)?,
+            }),
+            None => {
+                let each = stones(s)?;
+                Ok(StonesPerTurn {
+                    first: each,
+                    then: each,
+                })
+            }
+        }
+    }
+}
+
+/// A choice of sides that's due. People are named by the side they started
+/// out on, which is only different from the one they're playing after a
+/// swap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SwapOffer {
+    pub chooser: RRRCell,
+    /// Whether they can place two more stones and let the other player pick
+    /// instead
+    pub can_place_two: bool,
+}
+
+/// What someone offered a swap does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwapAnswer {
+    /// Play this side from now on
+    Play(RRRCell),
+    PlaceTwo,
+}
+
+impl fmt::Display for SwapAnswer {
+    /// The side picked, or "place-two"
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SwapAnswer::Play(side) => write!(f, "{}", side),
+            SwapAnswer::PlaceTwo => write!(f, "place-two"),
+        }
+    }
+}
+
+impl FromStr for SwapAnswer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "x" => Ok(SwapAnswer::Play(RRRCell::X)),
+            "o" => Ok(SwapAnswer::Play(RRRCell::O)),
+            "place-two" => Ok(SwapAnswer::PlaceTwo),
+            _ => Err(Error::BadInput(format!(
+                "'{}' isn't an answer to a swap, try X, O, or place-two",
+                s
+            ))),
+        }
+    }
+}
+
+// A swap offer due to the first player, or else the second
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Offer {
+    to_first: bool,
+    can_place_two: bool,
+}
+
+// How far through its opening rule a game is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpeningStage {
+    // The first player (or else the second) places every stone until there
+    // are `until` on the board, and then `offer` is made
+    Placing {
+        by_first: bool,
+        until: usize,
+        offer: Offer,
+    },
+    Offered(Offer),
+    Done,
+}
+
+// Is `coord` in the middle `middle`x`middle` cells of a board `size` across?
+fn in_middle(coord: &Coord, size: usize, middle: usize) -> bool {
+    let start = size.saturating_sub(middle) / 2;
+    let range = start..start + middle;
+    range.contains(&coord.x) && range.contains(&coord.y)
+}
+
+/// Time on a clock as minutes and seconds, rounded up so it only shows 0:00
+/// once it's run out
+pub fn format_clock(time: Duration) -> String {
+    let secs = time.as_millis().div_ceil(1000);
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Each player's clock in a timed game
+#[derive(Debug, Clone)]
+pub struct Clocks {
+    pub control: TimeControl,
+    // X's time, then O's
+    remaining: [Duration; 2],
+    // When the player to move started thinking, while a clock is running
+    started: Option<Instant>,
+}
+
+fn clock_index(player: RRRCell) -> usize {
+    match player {
+        RRRCell::O => 1,
+        _ => 0,
+    }
+}
+
+#[derive(Clone)]
 pub struct RRRGame {
     pub board: RRRBoard,
     pub player: RRRCell,
     pub outcome: Option<RRROutcome>,
+    pub last_move: Option<Coord>,
+    /// Who moved first
+    pub first: RRRCell,
+    /// Every move so far, in order
+    pub moves: Vec<Coord>,
+    /// None if there's no time limit
+    pub clocks: Option<Clocks>,
+    pub opening: Opening,
+    /// Every answer to a swap offer so far, in order
+    pub swap_answers: Vec<SwapAnswer>,
+    pub stones: StonesPerTurn,
+    // Stones the player to move still has to place this turn
+    stones_left: usize,
+    opening_stage: OpeningStage,
+    // Whether the players have swapped sides
+    swapped: bool,
 }
 
 impl RRRGame {
@@ -360,7 +986,9 @@ impl RRRGame {
         self.player
     }
 
+    /// Hand the turn over, with a full turn's worth of stones to place
     pub fn next_player(&mut self) -> Result<RRRCell, RRRError> {
+        self.stones_left = self.stones.then;
         match self.player {
             RRRCell::X => {
                 self.player = RRRCell::O;
@@ -370,16 +998,51 @@ impl RRRGame {
                 self.player = RRRCell::X;
                 Ok(self.player)
             }
-            RRRCell::Clear => Err(RRRError::NoActivePlayer),
+            RRRCell::Clear | RRRCell::Number(_) => Err(RRRError::NoActivePlayer),
         }
     }
 
+    /// Play the side to move's own symbol in `coord`
     pub fn take_turn(&mut self, coord: &Coord) -> Result<RRRCell, RRRError> {
-        match self.board.make_move(coord, self.player) {
+        self.place(coord, self.player)
+    }
+
+    /// Play `piece` in `coord` for the side to move, where the variant lets
+    /// them choose what to place
+    pub fn place(&mut self, coord: &Coord, piece: RRRCell) -> Result<RRRCell, RRRError> {
+        self.check_clock();
+        if let Some(RRROutcome::LossOnTime { loser }) = self.outcome {
+            return Err(RRRError::OutOfTime(loser));
+        }
+        if let OpeningStage::Offered(_) = self.opening_stage {
+            return Err(RRRError::SwapPending);
+        }
+        if let Opening::OffCentre(middle) = self.opening {
+            if self.opening.forbids(&self.board, coord) {
+                return Err(RRRError::RestrictedOpening(middle));
+            }
+        }
+        if !self.board.pieces(self.player).contains(&piece) {
+            return Err(RRRError::WrongPiece(self.player, piece));
+        }
+        match self.board.make_move(coord, piece) {
             Ok(_) => {
-                self.outcome = self.board.outcome();
-                if !self.over() {
+                self.last_move = Some(*coord);
+                self.moves.push(*coord);
+                self.outcome = self.board.outcome_after(coord);
+                if let OpeningStage::Placing { until, offer, .. } = self.opening_stage {
+                    if self.moves.len() >= until {
+                        self.opening_stage = OpeningStage::Offered(offer);
+                    }
+                }
+                // The clock only stops once the whole turn has been played
+                self.stones_left = self.stones_left.saturating_sub(1);
+                if self.stones_left == 0 || self.over() {
+                    self.stop_clock();
+                }
+                if !self.over() && self.stones_left == 0 {
                     self.next_player().unwrap();
+                    self.start_clock();
                 }
                 Ok(self.player)
             }
@@ -401,40 +1064,579 @@ impl RRRGame {
         self.outcome.is_some()
     }
 
-    pub fn new_anysize(size: usize) -> Self {
+    /// Stones the player to move still has to place before the other side's
+    /// turn
+    pub fn stones_left(&self) -> usize {
+        self.stones_left
+    }
+
+    /// Whether the player to move has already placed some of this turn's
+    /// stones
+    pub fn mid_turn(&self) -> bool {
+        let this_turn = if self.moves.len() < self.stones.first {
+            self.stones.first
+        } else {
+            self.stones.then
+        };
+        self.stones_left < this_turn
+    }
+
+    /// Place `stones` a turn instead of one
+    pub fn with_stones(mut self, stones: StonesPerTurn) -> Self {
+        self.stones = stones;
+        self.stones_left = stones.first;
+        self
+    }
+
+    /// Play under an opening rule
+    pub fn with_opening(mut self, opening: Opening) -> Self {
+        let placing = |until, can_place_two| OpeningStage::Placing {
+            by_first: true,
+            until,
+            offer: Offer {
+                to_first: false,
+                can_place_two,
+            },
+        };
+        self.opening = opening;
+        self.opening_stage = match opening {
+            Opening::Swap => placing(1, false),
+            Opening::Swap2 => placing(3, true),
+            Opening::Standard | Opening::OffCentre(_) => OpeningStage::Done,
+        };
+        self
+    }
+
+    // The player who moved first, or else the second, named by the side they
+    // started on
+    fn seat(&self, first: bool) -> RRRCell {
+        if first {
+            self.first
+        } else {
+            self.first.opponent()
+        }
+    }
+
+    /// Who's playing `side`, named by the side they started on
+    pub fn seat_of(&self, side: RRRCell) -> RRRCell {
+        if self.swapped {
+            side.opponent()
+        } else {
+            side
+        }
+    }
+
+    /// "first" or "second", for someone named by the side they started on
+    pub fn seat_name(&self, seat: RRRCell) -> &'static str {
+        if seat == self.first {
+            "first"
+        } else {
+            "second"
+        }
+    }
+
+    /// The side `seat`, named by the side they started on, is playing now
+    pub fn side_of(&self, seat: RRRCell) -> RRRCell {
+        // Swapping is its own inverse
+        self.seat_of(seat)
+    }
+
+    /// Who has to do something next, named by the side they started on:
+    /// move, place a stone for the opening, or pick a side
+    pub fn seat_to_move(&self) -> RRRCell {
+        match self.opening_stage {
+            OpeningStage::Placing { by_first, .. } => self.seat(by_first),
+            OpeningStage::Offered(offer) => self.seat(offer.to_first),
+            OpeningStage::Done => self.seat_of(self.player),
+        }
+    }
+
+    /// Whether one player is still placing stones for both sides, before a
+    /// swap offer
+    pub fn placing_opening(&self) -> bool {
+        matches!(self.opening_stage, OpeningStage::Placing { .. })
+    }
+
+    /// The choice of sides that has to be made before anyone moves, if any
+    pub fn swap_offer(&self) -> Option<SwapOffer> {
+        match self.opening_stage {
+            OpeningStage::Offered(offer) if !self.over() => Some(SwapOffer {
+                chooser: self.seat(offer.to_first),
+                can_place_two: offer.can_place_two,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn answer_swap(&mut self, answer: SwapAnswer) -> Result<(), RRRError> {
+        let offer = match self.swap_offer() {
+            Some(offer) => offer,
+            None => return Err(RRRError::NotOffered),
+        };
+        let to_first = offer.chooser == self.first;
+        self.opening_stage = match answer {
+            SwapAnswer::Play(RRRCell::Clear) => return Err(RRRError::NotOffered),
+            SwapAnswer::Play(side) => {
+                self.swapped = side != offer.chooser;
+                OpeningStage::Done
+            }
+            SwapAnswer::PlaceTwo if offer.can_place_two => OpeningStage::Placing {
+                by_first: to_first,
+                until: self.moves.len() + 2,
+                offer: Offer {
+                    to_first: !to_first,
+                    can_place_two: false,
+                },
+            },
+            SwapAnswer::PlaceTwo => return Err(RRRError::NotOffered),
+        };
+        self.swap_answers.push(answer);
+        Ok(())
+    }
+
+    /// Play with a time limit. The first player's clock starts right away.
+    pub fn with_time_control(mut self, control: TimeControl) -> Self {
+        self.clocks = Some(Clocks {
+            control,
+            remaining: [control.starting_time(); 2],
+            started: None,
+        });
+        self.start_clock();
+        self
+    }
+
+    /// Set the time left on X's and O's clocks, e.g. to carry on a saved game
+    pub fn with_time_left(mut self, time_left: [Duration; 2]) -> Self {
+        if let Some(ref mut clocks) = self.clocks {
+            clocks.remaining = time_left;
+        }
+        self
+    }
+
+    fn start_clock(&mut self) {
+        if let Some(ref mut clocks) = self.clocks {
+            clocks.started = Some(Instant::now());
+        }
+    }
+
+    // Charge the player to move for the time they took, and give them their
+    // increment or next move's time
+    fn stop_clock(&mut self) {
+        let index = clock_index(self.player);
+        if let Some(ref mut clocks) = self.clocks {
+            if let Some(started) = clocks.started.take() {
+                let remaining = clocks.remaining[index].saturating_sub(started.elapsed());
+                clocks.remaining[index] = match clocks.control {
+                    TimeControl::Fischer { increment, .. } => remaining + increment,
+                    TimeControl::PerMove(per_move) => per_move,
+                };
+            }
+        }
+    }
+
+    /// Time left on `player`'s clock, counting down while it's their move, or
+    /// None if the game isn't timed
+    pub fn time_left(&self, player: RRRCell) -> Option<Duration> {
+        let clocks = self.clocks.as_ref()?;
+        let remaining = clocks.remaining[clock_index(player)];
+        match clocks.started {
+            Some(started) if player == self.player => {
+                Some(remaining.saturating_sub(started.elapsed()))
+            }
+            _ => Some(remaining),
+        }
+    }
+
+    /// End the game if the player to move has run out of time. Returns
+    /// whether the game is over.
+    pub fn check_clock(&mut self) -> bool {
+        if !self.over() && self.time_left(self.player) == Some(Duration::from_secs(0)) {
+            self.outcome = Some(RRROutcome::LossOnTime { loser: self.player });
+            if let Some(ref mut clocks) = self.clocks {
+                clocks.remaining[clock_index(self.player)] = Duration::from_secs(0);
+                clocks.started = None;
+            }
+        }
+        self.over()
+    }
+
+    pub fn new_with_rules(rules: RRRRules) -> Self {
         RRRGame {
-            board: RRRBoard::new_anysize(size),
+            board: RRRBoard::new_with_rules(rules),
             player: RRRCell::X,
             outcome: None,
+            last_move: None,
+            first: RRRCell::X,
+            moves: Vec::new(),
+            clocks: None,
+            opening: Opening::Standard,
+            swap_answers: Vec::new(),
+            stones: StonesPerTurn::default(),
+            stones_left: 1,
+            opening_stage: OpeningStage::Done,
+            swapped: false,
         }
     }
 
+    /// Have `player` move first instead of X, e.g. to take turns starting in a
+    /// rematch
+    pub fn starting_with(mut self, player: RRRCell) -> Self {
+        self.player = player;
+        self.first = player;
+        self.board.odds = player;
+        self
+    }
+
+    /// Who moves first in a rematch: the players take turns, except in Order
+    /// and Chaos, where Order always starts
+    pub fn rematch_first(&self) -> RRRCell {
+        match self.board.get_rules().variant {
+            Variant::OrderAndChaos => RRRCell::X,
+            _ => self.first.opponent(),
+        }
+    }
+
+    pub fn new_anysize(size: usize) -> Self {
+        RRRGame::new_with_rules(RRRRules::standard(size))
+    }
+
     #[allow(dead_code)]
     pub fn new() -> Self {
         RRRGame::new_anysize(3)
     }
 }
 
-#[allow(dead_code)]
-fn main() {
-    let mut board = RRRBoard::new_anysize(6);
-    // TODO: Right now, just being lazy and allowing errors to panic
-    println!("Starting board:\n{}", board);
-    board.make_move(&Coord { x: 0, y: 0 }, RRRCell::X).unwrap();
-    println!("After single move:\n{}", board);
-    board.make_move(&Coord { x: 0, y: 1 }, RRRCell::X).unwrap();
-    println!("After next move:\n{}", board);
-    board.make_move(&Coord { x: 2, y: 2 }, RRRCell::X).unwrap();
-    println!("After next move:\n{}", board);
-    match board.make_move(&Coord { x: 2, y: 2 }, RRRCell::O) {
-        Ok(_) => println!("Well, that worked."),
-        Err(e) => println!("Something went wrong: {}", e),
-    }
-    println!("After next move:\n{}", board);
-    match board.outcome() {
-        Some(RRROutcome::XWins { .. }) => println!("X Wins!"),
-        Some(RRROutcome::OWins { .. }) => println!("O Wins!"),
-        Some(RRROutcome::Draw) => println!("It's a draw!"),
-        None => {}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Play `moves` in order, as x,y counting from 0
+    fn play(rules: RRRRules, moves: &[(usize, usize)]) -> RRRGame {
+        let mut game = RRRGame::new_with_rules(rules);
+        for &(x, y) in moves {
+            game.take_turn(&Coord { x, y }).unwrap();
+        }
+        game
+    }
+
+    // Place pieces for moves like "Oa1", where the variant lets players choose
+    fn place_all(rules: RRRRules, moves: &[&str]) -> RRRGame {
+        let mut game = RRRGame::new_with_rules(rules);
+        for text in moves {
+            let (piece, coord) = rules.variant.read_piece(text).unwrap();
+            game.place(&coord.parse().unwrap(), piece).unwrap();
+        }
+        game
+    }
+
+    fn winner(game: &RRRGame) -> Option<RRRCell> {
+        game.outcome.as_ref().and_then(RRROutcome::winner)
+    }
+
+    #[test]
+    fn a_full_row_wins() {
+        let game = play(
+            RRRRules::default(),
+            &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)],
+        );
+        assert_eq!(winner(&game), Some(RRRCell::X));
+        match game.outcome {
+            Some(RRROutcome::XWins { winning_cells }) => assert_eq!(winning_cells.len(), 3),
+            other => panic!("expected X to win, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn a_full_board_without_a_line_is_a_draw() {
+        let game = play(
+            RRRRules::default(),
+            &[
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (1, 1),
+                (0, 1),
+                (2, 1),
+                (1, 2),
+                (0, 2),
+                (2, 2),
+            ],
+        );
+        assert_eq!(game.outcome, Some(RRROutcome::Draw));
+    }
+
+    #[test]
+    fn shorter_lines_win_on_bigger_boards() {
+        let rules = RRRRules {
+            win_length: 3,
+            ..RRRRules::standard(4)
+        };
+        // A diagonal that doesn't start in a corner
+        let game = play(rules, &[(1, 0), (0, 0), (2, 1), (0, 1), (3, 2)]);
+        assert_eq!(winner(&game), Some(RRRCell::X));
+        let game = play(rules, &[(0, 0), (1, 0), (2, 0)]);
+        assert!(!game.over());
+    }
+
+    #[test]
+    fn completing_a_line_loses_in_misere() {
+        let rules = RRRRules {
+            variant: Variant::Misere,
+            ..RRRRules::default()
+        };
+        let game = play(rules, &[(0, 0), (0, 1), (1, 0), (1, 1), (2, 0)]);
+        assert_eq!(winner(&game), Some(RRRCell::O));
+    }
+
+    #[test]
+    fn rotations_and_reflections_hash_the_same() {
+        let corner = play(RRRRules::default(), &[(0, 0), (1, 1)]);
+        let other_corner = play(RRRRules::default(), &[(2, 2), (1, 1)]);
+        let edge = play(RRRRules::default(), &[(1, 0), (1, 1)]);
+        assert_eq!(
+            corner.board.canonical_hash(),
+            other_corner.board.canonical_hash()
+        );
+        assert_ne!(corner.board.canonical_hash(), edge.board.canonical_hash());
+    }
+
+    #[test]
+    fn hashes_follow_the_cells() {
+        let mut board = RRRBoard::new_with_rules(RRRRules::default());
+        let empty = board.hash;
+        board.make_move(&Coord { x: 2, y: 1 }, RRRCell::X).unwrap();
+        assert_ne!(board.hash, empty);
+        board.set(&Coord { x: 2, y: 1 }, RRRCell::Clear).unwrap();
+        assert_eq!(board.hash, empty);
+    }
+
+    #[test]
+    fn played_cells_cant_be_played_again() {
+        let mut game = play(RRRRules::default(), &[(1, 1)]);
+        assert!(matches!(
+            game.take_turn(&Coord { x: 1, y: 1 }),
+            Err(RRRError::CellAlreadySet(..))
+        ));
+        assert_eq!(game.get_turn(), RRRCell::O);
+    }
+
+    #[test]
+    fn either_side_can_start() {
+        let mut game = RRRGame::new().starting_with(RRRCell::O);
+        assert_eq!(game.get_turn(), RRRCell::O);
+        game.take_turn(&Coord { x: 1, y: 1 }).unwrap();
+        assert_eq!(game.board.fetch(&Coord { x: 1, y: 1 }).unwrap(), RRRCell::O);
+        assert_eq!(game.get_turn(), RRRCell::X);
+    }
+
+    #[test]
+    fn order_starts_every_rematch() {
+        let game = RRRGame::new();
+        assert_eq!(game.rematch_first(), RRRCell::O);
+        assert_eq!(game.starting_with(RRRCell::O).rematch_first(), RRRCell::X);
+        let game = RRRGame::new_with_rules(RRRRules::order_and_chaos());
+        assert_eq!(game.rematch_first(), RRRCell::X);
+    }
+
+    #[test]
+    fn time_controls_parse() {
+        assert_eq!(
+            "300+5".parse::<TimeControl>().unwrap(),
+            TimeControl::Fischer {
+                total: Duration::from_secs(300),
+                increment: Duration::from_secs(5),
+            }
+        );
+        assert_eq!(
+            "10/move".parse::<TimeControl>().unwrap(),
+            TimeControl::PerMove(Duration::from_secs(10))
+        );
+        assert!("0/move".parse::<TimeControl>().is_err());
+        assert!("soon".parse::<TimeControl>().is_err());
+    }
+
+    #[test]
+    fn clocks_gain_their_increment_and_run_out() {
+        let mut game = RRRGame::new().with_time_control("60+5".parse().unwrap());
+        game.take_turn(&Coord { x: 1, y: 1 }).unwrap();
+        assert_eq!(format_clock(game.time_left(RRRCell::X).unwrap()), "1:05");
+        assert_eq!(format_clock(game.time_left(RRRCell::O).unwrap()), "1:00");
+
+        let mut game =
+            RRRGame::new().with_time_control(TimeControl::PerMove(Duration::from_secs(0)));
+        assert!(game.check_clock());
+        assert_eq!(
+            game.outcome,
+            Some(RRROutcome::LossOnTime { loser: RRRCell::X })
+        );
+        assert!(matches!(
+            game.take_turn(&Coord { x: 1, y: 1 }),
+            Err(RRRError::OutOfTime(RRRCell::X))
+        ));
+    }
+
+    #[test]
+    fn openings_parse_from_their_names() {
+        for opening in &[
+            Opening::Standard,
+            Opening::Swap,
+            Opening::Swap2,
+            Opening::OffCentre(3),
+        ] {
+            assert_eq!(opening.to_string().parse::<Opening>().unwrap(), *opening);
+        }
+        assert_eq!(
+            "off-center-2".parse::<Opening>().unwrap(),
+            Opening::OffCentre(2)
+        );
+        for bad in &["swap3", "off-centre-", "off-centre-0", "off-centre-x"] {
+            assert!(matches!(bad.parse::<Opening>(), Err(Error::BadInput(_))));
+        }
+    }
+
+    #[test]
+    fn off_centre_keeps_only_the_first_stone_out_of_the_middle() {
+        let mut game =
+            RRRGame::new_with_rules(RRRRules::standard(5)).with_opening(Opening::OffCentre(3));
+        let middle = Coord { x: 2, y: 2 };
+        assert!(game.opening.forbids(&game.board, &middle));
+        assert!(matches!(
+            game.take_turn(&Coord { x: 1, y: 3 }),
+            Err(RRRError::RestrictedOpening(3))
+        ));
+        assert!(!game.opening.forbids(&game.board, &Coord { x: 0, y: 0 }));
+        game.take_turn(&Coord { x: 0, y: 0 }).unwrap();
+        assert!(!game.opening.forbids(&game.board, &middle));
+        game.take_turn(&middle).unwrap();
+    }
+
+    #[test]
+    fn swapping_sides_after_the_first_move() {
+        let mut game = RRRGame::new().with_opening(Opening::Swap);
+        game.take_turn(&Coord { x: 1, y: 1 }).unwrap();
+        let offer = game.swap_offer().unwrap();
+        assert_eq!(offer.chooser, RRRCell::O);
+        assert!(!offer.can_place_two);
+        assert!(matches!(
+            game.take_turn(&Coord { x: 0, y: 0 }),
+            Err(RRRError::SwapPending)
+        ));
+        assert!(game.answer_swap(SwapAnswer::PlaceTwo).is_err());
+
+        // The second player takes X, so the first player moves next as O
+        game.answer_swap(SwapAnswer::Play(RRRCell::X)).unwrap();
+        assert_eq!(game.swap_offer(), None);
+        assert_eq!(game.get_turn(), RRRCell::O);
+        assert_eq!(game.seat_to_move(), RRRCell::X);
+        assert_eq!(game.side_of(RRRCell::O), RRRCell::X);
+    }
+
+    #[test]
+    fn swap2_can_pass_the_choice_back() {
+        let mut game = RRRGame::new_with_rules(RRRRules::standard(5)).with_opening(Opening::Swap2);
+        for &(x, y) in &[(0, 0), (1, 1), (2, 2)] {
+            assert_eq!(game.seat_to_move(), RRRCell::X);
+            assert!(game.placing_opening());
+            game.take_turn(&Coord { x, y }).unwrap();
+        }
+        assert!(game.swap_offer().unwrap().can_place_two);
+        game.answer_swap(SwapAnswer::PlaceTwo).unwrap();
+        for &(x, y) in &[(3, 3), (4, 4)] {
+            assert_eq!(game.seat_to_move(), RRRCell::O);
+            game.take_turn(&Coord { x, y }).unwrap();
+        }
+        let offer = game.swap_offer().unwrap();
+        assert_eq!(offer.chooser, RRRCell::X);
+        assert!(!offer.can_place_two);
+        game.answer_swap(SwapAnswer::Play(RRRCell::X)).unwrap();
+        assert_eq!(game.get_turn(), RRRCell::O);
+        assert_eq!(game.seat_to_move(), RRRCell::O);
+    }
+
+    #[test]
+    fn connect6_places_one_stone_then_two() {
+        let mut game =
+            RRRGame::new_with_rules(RRRRules::standard(6)).with_stones(StonesPerTurn::connect6());
+        game.take_turn(&Coord { x: 0, y: 0 }).unwrap();
+        assert_eq!(game.get_turn(), RRRCell::O);
+        game.take_turn(&Coord { x: 1, y: 0 }).unwrap();
+        assert_eq!(game.get_turn(), RRRCell::O);
+        assert!(game.mid_turn());
+        game.take_turn(&Coord { x: 2, y: 0 }).unwrap();
+        assert_eq!(game.get_turn(), RRRCell::X);
+    }
+
+    #[test]
+    fn stones_per_turn_parse() {
+        assert_eq!(
+            "connect6".parse::<StonesPerTurn>().unwrap(),
+            StonesPerTurn::connect6()
+        );
+        assert_eq!(
+            "2".parse::<StonesPerTurn>().unwrap(),
+            StonesPerTurn { first: 2, then: 2 }
+        );
+        assert_eq!(
+            "1,3".parse::<StonesPerTurn>().unwrap(),
+            StonesPerTurn { first: 1, then: 3 }
+        );
+        for bad in &["0", "1,0", "two", ""] {
+            assert!(bad.parse::<StonesPerTurn>().is_err());
+        }
+    }
+
+    #[test]
+    fn order_wins_with_a_line_of_either_symbol() {
+        let game = place_all(
+            RRRRules::order_and_chaos(),
+            &[
+                "Oa1", "Xf6", "Ob1", "Xf5", "Oc1", "Xf4", "Od1", "Xf3", "Oe1",
+            ],
+        );
+        assert!(matches!(game.outcome, Some(RRROutcome::OrderWins { .. })));
+        assert_eq!(winner(&game), Some(RRRCell::X));
+    }
+
+    #[test]
+    fn completing_fifteen_wins_numerical() {
+        let game = place_all(RRRRules::numerical(), &["5b2", "2a1", "1c1", "8c3"]);
+        assert_eq!(winner(&game), Some(RRRCell::O));
+    }
+
+    #[test]
+    fn numerical_players_only_place_their_own_numbers() {
+        let mut game = RRRGame::new_with_rules(RRRRules::numerical());
+        let b2 = Coord { x: 1, y: 1 };
+        assert!(matches!(
+            game.place(&b2, RRRCell::Number(2)),
+            Err(RRRError::WrongPiece(..))
+        ));
+        game.place(&b2, RRRCell::Number(5)).unwrap();
+        game.place(&Coord { x: 0, y: 0 }, RRRCell::Number(2))
+            .unwrap();
+        assert!(matches!(
+            game.place(&Coord { x: 2, y: 0 }, RRRCell::Number(5)),
+            Err(RRRError::WrongPiece(..))
+        ));
+    }
+
+    #[test]
+    fn numbers_dont_share_zobrist_keys_with_the_next_cell() {
+        assert_ne!(
+            zobrist_key(&Coord { x: 0, y: 0 }, RRRCell::Number(1)),
+            zobrist_key(&Coord { x: 1, y: 0 }, RRRCell::X)
+        );
+    }
+
+    #[test]
+    fn variants_parse_from_their_names() {
+        for variant in Variant::all() {
+            assert_eq!(variant.to_string().parse::<Variant>().unwrap(), *variant);
+        }
+        assert_eq!(" Misere ".parse::<Variant>().unwrap(), Variant::Misere);
+        assert!(matches!(
+            "chess".parse::<Variant>(),
+            Err(Error::BadInput(_))
+        ));
     }
 }
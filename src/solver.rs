@@ -0,0 +1,457 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use log::{debug, info};
+
+use crate::coord::Coord;
+use crate::errors::{Error, Result};
+use crate::ricracroe::{RRRBoard, RRRCell, RRRGame, RRRRules, Variant};
+use crate::symmetry::Symmetry;
+
+// Boards are square, so this allows up to 4x4. Position keys are the board
+// read as a base-3 number, which has to fit in a u32.
+pub const MAX_SOLVABLE_CELLS: usize = 16;
+
+const TABLE_MAGIC: &[u8; 4] = b"RRRS";
+const TABLE_VERSION: u8 = 2;
+
+/// Game-theoretic value of a position for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SolvedValue {
+    Win,
+    Draw,
+    Loss,
+}
+
+impl SolvedValue {
+    fn flip(self) -> SolvedValue {
+        match self {
+            SolvedValue::Win => SolvedValue::Loss,
+            SolvedValue::Draw => SolvedValue::Draw,
+            SolvedValue::Loss => SolvedValue::Win,
+        }
+    }
+
+    fn to_bits(self) -> u8 {
+        match self {
+            SolvedValue::Win => 0,
+            SolvedValue::Draw => 1,
+            SolvedValue::Loss => 2,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Option<SolvedValue> {
+        match bits {
+            0 => Some(SolvedValue::Win),
+            1 => Some(SolvedValue::Draw),
+            2 => Some(SolvedValue::Loss),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SolvedValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SolvedValue::Win => write!(f, "win"),
+            SolvedValue::Draw => write!(f, "draw"),
+            SolvedValue::Loss => write!(f, "loss"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SolvedPosition {
+    pub value: SolvedValue,
+    /// Moves left until the game ends, with both sides playing perfectly
+    pub distance: u8,
+}
+
+impl SolvedPosition {
+    // Is this (from the point of view of the player choosing) better than `other`?
+    // Win fast, lose slow, and drag out draws in case the opponent slips up.
+    fn better_than(&self, other: &SolvedPosition) -> bool {
+        match (self.value, other.value) {
+            (a, b) if a == b => match a {
+                SolvedValue::Win => self.distance < other.distance,
+                _ => self.distance > other.distance,
+            },
+            (SolvedValue::Win, _) => true,
+            (SolvedValue::Draw, SolvedValue::Loss) => true,
+            _ => false,
+        }
+    }
+
+    // Values are 2 bits, distances the remaining 6 (a 4x4 board is 16 moves long)
+    fn to_byte(self) -> u8 {
+        (self.value.to_bits() << 6) | (self.distance & 0x3f)
+    }
+
+    fn from_byte(byte: u8) -> Option<SolvedPosition> {
+        Some(SolvedPosition {
+            value: SolvedValue::from_bits(byte >> 6)?,
+            distance: byte & 0x3f,
+        })
+    }
+}
+
+impl fmt::Display for SolvedPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} in {}", self.value, self.distance)
+    }
+}
+
+/// Every position reachable from the empty board, up to symmetry, with its
+/// solved value
+pub struct SolverTable {
+    rules: RRRRules,
+    positions: HashMap<u32, SolvedPosition>,
+}
+
+// The board as seen through `sym`, read as a base-3 number. With `swap`, X
+// and O trade places.
+fn position_key(board: &RRRBoard, sym: Symmetry, swap: bool) -> u32 {
+    let size = board.get_size();
+    let mut key: u32 = 0;
+    for y in 0..size {
+        for x in 0..size {
+            let digit = match (board.fetch(&sym.apply(&Coord { x, y }, size)), swap) {
+                (Ok(RRRCell::X), false) | (Ok(RRRCell::O), true) => 1,
+                (Ok(RRRCell::O), false) | (Ok(RRRCell::X), true) => 2,
+                _ => 0,
+            };
+            key = key * 3 + digit;
+        }
+    }
+    key
+}
+
+// Rotations and reflections of a position share a single table entry, which
+// cuts the table to roughly an eighth of the size
+fn canonical_key(board: &RRRBoard, swap: bool) -> u32 {
+    Symmetry::all()
+        .iter()
+        .map(|&sym| position_key(board, sym, swap))
+        .min()
+        .unwrap_or(0)
+}
+
+// How many of `piece` are on the board
+fn count(board: &RRRBoard, piece: RRRCell) -> usize {
+    let size = board.get_size();
+    (0..size)
+        .flat_map(|y| (0..size).map(move |x| Coord { x, y }))
+        .filter(|coord| board.fetch(coord).ok() == Some(piece))
+        .count()
+}
+
+fn invalid_table(msg: &str) -> Error {
+    Error::from(io::Error::new(io::ErrorKind::InvalidData, msg))
+}
+
+impl SolverTable {
+    /// Small enough, and each side only ever places their own symbol
+    pub fn can_solve(rules: &RRRRules) -> bool {
+        rules.size * rules.size <= MAX_SOLVABLE_CELLS && !rules.variant.chooses_pieces()
+    }
+
+    pub fn solve(rules: RRRRules) -> Result<Self> {
+        if !SolverTable::can_solve(&rules) {
+            return Err(Error::InvalidGameInput);
+        }
+        let mut table = SolverTable {
+            rules,
+            positions: HashMap::new(),
+        };
+        let mut board = RRRBoard::new_with_rules(rules);
+        let root = table.solve_position(&mut board, RRRCell::X);
+        info!(
+            "Solved {}: {} for X, {} reachable positions",
+            rules,
+            root,
+            table.len()
+        );
+        Ok(table)
+    }
+
+    // `board` is undecided with `player` to move. Moves are made and unmade in
+    // place to avoid cloning the board at every node.
+    fn solve_position(&mut self, board: &mut RRRBoard, player: RRRCell) -> SolvedPosition {
+        let key = canonical_key(board, false);
+        if let Some(solved) = self.positions.get(&key) {
+            return *solved;
+        }
+
+        let mut best: Option<SolvedPosition> = None;
+        for coord in board.empty_cells() {
+            // Can't fail, the cell came from the board's own list of empty cells
+            board.make_move(&coord, player).unwrap();
+            let result = match board.outcome_after(&coord) {
+                Some(outcome) => {
+                    let value = match outcome.winner() {
+                        Some(winner) if winner == player => SolvedValue::Loss,
+                        Some(_) => SolvedValue::Win,
+                        None => SolvedValue::Draw,
+                    };
+                    // Record the finished position too, for the side that would be next
+                    self.positions.insert(
+                        canonical_key(board, false),
+                        SolvedPosition { value, distance: 0 },
+                    );
+                    SolvedPosition {
+                        value: value.flip(),
+                        distance: 1,
+                    }
+                }
+                None => {
+                    let reply = self.solve_position(board, player.opponent());
+                    SolvedPosition {
+                        value: reply.value.flip(),
+                        distance: reply.distance + 1,
+                    }
+                }
+            };
+            board.set(&coord, RRRCell::Clear).unwrap();
+
+            match best {
+                Some(ref cur) if !result.better_than(cur) => {}
+                _ => best = Some(result),
+            }
+        }
+
+        // An undecided board always has an empty cell, but be defensive about it
+        let solved = best.unwrap_or(SolvedPosition {
+            value: SolvedValue::Draw,
+            distance: 0,
+        });
+        self.positions.insert(key, solved);
+        solved
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Value of `board` for `to_move`. The table only has games X starts, so
+    /// whose turn it is follows from how many of each symbol there are. A
+    /// game O started is looked up with the symbols swapped, as the rules in
+    /// tables don't favour either one. None if `to_move` can't be the side
+    /// to move either way.
+    pub fn lookup(&self, board: &RRRBoard, to_move: RRRCell) -> Option<SolvedPosition> {
+        if board.get_rules() != self.rules {
+            return None;
+        }
+        let (xs, os) = (count(board, RRRCell::X), count(board, RRRCell::O));
+        let swap = match to_move {
+            RRRCell::X if xs == os => false,
+            RRRCell::O if xs == os + 1 => false,
+            RRRCell::O if os == xs => true,
+            RRRCell::X if os == xs + 1 => true,
+            _ => return None,
+        };
+        self.positions.get(&canonical_key(board, swap)).cloned()
+    }
+
+    /// Value of playing in `coord`, from the point of view of the side to move.
+    /// Only games of one stone a turn are in the table.
+    pub fn evaluate_move(&self, game: &RRRGame, coord: &Coord) -> Option<SolvedPosition> {
+        if !game.stones.is_single() {
+            return None;
+        }
+        let mut board = game.board.clone();
+        board.make_move(coord, game.get_turn()).ok()?;
+        let reply = self.lookup(&board, game.get_turn().opponent())?;
+        Some(SolvedPosition {
+            value: reply.value.flip(),
+            distance: reply.distance + 1,
+        })
+    }
+
+    pub fn best_move(&self, game: &RRRGame) -> Option<Coord> {
+        let mut best: Option<(Coord, SolvedPosition)> = None;
+        for coord in game.board.empty_cells() {
+            if let Some(result) = self.evaluate_move(game, &coord) {
+                match best {
+                    Some((_, ref cur)) if !result.better_than(cur) => {}
+                    _ => best = Some((coord, result)),
+                }
+            }
+        }
+        debug!("Solver table picks {:?}", best);
+        best.map(|(coord, _)| coord)
+    }
+
+    /// Binary layout, all integers little endian:
+    ///   magic "RRRS", version u8, size u8, win length u8, variant u8, count u32,
+    ///   then `count` entries of canonical position key u32 + packed
+    ///   value/distance u8,
+    ///   sorted by key.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(TABLE_MAGIC)?;
+        writer.write_all(&[
+            TABLE_VERSION,
+            self.rules.size as u8,
+            self.rules.win_length as u8,
+            self.rules.variant.code(),
+        ])?;
+        writer.write_all(&(self.positions.len() as u32).to_le_bytes())?;
+
+        let mut keys: Vec<&u32> = self.positions.keys().collect();
+        keys.sort();
+        for key in keys {
+            writer.write_all(&key.to_le_bytes())?;
+            writer.write_all(&[self.positions[key].to_byte()])?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[0..4] != TABLE_MAGIC {
+            return Err(invalid_table("not a solver table"));
+        }
+        if header[4] != TABLE_VERSION {
+            return Err(invalid_table("unsupported solver table version"));
+        }
+        let rules = RRRRules {
+            size: header[5] as usize,
+            win_length: header[6] as usize,
+            variant: Variant::from_code(header[7])
+                .ok_or_else(|| invalid_table("unknown variant"))?,
+        };
+        let mut count_bytes = [0u8; 4];
+        count_bytes.copy_from_slice(&header[8..12]);
+        let count = u32::from_le_bytes(count_bytes) as usize;
+
+        let mut positions = HashMap::with_capacity(count);
+        let mut entry = [0u8; 5];
+        for _ in 0..count {
+            reader.read_exact(&mut entry)?;
+            let mut key_bytes = [0u8; 4];
+            key_bytes.copy_from_slice(&entry[0..4]);
+            let solved = SolvedPosition::from_byte(entry[4])
+                .ok_or_else(|| invalid_table("corrupt solver table entry"))?;
+            positions.insert(u32::from_le_bytes(key_bytes), solved);
+        }
+        Ok(SolverTable { rules, positions })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let mut writer = BufWriter::new(File::create(path).map_err(Error::saving(path))?);
+        self.write_to(&mut writer)?;
+        writer.flush().map_err(Error::saving(path))?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).map_err(Error::loading(path))?;
+        SolverTable::read_from(&mut BufReader::new(file))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(moves: &[(usize, usize)]) -> RRRGame {
+        let mut game = RRRGame::new_with_rules(RRRRules::default());
+        for &(x, y) in moves {
+            game.take_turn(&Coord { x, y }).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn only_small_boards_of_own_symbols_are_solved() {
+        assert!(SolverTable::can_solve(&RRRRules::default()));
+        assert!(SolverTable::can_solve(&RRRRules::standard(4)));
+        assert!(!SolverTable::can_solve(&RRRRules::standard(5)));
+        let order_and_chaos = RRRRules {
+            variant: Variant::OrderAndChaos,
+            ..RRRRules::standard(4)
+        };
+        assert!(!SolverTable::can_solve(&order_and_chaos));
+        assert!(!SolverTable::can_solve(&RRRRules::numerical()));
+        assert!(SolverTable::solve(RRRRules::standard(5)).is_err());
+    }
+
+    #[test]
+    fn tic_tac_toe_is_a_draw() {
+        let table = SolverTable::solve(RRRRules::default()).unwrap();
+        // Every legal position, once per rotation or reflection
+        assert_eq!(table.len(), 765);
+        assert_eq!(
+            table.lookup(&play(&[]).board, RRRCell::X),
+            Some(SolvedPosition {
+                value: SolvedValue::Draw,
+                distance: 9
+            })
+        );
+        // Answering the centre on an edge loses
+        let game = play(&[(1, 1), (1, 0)]);
+        assert_eq!(
+            table.lookup(&game.board, RRRCell::X).unwrap().value,
+            SolvedValue::Win
+        );
+        // Take the win rather than block
+        let game = play(&[(0, 0), (0, 1), (1, 0), (1, 1)]);
+        let win = Coord { x: 2, y: 0 };
+        assert_eq!(table.best_move(&game), Some(win));
+        assert_eq!(
+            table.evaluate_move(&game, &win),
+            Some(SolvedPosition {
+                value: SolvedValue::Win,
+                distance: 1
+            })
+        );
+        // Tables are for one set of rules only
+        let misere = RRRRules {
+            variant: Variant::Misere,
+            ..RRRRules::default()
+        };
+        assert!(table
+            .lookup(&RRRGame::new_with_rules(misere).board, RRRCell::X)
+            .is_none());
+    }
+
+    #[test]
+    fn games_o_starts_are_looked_up_for_the_right_side() {
+        let table = SolverTable::solve(RRRRules::default()).unwrap();
+        // O takes the centre and X answers on an edge, as in the X first
+        // game that loses for the edge
+        let mut game = RRRGame::new().starting_with(RRRCell::O);
+        game.take_turn(&Coord { x: 1, y: 1 }).unwrap();
+        game.take_turn(&Coord { x: 1, y: 0 }).unwrap();
+        assert_eq!(
+            table.lookup(&game.board, RRRCell::O).unwrap().value,
+            SolvedValue::Win
+        );
+        assert!(table.best_move(&game).is_some());
+        // Nobody could be next on a board with two more X than O
+        let mut board = RRRBoard::new_with_rules(RRRRules::default());
+        board.make_move(&Coord { x: 0, y: 0 }, RRRCell::X).unwrap();
+        board.make_move(&Coord { x: 2, y: 2 }, RRRCell::X).unwrap();
+        assert!(table.lookup(&board, RRRCell::X).is_none());
+        assert!(table.lookup(&board, RRRCell::O).is_none());
+    }
+
+    #[test]
+    fn tables_round_trip() {
+        let table = SolverTable::solve(RRRRules::default()).unwrap();
+        let mut bytes = Vec::new();
+        table.write_to(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 12 + 5 * table.len());
+        let read = SolverTable::read_from(&mut &bytes[..]).unwrap();
+        assert_eq!(read.rules, table.rules);
+        assert_eq!(read.positions, table.positions);
+
+        let mut corrupt = bytes.clone();
+        corrupt[0] = b'X';
+        assert!(SolverTable::read_from(&mut &corrupt[..]).is_err());
+        assert!(SolverTable::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+}
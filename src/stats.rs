@@ -0,0 +1,487 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use crate::errors::{Error, Result};
+use crate::ricracroe::{RRRCell, RRRGame, RRRRules, Variant};
+
+/// How a game went for one of the players in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Finish {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// One finished game, as kept in the stats file
+#[derive(Debug, Clone)]
+pub struct GameResult {
+    pub x: String,
+    pub o: String,
+    pub rules: RRRRules,
+    pub winner: Option<RRRCell>,
+    pub moves: usize,
+}
+
+impl GameResult {
+    /// The result of a finished game between `x` and `o`, or None if it isn't
+    /// over yet
+    pub fn new(game: &RRRGame, x: &str, o: &str) -> Option<Self> {
+        let outcome = game.outcome.as_ref()?;
+        Some(GameResult {
+            x: x.to_string(),
+            o: o.to_string(),
+            rules: game.board.get_rules(),
+            winner: outcome.winner(),
+            moves: game.moves.len(),
+        })
+    }
+
+    // game, X, O, size, win length, variant (0 standard, 1 misère, 2 Order and
+    // Chaos, 3 numerical), winner (X, O or -), moves
+    fn to_line(&self) -> String {
+        let winner = match self.winner {
+            Some(winner) => winner.to_string(),
+            None => "-".to_string(),
+        };
+        format!(
+            "game\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            self.x,
+            self.o,
+            self.rules.size,
+            self.rules.win_length,
+            self.rules.variant.code(),
+            winner,
+            self.moves
+        )
+    }
+
+    fn from_fields(fields: &[&str]) -> Option<Self> {
+        if fields.len() != 7 {
+            return None;
+        }
+        let winner = match fields[5] {
+            "X" => Some(RRRCell::X),
+            "O" => Some(RRRCell::O),
+            "-" => None,
+            _ => return None,
+        };
+        Some(GameResult {
+            x: fields[0].to_string(),
+            o: fields[1].to_string(),
+            rules: RRRRules {
+                size: fields[2].parse().ok()?,
+                win_length: fields[3].parse().ok()?,
+                variant: Variant::from_code(fields[4].parse().ok()?)?,
+            },
+            winner,
+            moves: fields[6].parse().ok()?,
+        })
+    }
+
+    // Which side `name` played, and who against
+    fn side_of(&self, name: &str) -> Option<(RRRCell, &str)> {
+        if self.x == name {
+            Some((RRRCell::X, &self.o))
+        } else if self.o == name {
+            Some((RRRCell::O, &self.x))
+        } else {
+            None
+        }
+    }
+
+    fn finish_for(&self, side: RRRCell) -> Finish {
+        match self.winner {
+            None => Finish::Draw,
+            Some(winner) if winner == side => Finish::Win,
+            Some(_) => Finish::Loss,
+        }
+    }
+}
+
+/// Wins, losses and draws, and how long the games took
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tally {
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+    pub moves: usize,
+}
+
+impl Tally {
+    fn add(&mut self, finish: Finish, moves: usize) {
+        match finish {
+            Finish::Win => self.wins += 1,
+            Finish::Loss => self.losses += 1,
+            Finish::Draw => self.draws += 1,
+        }
+        self.moves += moves;
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.losses + self.draws
+    }
+
+    /// Moves per game, counting both sides' moves
+    pub fn average_length(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.0;
+        }
+        self.moves as f64 / self.games() as f64
+    }
+}
+
+impl fmt::Display for Tally {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>5} {:>5} {:>5} {:>5} {:>7.1}",
+            self.games(),
+            self.wins,
+            self.losses,
+            self.draws,
+            self.average_length()
+        )
+    }
+}
+
+const TALLY_HEADER: &str = "Games  Wins  Lost Drawn  Length";
+
+/// A run of the same result
+#[derive(Debug, Clone, Copy)]
+pub struct Streak {
+    pub finish: Finish,
+    pub length: usize,
+}
+
+impl fmt::Display for Streak {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (one, many) = match self.finish {
+            Finish::Win => ("win", "wins"),
+            Finish::Loss => ("loss", "losses"),
+            Finish::Draw => ("draw", "draws"),
+        };
+        let noun = if self.length == 1 { one } else { many };
+        write!(f, "{} {}", self.length, noun)
+    }
+}
+
+/// Everything known about how one player has done
+#[derive(Debug, Clone, Default)]
+pub struct Summary {
+    pub overall: Tally,
+    pub by_opponent: Vec<(String, Tally)>,
+    pub by_rules: Vec<(RRRRules, Tally)>,
+    pub current_streak: Option<Streak>,
+    pub best_win_streak: usize,
+}
+
+fn tally_for<K: PartialEq>(tallies: &mut Vec<(K, Tally)>, key: K) -> &mut Tally {
+    let index = match tallies.iter().position(|(existing, _)| *existing == key) {
+        Some(index) => index,
+        None => {
+            tallies.push((key, Tally::default()));
+            tallies.len() - 1
+        }
+    };
+    &mut tallies[index].1
+}
+
+/// Named player profiles and the results of every game they've played,
+/// kept in a file so they last from one session to the next. Each line is
+/// tab separated: "player", name for a profile, or "game" then the fields
+/// of a game result.
+#[derive(Debug, Default)]
+pub struct Stats {
+    // Where to save new results, if anywhere
+    path: Option<PathBuf>,
+    profiles: Vec<String>,
+    games: Vec<GameResult>,
+}
+
+impl Stats {
+    /// Read the stats file at `path`, which is fine not to exist yet
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut stats = Stats {
+            path: Some(path.to_path_buf()),
+            ..Stats::default()
+        };
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => return Ok(stats),
+            Err(err) => return Err(Error::Load(path.to_path_buf(), err)),
+        };
+        for (num, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad_line =
+                || Error::BadStats(format!("{} line {}: {}", path.display(), num + 1, line));
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[0] {
+                "player" if fields.len() == 2 => stats.profiles.push(fields[1].to_string()),
+                "game" => stats
+                    .games
+                    .push(GameResult::from_fields(&fields[1..]).ok_or_else(bad_line)?),
+                _ => return Err(bad_line()),
+            }
+        }
+        Ok(stats)
+    }
+
+    pub fn profiles(&self) -> &[String] {
+        &self.profiles
+    }
+
+    // Add a line to the end of the file, starting it if need be
+    fn append(&self, line: &str) -> Result<()> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(Error::saving(dir))?;
+        }
+        let new = !path.exists();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(Error::saving(path))?;
+        if new {
+            file.write_all(b"# ricracroe players and results, tab separated\n")
+                .map_err(Error::saving(path))?;
+        }
+        file.write_all(line.as_bytes()).map_err(Error::saving(path))
+    }
+
+    /// Make a new profile. Names can't be reused, or use tabs or newlines,
+    /// which the file format needs to itself.
+    pub fn add_profile(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(|ch: char| ch == '\t' || ch.is_control()) {
+            return Err(Error::BadStats(format!(
+                "'{}' can't be a player name",
+                name
+            )));
+        }
+        if self.profiles.iter().any(|profile| profile == name) {
+            return Err(Error::BadStats(format!(
+                "there's already a player called '{}'",
+                name
+            )));
+        }
+        self.append(&format!("player\t{}\n", name))?;
+        self.profiles.push(name.to_string());
+        Ok(())
+    }
+
+    /// Keep the result of a finished game
+    pub fn record(&mut self, game: GameResult) -> Result<()> {
+        self.append(&game.to_line())?;
+        self.games.push(game);
+        Ok(())
+    }
+
+    pub fn summary(&self, name: &str) -> Summary {
+        let mut summary = Summary::default();
+        let mut win_streak = 0;
+        for game in &self.games {
+            let (side, opponent) = match game.side_of(name) {
+                Some(side) => side,
+                None => continue,
+            };
+            let finish = game.finish_for(side);
+            summary.overall.add(finish, game.moves);
+            tally_for(&mut summary.by_opponent, opponent.to_string()).add(finish, game.moves);
+            tally_for(&mut summary.by_rules, game.rules).add(finish, game.moves);
+
+            summary.current_streak = match summary.current_streak {
+                Some(streak) if streak.finish == finish => Some(Streak {
+                    length: streak.length + 1,
+                    ..streak
+                }),
+                _ => Some(Streak { finish, length: 1 }),
+            };
+            win_streak = if finish == Finish::Win {
+                win_streak + 1
+            } else {
+                0
+            };
+            summary.best_win_streak = summary.best_win_streak.max(win_streak);
+        }
+        summary
+    }
+
+    /// A line per profile, for an overview
+    pub fn overview(&self) -> Vec<String> {
+        let name_width = self
+            .profiles
+            .iter()
+            .map(|name| name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max("Player".len());
+        let mut lines = vec![format!(
+            "{:<width$}  {}  Streak",
+            "Player",
+            TALLY_HEADER,
+            width = name_width
+        )];
+        for name in &self.profiles {
+            let summary = self.summary(name);
+            let streak = summary
+                .current_streak
+                .map(|streak| streak.to_string())
+                .unwrap_or_default();
+            lines.push(format!(
+                "{:<width$}  {}  {}",
+                name,
+                summary.overall,
+                streak,
+                width = name_width
+            ));
+        }
+        lines
+    }
+
+    /// Everything about one player: totals, then broken down by opponent and
+    /// by variant
+    pub fn report(&self, name: &str) -> String {
+        let summary = self.summary(name);
+        let mut report = format!("{}\n", name);
+        if summary.overall.games() == 0 {
+            report.push_str("  No games played yet.\n");
+            return report;
+        }
+        report.push_str(&format!("  {:<24}{}\n", "", TALLY_HEADER));
+        report.push_str(&format!("  {:<24}{}\n", "All games", summary.overall));
+        if let Some(streak) = summary.current_streak {
+            report.push_str(&format!("  Current streak: {}\n", streak));
+        }
+        report.push_str(&format!(
+            "  Best winning streak: {}\n",
+            summary.best_win_streak
+        ));
+        report.push_str("  Against:\n");
+        for (opponent, tally) in &summary.by_opponent {
+            report.push_str(&format!("    {:<22}{}\n", opponent, tally));
+        }
+        report.push_str("  Playing:\n");
+        for (rules, tally) in &summary.by_rules {
+            report.push_str(&format!("    {:<22}{}\n", rules.to_string(), tally));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::coord::Coord;
+
+    // A game between `x` and `o` that's won by `winner`, or drawn if None
+    fn result(x: &str, o: &str, winner: Option<RRRCell>) -> GameResult {
+        GameResult {
+            x: x.to_string(),
+            o: o.to_string(),
+            rules: RRRRules::default(),
+            winner,
+            moves: if winner.is_some() { 5 } else { 9 },
+        }
+    }
+
+    #[test]
+    fn profile_names_are_checked() {
+        let mut stats = Stats::default();
+        stats.add_profile(" alice ").unwrap();
+        assert_eq!(stats.profiles(), ["alice".to_string()]);
+        for bad in &["", "   ", "al\tice", "al\nice", "alice"] {
+            assert!(
+                matches!(stats.add_profile(bad), Err(Error::BadStats(_))),
+                "{:?}",
+                bad
+            );
+        }
+        assert_eq!(stats.profiles().len(), 1);
+    }
+
+    #[test]
+    fn results_last_from_one_session_to_the_next() {
+        let dir = TempDir::new("ricracroe-stats").unwrap();
+        let path = dir.path().join("stats").join("players.tsv");
+        let mut stats = Stats::load(&path).unwrap();
+        stats.add_profile("alice").unwrap();
+        stats.add_profile("bob").unwrap();
+        stats.record(result("alice", "bob", None)).unwrap();
+        stats
+            .record(result("alice", "bob", Some(RRRCell::X)))
+            .unwrap();
+        stats
+            .record(result("bob", "alice", Some(RRRCell::O)))
+            .unwrap();
+        let bigger = GameResult {
+            rules: RRRRules::standard(4),
+            ..result("bob", "perfect", Some(RRRCell::O))
+        };
+        stats.record(bigger).unwrap();
+
+        let stats = Stats::load(&path).unwrap();
+        assert_eq!(stats.profiles(), ["alice".to_string(), "bob".to_string()]);
+        let alice = stats.summary("alice");
+        assert_eq!(
+            (
+                alice.overall.wins,
+                alice.overall.losses,
+                alice.overall.draws
+            ),
+            (2, 0, 1)
+        );
+        assert_eq!(alice.overall.moves, 19);
+        assert_eq!(alice.best_win_streak, 2);
+        assert_eq!(alice.current_streak.unwrap().to_string(), "2 wins");
+        let bob = stats.summary("bob");
+        assert_eq!(bob.overall.losses, 3);
+        assert_eq!(bob.by_opponent.len(), 2);
+        assert_eq!(bob.by_rules.len(), 2);
+        assert!(stats.report("bob").contains("4x4, 4 in a row"));
+        assert!(stats.report("carol").contains("No games played yet."));
+    }
+
+    #[test]
+    fn bad_stats_lines_are_refused() {
+        let dir = TempDir::new("ricracroe-stats").unwrap();
+        let path = dir.path().join("players.tsv");
+        for bad in &[
+            "player\n",
+            "game\talice\tbob\t3\t3\t0\tX\n",
+            "game\talice\tbob\tthree\t3\t0\tX\t5\n",
+            "game\talice\tbob\t3\t3\t0\tZ\t5\n",
+            "score\talice\t3\n",
+        ] {
+            fs::write(&path, format!("# comment\nplayer\talice\n{}", bad)).unwrap();
+            match Stats::load(&path) {
+                Err(Error::BadStats(reason)) => assert!(reason.contains(" line 3: "), "{}", reason),
+                Err(e) => panic!("expected a stats error, got {}", e),
+                Ok(_) => panic!("expected {:?} to be refused", bad),
+            }
+        }
+    }
+
+    #[test]
+    fn only_finished_games_have_results() {
+        let mut game = RRRGame::new_with_rules(RRRRules::default());
+        for cell in &["a1", "a2", "b1", "b2"] {
+            game.take_turn(&cell.parse::<Coord>().unwrap()).unwrap();
+        }
+        assert!(GameResult::new(&game, "alice", "bob").is_none());
+        game.take_turn(&"c1".parse::<Coord>().unwrap()).unwrap();
+        let result = GameResult::new(&game, "alice", "bob").unwrap();
+        assert_eq!(result.winner, Some(RRRCell::X));
+        assert_eq!(result.moves, 5);
+        assert_eq!(result.finish_for(RRRCell::O), Finish::Loss);
+    }
+}
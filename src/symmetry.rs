@@ -0,0 +1,76 @@
+use crate::coord::Coord;
+
+/// The 8 symmetries of a square board (the dihedral group of order 8)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipHorizontal,
+    FlipVertical,
+    FlipDiagonal,
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    pub fn all() -> &'static [Symmetry] {
+        &[
+            Symmetry::Identity,
+            Symmetry::Rotate90,
+            Symmetry::Rotate180,
+            Symmetry::Rotate270,
+            Symmetry::FlipHorizontal,
+            Symmetry::FlipVertical,
+            Symmetry::FlipDiagonal,
+            Symmetry::FlipAntiDiagonal,
+        ]
+    }
+
+    /// Where `coord` ends up on a board of `size` x `size` cells
+    pub fn apply(self, coord: &Coord, size: usize) -> Coord {
+        let last = size - 1;
+        let (x, y) = (coord.x, coord.y);
+        let (x, y) = match self {
+            Symmetry::Identity => (x, y),
+            Symmetry::Rotate90 => (last - y, x),
+            Symmetry::Rotate180 => (last - x, last - y),
+            Symmetry::Rotate270 => (y, last - x),
+            Symmetry::FlipHorizontal => (last - x, y),
+            Symmetry::FlipVertical => (x, last - y),
+            Symmetry::FlipDiagonal => (y, x),
+            Symmetry::FlipAntiDiagonal => (last - y, last - x),
+        };
+        Coord { x, y }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn symmetries_move_every_cell_somewhere_different() {
+        for size in 1..=4 {
+            let cells: Vec<Coord> = (0..size)
+                .flat_map(|y| (0..size).map(move |x| Coord { x, y }))
+                .collect();
+            for &sym in Symmetry::all() {
+                let moved: HashSet<Coord> =
+                    cells.iter().map(|coord| sym.apply(coord, size)).collect();
+                assert_eq!(moved.len(), cells.len(), "{:?} on {}x{}", sym, size, size);
+                assert!(moved.iter().all(|coord| coord.x < size && coord.y < size));
+            }
+        }
+    }
+
+    #[test]
+    fn rotating_four_times_gets_back_to_the_start() {
+        let start = Coord { x: 1, y: 0 };
+        assert_eq!(Symmetry::Rotate90.apply(&start, 4), Coord { x: 3, y: 1 });
+        let coord = (0..4).fold(start, |coord, _| Symmetry::Rotate90.apply(&coord, 4));
+        assert_eq!(coord, start);
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::VecDeque;
+use std::io::Write;
+
+use crossterm::input::{InputEvent, MouseButton, MouseEvent};
+use crossterm::style::ContentStyle;
+use crossterm::{cursor, input, screen, style, terminal};
+use crossterm::{Output, QueueableCommand};
+
+use crate::coord::Coord;
+use crate::errors::{Error, Result};
+use crate::terminal::keymap;
+
+/// Somewhere key presses and mouse clicks come from
+pub trait InputSource {
+    /// The next event, or None if there isn't one yet. Doesn't wait.
+    fn poll_event(&mut self) -> Result<Option<InputEvent>>;
+}
+
+/// Something the game can be drawn on
+pub trait Screen {
+    /// Width and height, in characters
+    fn size(&self) -> Result<Coord>;
+
+    /// Take over the display
+    fn enter(&mut self) -> Result<()>;
+
+    /// Give the display back the way it was found
+    fn leave(&mut self) -> Result<()>;
+
+    fn move_to(&mut self, coord: &Coord) -> Result<()>;
+
+    /// Write `text` at the cursor position, moving the cursor past it
+    fn print(&mut self, text: &str) -> Result<()>;
+
+    fn print_styled(&mut self, text: &str, style: &ContentStyle) -> Result<()>;
+
+    fn clear_all(&mut self) -> Result<()>;
+
+    /// Blank out the whole line the cursor is on
+    fn clear_line(&mut self) -> Result<()>;
+
+    fn show_cursor(&mut self) -> Result<()>;
+
+    fn hide_cursor(&mut self) -> Result<()>;
+
+    /// Make everything written so far visible
+    fn flush(&mut self) -> Result<()>;
+}
+
+impl<T: InputSource + ?Sized> InputSource for &mut T {
+    fn poll_event(&mut self) -> Result<Option<InputEvent>> {
+        (**self).poll_event()
+    }
+}
+
+impl<T: Screen + ?Sized> Screen for &mut T {
+    fn size(&self) -> Result<Coord> {
+        (**self).size()
+    }
+    fn enter(&mut self) -> Result<()> {
+        (**self).enter()
+    }
+    fn leave(&mut self) -> Result<()> {
+        (**self).leave()
+    }
+    fn move_to(&mut self, coord: &Coord) -> Result<()> {
+        (**self).move_to(coord)
+    }
+    fn print(&mut self, text: &str) -> Result<()> {
+        (**self).print(text)
+    }
+    fn print_styled(&mut self, text: &str, style: &ContentStyle) -> Result<()> {
+        (**self).print_styled(text, style)
+    }
+    fn clear_all(&mut self) -> Result<()> {
+        (**self).clear_all()
+    }
+    fn clear_line(&mut self) -> Result<()> {
+        (**self).clear_line()
+    }
+    fn show_cursor(&mut self) -> Result<()> {
+        (**self).show_cursor()
+    }
+    fn hide_cursor(&mut self) -> Result<()> {
+        (**self).hide_cursor()
+    }
+    fn flush(&mut self) -> Result<()> {
+        (**self).flush()
+    }
+}
+
+/// Keyboard and mouse input from the real terminal
+pub struct CrosstermInput {
+    input: input::TerminalInput,
+    reader: input::AsyncReader,
+}
+
+impl CrosstermInput {
+    pub fn new() -> Self {
+        let input = input::input();
+        let reader = input.read_async();
+        CrosstermInput { input, reader }
+    }
+}
+
+impl Drop for CrosstermInput {
+    #[allow(unused_must_use)]
+    fn drop(&mut self) {
+        self.input.disable_mouse_mode();
+    }
+}
+
+impl InputSource for CrosstermInput {
+    fn poll_event(&mut self) -> Result<Option<InputEvent>> {
+        Ok(self.reader.next())
+    }
+}
+
+/// The real terminal, in raw mode for as long as this is around
+pub struct CrosstermScreen<W: Write> {
+    _raw: screen::RawScreen,
+    writer: W,
+}
+
+impl<W: Write> CrosstermScreen<W> {
+    pub fn new(writer: W) -> Result<Self> {
+        Ok(CrosstermScreen {
+            _raw: screen::RawScreen::into_raw_mode().map_err(Error::from)?,
+            writer,
+        })
+    }
+}
+
+impl<W: Write> Screen for CrosstermScreen<W> {
+    fn size(&self) -> Result<Coord> {
+        let (width, height) = terminal::size().map_err(Error::from)?;
+        Ok(Coord {
+            x: width as usize,
+            y: height as usize,
+        })
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.writer
+            .queue(screen::EnterAlternateScreen)
+            .map_err(Error::from)?
+            .queue(input::EnableMouseCapture)
+            .map_err(Error::from)?
+            .queue(cursor::Hide)
+            .map_err(Error::from)?
+            .queue(terminal::Clear(terminal::ClearType::All))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn leave(&mut self) -> Result<()> {
+        self.writer
+            .queue(cursor::Show)
+            .map_err(Error::from)?
+            .queue(input::DisableMouseCapture)
+            .map_err(Error::from)?
+            .queue(screen::LeaveAlternateScreen)
+            .map_err(Error::from)?;
+        self.flush()
+    }
+
+    fn move_to(&mut self, coord: &Coord) -> Result<()> {
+        self.writer
+            .queue(cursor::MoveTo(coord.x as u16, coord.y as u16))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn print(&mut self, text: &str) -> Result<()> {
+        self.writer
+            .queue(Output(text.to_string()))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn print_styled(&mut self, text: &str, text_style: &ContentStyle) -> Result<()> {
+        self.writer
+            .queue(style::PrintStyledContent(text_style.apply(text)))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn clear_all(&mut self) -> Result<()> {
+        self.writer
+            .queue(terminal::Clear(terminal::ClearType::All))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn clear_line(&mut self) -> Result<()> {
+        self.writer
+            .queue(terminal::Clear(terminal::ClearType::CurrentLine))
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> Result<()> {
+        self.writer
+            .queue(cursor::Show)
+            .map_err(Error::from)?
+            .queue(cursor::EnableBlinking)
+            .map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> Result<()> {
+        self.writer.queue(cursor::Hide).map_err(Error::from)?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush().map_err(Error::from)?;
+        Ok(())
+    }
+}
+
+/// A grid of characters standing in for a terminal, for running the game
+/// without one. Styles aren't kept, only what would be read off the screen.
+pub struct VirtualScreen {
+    rows: Vec<Vec<char>>,
+    cursor: Coord,
+}
+
+impl VirtualScreen {
+    pub fn new(size: Coord) -> Self {
+        VirtualScreen {
+            rows: vec![vec![' '; size.x]; size.y],
+            cursor: Coord::default(),
+        }
+    }
+
+    fn row_text(&self, y: usize) -> String {
+        self.rows
+            .get(y)
+            .map(|row| row.iter().collect())
+            .unwrap_or_default()
+    }
+
+    /// Everything on screen, without trailing blanks on each line or trailing
+    /// blank lines
+    pub fn contents(&self) -> String {
+        let mut lines: Vec<String> = (0..self.rows.len())
+            .map(|y| self.row_text(y).trim_end().to_string())
+            .collect();
+        while lines.last().map(String::is_empty).unwrap_or(false) {
+            lines.pop();
+        }
+        let mut contents = lines.join("\n");
+        contents.push('\n');
+        contents
+    }
+}
+
+impl Screen for VirtualScreen {
+    fn size(&self) -> Result<Coord> {
+        Ok(Coord {
+            x: self.rows.first().map(Vec::len).unwrap_or(0),
+            y: self.rows.len(),
+        })
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        self.clear_all()
+    }
+
+    fn leave(&mut self) -> Result<()> {
+        // Leave everything on screen so it can be looked at afterwards
+        Ok(())
+    }
+
+    fn move_to(&mut self, coord: &Coord) -> Result<()> {
+        self.cursor = *coord;
+        Ok(())
+    }
+
+    fn print_styled(&mut self, text: &str, _style: &ContentStyle) -> Result<()> {
+        self.print(text)
+    }
+
+    fn print(&mut self, text: &str) -> Result<()> {
+        for ch in text.chars() {
+            let Coord { x, y } = self.cursor;
+            if let Some(cell) = self.rows.get_mut(y).and_then(|row| row.get_mut(x)) {
+                *cell = ch;
+            }
+            self.cursor.x += 1;
+        }
+        Ok(())
+    }
+
+    fn clear_all(&mut self) -> Result<()> {
+        for row in self.rows.iter_mut() {
+            row.iter_mut().for_each(|cell| *cell = ' ');
+        }
+        Ok(())
+    }
+
+    fn clear_line(&mut self) -> Result<()> {
+        if let Some(row) = self.rows.get_mut(self.cursor.y) {
+            row.iter_mut().for_each(|cell| *cell = ' ');
+        }
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Input events given up front, handed out one at a time. Running out is an
+/// error, so a script that doesn't finish the game can't hang waiting.
+pub struct ScriptedInput {
+    events: VecDeque<InputEvent>,
+}
+
+impl ScriptedInput {
+    pub fn new<T: IntoIterator<Item = InputEvent>>(events: T) -> Self {
+        ScriptedInput {
+            events: events.into_iter().collect(),
+        }
+    }
+}
+
+impl InputSource for ScriptedInput {
+    fn poll_event(&mut self) -> Result<Option<InputEvent>> {
+        match self.events.pop_front() {
+            Some(event) => Ok(Some(event)),
+            None => Err(Error::EndOfInput),
+        }
+    }
+}
+
+/// Parse an input event written as a key name (see `keymap::parse_key`), or
+/// a mouse click as click:X,Y in terminal coordinates
+pub fn parse_input_event(text: &str) -> Result<InputEvent> {
+    let bad = || Error::BadInput(format!("'{}' isn't a key or a click", text));
+    if let Some(position) = text.strip_prefix("click:") {
+        let (x, y) = position.split_once(',').ok_or_else(bad)?;
+        let x = x.trim().parse().map_err(|_| bad())?;
+        let y = y.trim().parse().map_err(|_| bad())?;
+        return Ok(InputEvent::Mouse(MouseEvent::Press(
+            MouseButton::Left,
+            x,
+            y,
+        )));
+    }
+    let key = keymap::parse_key(text).ok_or_else(bad)?;
+    Ok(InputEvent::Keyboard(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::input::KeyEvent;
+
+    use super::*;
+
+    #[test]
+    fn virtual_screens_keep_what_was_printed() {
+        let mut screen = VirtualScreen::new(Coord { x: 8, y: 4 });
+        screen.move_to(&Coord { x: 2, y: 1 }).unwrap();
+        screen.print("hello there").unwrap();
+        assert_eq!(screen.contents(), "\n  hello\n");
+        screen.clear_line().unwrap();
+        assert_eq!(screen.contents(), "\n");
+    }
+
+    #[test]
+    fn scripts_run_out() {
+        let mut input = ScriptedInput::new(vec![parse_input_event("q").unwrap()]);
+        assert!(matches!(
+            input.poll_event(),
+            Ok(Some(InputEvent::Keyboard(KeyEvent::Char('q'))))
+        ));
+        assert!(matches!(input.poll_event(), Err(Error::EndOfInput)));
+    }
+
+    #[test]
+    fn events_parse_from_names_and_clicks() {
+        assert!(matches!(
+            parse_input_event("enter"),
+            Ok(InputEvent::Keyboard(KeyEvent::Enter))
+        ));
+        assert!(matches!(
+            parse_input_event("click:13,7"),
+            Ok(InputEvent::Mouse(MouseEvent::Press(
+                MouseButton::Left,
+                13,
+                7
+            )))
+        ));
+        assert!(parse_input_event("click:13").is_err());
+        assert!(parse_input_event("jump").is_err());
+    }
+}
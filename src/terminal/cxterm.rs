@@ -1,90 +1,310 @@
-use std::io::Write;
+use std::ops::Range;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crossterm::{cursor, input, screen, terminal};
-use crossterm::{Output, QueueableCommand};
+use crossterm::input;
+use log::debug;
 
-use crate::coord::Coord;
+use crate::coord::{column_name, Coord};
 use crate::errors::{Error, Result};
-use crate::terminal::settings::RenderSettings;
-use crate::terminal::GameAction;
+use crate::render::UiState;
+use crate::ricracroe::{RRRBoard, RRRCell, SwapAnswer, SwapOffer};
+use crate::terminal::backend::{InputSource, Screen};
+use crate::terminal::keymap::{KeyAction, Keymap};
+use crate::terminal::menu::{Menu, MenuAction};
+use crate::terminal::settings::{RenderSettings, MAX_MSGLOG_LINES, SCOREBOARD_WIDTH};
+use crate::terminal::theme::Theme;
+use crate::terminal::{GameAction, ReplayAction};
 
-const MAX_MSGLOG_LINES: usize = 4;
+// How often to look for input and terminal size changes
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
 
-pub struct CxTerm<W: Write> {
-    _raw: screen::RawScreen,
-    input: input::TerminalInput,
-    reader: input::SyncReader,
-    writer: W,
+pub struct CxTerm<S: Screen, I: InputSource> {
+    screen: S,
+    input: I,
     settings: RenderSettings,
+    theme: Theme,
+    keymap: Keymap,
     active_cell: Option<Coord>,
+    // Last board drawn, so it can be redrawn as the cursor moves
+    board: Option<(RRRBoard, UiState)>,
+    // Everything else on screen, so it can all be redrawn after a resize
+    title: String,
+    status: String,
+    msglog: String,
+    scoreboard: String,
+    clocks: String,
+    terminal_size: Option<Coord>,
+    // A cell being typed in, e.g. "b2", while the input line is open
+    typed: Option<String>,
+    // The menu on screen, if any, in place of the game
+    menu: Option<Menu>,
 }
 
-impl<W: Write> Drop for CxTerm<W> {
+impl<S: Screen, I: InputSource> Drop for CxTerm<S, I> {
     #[allow(unused_must_use)]
     fn drop(&mut self) {
-        self.input.disable_mouse_mode();
-        self.writer.queue(cursor::Show);
-        self.writer.queue(input::DisableMouseCapture);
-        self.writer.queue(screen::LeaveAlternateScreen);
-        self.writer.flush();
+        self.screen.leave();
     }
 }
 
-impl<W: Write> CxTerm<W> {
-    pub fn new(settings: RenderSettings, writer: W) -> Result<Self> {
-        let input = input::input();
-        let reader = input.read_sync();
+impl<S: Screen, I: InputSource> CxTerm<S, I> {
+    pub fn new(
+        settings: RenderSettings,
+        theme: Theme,
+        keymap: Keymap,
+        screen: S,
+        input: I,
+    ) -> Result<Self> {
         Ok(Self {
-            _raw: screen::RawScreen::into_raw_mode().map_err(Error::from)?,
+            screen,
             input,
-            reader,
-            writer,
             settings,
+            theme,
+            keymap,
             active_cell: None,
+            board: None,
+            title: String::new(),
+            status: String::new(),
+            msglog: String::new(),
+            scoreboard: String::new(),
+            clocks: String::new(),
+            terminal_size: None,
+            typed: None,
+            menu: None,
         })
     }
 
     pub fn reset_display(&mut self) -> Result<()> {
-        self.writer
-            .queue(screen::EnterAlternateScreen)
-            .map_err(Error::from)?
-            .queue(input::EnableMouseCapture)
-            .map_err(Error::from)?
-            .queue(cursor::Hide)
-            .map_err(Error::from)?
-            .queue(terminal::Clear(terminal::ClearType::All))
-            .map_err(Error::from)?;
+        self.screen.enter()?;
+        self.check_terminal_size()?;
         Ok(())
     }
 
+    // Lay everything out again if the terminal has changed size since last time
+    fn check_terminal_size(&mut self) -> Result<()> {
+        let size = self.screen.size()?;
+        if self.terminal_size != Some(size) {
+            debug!("Terminal size is now {}x{}", size.x, size.y);
+            self.terminal_size = Some(size);
+            self.settings.set_terminal_size(size);
+            self.redraw_all()?;
+        }
+        Ok(())
+    }
+
+    fn redraw_all(&mut self) -> Result<()> {
+        self.screen.clear_all()?;
+        if self.menu.is_some() {
+            self.draw_menu()?;
+        } else if !self.settings.fits_terminal() {
+            let required = self.settings.get_required_size();
+            let message = format!(
+                "Terminal too small, need {}x{}. Press {} to quit.",
+                required.x,
+                required.y,
+                self.keymap.describe(KeyAction::Quit)
+            );
+            self.screen.move_to(&Coord::default())?;
+            self.screen.print(&message)?;
+        } else {
+            let title = self.title.clone();
+            let status = self.status.clone();
+            let msglog = self.msglog.clone();
+            self.write_title(&title)?;
+            self.write_status(&status)?;
+            self.write_msglog(&msglog)?;
+            self.draw_typed()?;
+            self.redraw_board(None)?;
+            self.draw_scoreboard()?;
+        }
+        self.commit()
+    }
+
+    /// Clear away the last game (or menu) to start a new one on a board
+    /// `board_size` cells across
+    pub fn start_game(&mut self, board_size: usize) -> Result<()> {
+        self.settings.set_board_size(board_size);
+        self.active_cell = None;
+        self.board = None;
+        self.title.clear();
+        self.status.clear();
+        self.msglog.clear();
+        self.scoreboard.clear();
+        self.clocks.clear();
+        self.typed = None;
+        self.menu = None;
+        self.redraw_all()
+    }
+
+    // Top left of the menu, which is centered in the terminal
+    fn get_menu_origin(&self, menu: &Menu) -> Coord {
+        let width = menu.width();
+        // Title, a blank line, the items, a blank line, and the key reminder
+        let height = menu.items.len() + 4;
+        let terminal_size = self.terminal_size.unwrap_or_default();
+        Coord {
+            x: terminal_size.x.saturating_sub(width) / 2,
+            y: terminal_size.y.saturating_sub(height) / 2,
+        }
+    }
+
+    fn draw_menu(&mut self) -> Result<()> {
+        let menu = match self.menu {
+            Some(ref menu) => menu.clone(),
+            None => return Ok(()),
+        };
+        let origin = self.get_menu_origin(&menu);
+        self.screen.move_to(&origin)?;
+        self.screen.print(&menu.title)?;
+        for (num, (line, item)) in menu.lines().iter().zip(menu.items.iter()).enumerate() {
+            let line_origin = origin + Coord { x: 0, y: num + 2 };
+            self.clear_line(&line_origin)?;
+            self.screen.move_to(&line_origin)?;
+            if num == menu.selected {
+                self.screen.print_styled(line, &self.theme.cursor)?;
+            } else if !item.enabled {
+                self.screen.print_styled(line, &self.theme.grid)?;
+            } else {
+                self.screen.print(line)?;
+            }
+        }
+        let keys = format!(
+            "{}/{} to choose, {}/{} to change, {} to pick, {} to go back",
+            self.keymap.describe(KeyAction::Up),
+            self.keymap.describe(KeyAction::Down),
+            self.keymap.describe(KeyAction::Left),
+            self.keymap.describe(KeyAction::Right),
+            self.keymap.describe(KeyAction::Select),
+            self.keymap.describe(KeyAction::Quit),
+        );
+        let keys_origin = Coord {
+            x: self
+                .terminal_size
+                .unwrap_or_default()
+                .x
+                .saturating_sub(keys.chars().count())
+                / 2,
+            y: origin.y + menu.items.len() + 3,
+        };
+        self.screen.move_to(&keys_origin)?;
+        self.screen.print_styled(&keys, &self.theme.grid)
+    }
+
+    /// Show `menu` until something is picked or changed, or the player backs
+    /// out. Keys and mouse clicks both work.
+    pub fn run_menu(&mut self, menu: &mut Menu) -> Result<MenuAction> {
+        self.menu = Some(menu.clone());
+        self.redraw_all()?;
+        self.hide_cursor()?;
+        let action = self.get_menu_action();
+        if let Some(shown) = self.menu.take() {
+            *menu = shown;
+        }
+        action
+    }
+
+    fn get_menu_action(&mut self) -> Result<MenuAction> {
+        loop {
+            self.draw_menu()?;
+            self.commit()?;
+            let event = match self.poll_input_event(None)? {
+                Some(event) => event,
+                None => continue,
+            };
+            let menu = match self.menu {
+                Some(ref mut menu) => menu,
+                None => return Ok(MenuAction::Back),
+            };
+            match event {
+                input::InputEvent::Keyboard(key) => match self.keymap.action(&key) {
+                    Some(KeyAction::Up) => menu.move_selection(-1),
+                    Some(KeyAction::Down) => menu.move_selection(1),
+                    Some(direction @ KeyAction::Left) | Some(direction @ KeyAction::Right) => {
+                        let step = if direction == KeyAction::Left { -1 } else { 1 };
+                        if menu.change_choice(step) {
+                            return Ok(MenuAction::Changed(menu.selected));
+                        }
+                    }
+                    Some(KeyAction::Select) => {
+                        if menu.change_choice(1) {
+                            return Ok(MenuAction::Changed(menu.selected));
+                        }
+                        return Ok(MenuAction::Chosen(menu.selected));
+                    }
+                    Some(KeyAction::Quit) => return Ok(MenuAction::Back),
+                    _ => (),
+                },
+                input::InputEvent::Mouse(input::MouseEvent::Press(_, term_x, term_y)) => {
+                    let menu = menu.clone();
+                    let origin = self.get_menu_origin(&menu);
+                    let (term_x, term_y) = (term_x as usize, term_y as usize);
+                    if term_x < origin.x || term_y < origin.y + 2 {
+                        continue;
+                    }
+                    let index = term_y - origin.y - 2;
+                    if index >= menu.items.len() || !menu.items[index].enabled {
+                        continue;
+                    }
+                    if let Some(ref mut shown) = self.menu {
+                        shown.selected = index;
+                        if shown.change_choice(1) {
+                            return Ok(MenuAction::Changed(index));
+                        }
+                    }
+                    return Ok(MenuAction::Chosen(index));
+                }
+                _ => (),
+            }
+        }
+    }
+
     pub fn draw_line(&mut self, coord: &Coord, text: &str) -> Result<()> {
+        // Don't scribble over the "too small" message
+        if !self.settings.fits_terminal() {
+            return Ok(());
+        }
         self.clear_line(coord)?;
-        self.writer
-            .queue(cursor::MoveTo(coord.x as u16, coord.y as u16))
-            .map_err(Error::from)?
-            .queue(Output(text.to_string()))
-            .map_err(Error::from)?;
-        Ok(())
+        self.screen.move_to(coord)?;
+        self.screen.print(text)
     }
 
     pub fn clear_line(&mut self, coord: &Coord) -> Result<()> {
-        self.writer
-            .queue(cursor::MoveTo(coord.x as u16, coord.y as u16))
-            .map_err(Error::from)?
-            .queue(terminal::Clear(terminal::ClearType::CurrentLine))
-            .map_err(Error::from)?;
-        Ok(())
+        self.screen.move_to(coord)?;
+        self.screen.clear_line()
     }
 
     pub fn write_title(&mut self, title: &str) -> Result<()> {
+        self.title = title.to_string();
         self.draw_line(&self.settings.get_title_origin(), title)
     }
 
     pub fn write_status(&mut self, status: &str) -> Result<()> {
-        self.draw_line(&self.settings.get_status_origin(), status)
+        self.status = status.to_string();
+        self.draw_line(&self.settings.get_status_origin(), status)?;
+        self.draw_clocks()
+    }
+
+    /// Show the players' clocks at the end of the status line
+    pub fn write_clocks(&mut self, clocks: &str) -> Result<()> {
+        self.clocks = clocks.to_string();
+        self.draw_clocks()
+    }
+
+    fn draw_clocks(&mut self) -> Result<()> {
+        if self.clocks.is_empty() || !self.settings.fits_terminal() {
+            return Ok(());
+        }
+        let origin = self.settings.get_clock_origin(self.clocks.chars().count());
+        self.screen.move_to(&origin)?;
+        self.screen.print(&self.clocks)
     }
 
     pub fn clear_msglog(&mut self) -> Result<()> {
+        self.msglog.clear();
+        if !self.settings.fits_terminal() {
+            return Ok(());
+        }
         // Clear *all* msglog lines
         for num in 0..MAX_MSGLOG_LINES {
             let coord = self.settings.get_msglog_origin() + Coord { x: 0, y: num };
@@ -95,6 +315,7 @@ impl<W: Write> CxTerm<W> {
 
     pub fn write_msglog(&mut self, status: &str) -> Result<()> {
         self.clear_msglog()?;
+        self.msglog = status.to_string();
         // Write *up to* max number of msglog lines
         for (num, line) in status.lines().enumerate() {
             if num >= MAX_MSGLOG_LINES {
@@ -106,37 +327,169 @@ impl<W: Write> CxTerm<W> {
         Ok(())
     }
 
-    pub fn write_rendered_board_row(&mut self, row: usize, line_draw: &str) -> Result<()> {
-        let mut board_row_origin = self.settings.get_board_origin();
-        board_row_origin.y += row;
-        self.draw_line(&board_row_origin, line_draw)
+    /// Show `scoreboard` beside the board, a line at a time
+    pub fn write_scoreboard(&mut self, scoreboard: &str) -> Result<()> {
+        self.scoreboard = scoreboard.to_string();
+        self.draw_scoreboard()
     }
 
-    pub fn blink_cursor(&mut self, coord: &Coord) -> Result<()> {
-        self.writer
-            .queue(cursor::MoveTo(coord.x as u16, coord.y as u16))
-            .map_err(Error::from)?
-            .queue(cursor::Show)
-            .map_err(Error::from)?
-            .queue(cursor::EnableBlinking)
-            .map_err(Error::from)?;
+    // Drawn without clearing whole lines, since they're shared with the board
+    fn draw_scoreboard(&mut self) -> Result<()> {
+        if !self.settings.fits_terminal() {
+            return Ok(());
+        }
+        let origin = self.settings.get_scoreboard_origin();
+        for (num, line) in self.scoreboard.lines().enumerate() {
+            let text: String = line.chars().take(SCOREBOARD_WIDTH).collect();
+            self.screen.move_to(&(origin + Coord { x: 0, y: num }))?;
+            self.screen
+                .print(&format!("{:<width$}", text, width = SCOREBOARD_WIDTH))?;
+        }
         Ok(())
     }
 
-    pub fn hide_cursor(&mut self) -> Result<()> {
-        self.writer.queue(cursor::Hide).map_err(Error::from)?;
+    /// Draw the board, and remember it for redrawing when the cursor moves
+    pub fn draw_board(&mut self, board: &RRRBoard, ui: UiState) -> Result<()> {
+        self.board = Some((board.clone(), ui));
+        self.redraw_board(None)
+    }
+
+    fn redraw_board(&mut self, cursor: Option<Coord>) -> Result<()> {
+        if let Some(cursor) = cursor {
+            self.settings.scroll_to(&cursor);
+        }
+        let rendered = match self.board {
+            Some((ref board, ref ui)) => {
+                let mut ui = ui.clone();
+                ui.cursor = cursor;
+                self.settings.get_renderer().render(board, &ui)
+            }
+            None => return Ok(()),
+        };
+        if !self.settings.fits_terminal() {
+            return Ok(());
+        }
+
+        let origin = self.settings.get_board_origin();
+        let scroll = self.settings.get_scroll_offset();
+        let view = self.settings.get_view_size();
+        for (num, row) in rendered.iter().skip(scroll.y).take(view.y).enumerate() {
+            let row_origin = origin + Coord { x: 0, y: num };
+            self.clear_line(&row_origin)?;
+            self.screen.move_to(&row_origin)?;
+            for glyph in row.iter().skip(scroll.x).take(view.x) {
+                let glyph_style = self.theme.glyph_style(glyph);
+                self.screen
+                    .print_styled(glyph.ch.encode_utf8(&mut [0; 4]), &glyph_style)?;
+            }
+        }
+        self.draw_board_labels()?;
+        self.draw_scrollbars()?;
+        self.draw_scoreboard()
+    }
+
+    // Row and column numbers for the cells in view, so they stay put while
+    // the board scrolls underneath them
+    fn draw_board_labels(&mut self) -> Result<()> {
+        let (first, cells) = self.settings.get_viewport();
+        let label = self.settings.get_label_size();
+        let origin = self.settings.get_board_origin();
+
+        // Column letters are written downwards, so wide ones don't run together
+        for line in 0..label.y {
+            let line_origin = Coord {
+                x: 0,
+                y: origin.y - label.y + line,
+            };
+            self.clear_line(&line_origin)?;
+            for x in first.x..first.x + cells.x {
+                let letters = format!("{: >width$}", column_name(x), width = label.y);
+                let term_x = self
+                    .settings
+                    .cell_coord_to_term_coord(&Coord { x, y: first.y })
+                    .x;
+                self.screen.move_to(&Coord {
+                    x: term_x,
+                    y: line_origin.y,
+                })?;
+                self.screen
+                    .print_styled(&letters[line..=line], &self.theme.grid)?;
+            }
+        }
+
+        for y in first.y..first.y + cells.y {
+            let term_y = self
+                .settings
+                .cell_coord_to_term_coord(&Coord { x: first.x, y })
+                .y;
+            let number = format!("{: >width$}", y + 1, width = label.x - 1);
+            self.screen.move_to(&Coord {
+                x: origin.x - label.x,
+                y: term_y,
+            })?;
+            self.screen.print_styled(&number, &self.theme.grid)?;
+        }
         Ok(())
     }
 
-    pub fn commit(&mut self) -> Result<()> {
-        self.writer.flush().map_err(Error::from)?;
+    // Bars along the right and bottom of the board showing which part of it
+    // is in view, for boards too big to show all at once
+    fn draw_scrollbars(&mut self) -> Result<()> {
+        let (first, cells) = self.settings.get_viewport();
+        let (scroll_x, scroll_y) = self.settings.get_scrolling();
+        let origin = self.settings.get_board_origin();
+        let view = self.settings.get_view_size();
+        let board_size = self.settings.get_board_size();
+
+        if scroll_y {
+            let thumb = scrollbar_thumb(first.y, cells.y, board_size, view.y);
+            for num in 0..view.y {
+                let ch = if thumb.contains(&num) { "┃" } else { "│" };
+                self.screen.move_to(&Coord {
+                    x: origin.x + view.x,
+                    y: origin.y + num,
+                })?;
+                self.screen.print_styled(ch, &self.theme.grid)?;
+            }
+        }
+        if scroll_x {
+            let bar_origin = origin + Coord { x: 0, y: view.y };
+            let thumb = scrollbar_thumb(first.x, cells.x, board_size, view.x);
+            let bar: String = (0..view.x)
+                .map(|num| if thumb.contains(&num) { '━' } else { '─' })
+                .collect();
+            self.clear_line(&bar_origin)?;
+            self.screen.move_to(&bar_origin)?;
+            self.screen.print_styled(&bar, &self.theme.grid)?;
+        }
         Ok(())
     }
 
+    pub fn blink_cursor(&mut self, coord: &Coord) -> Result<()> {
+        self.screen.move_to(coord)?;
+        self.screen.show_cursor()
+    }
+
+    pub fn hide_cursor(&mut self) -> Result<()> {
+        self.screen.hide_cursor()
+    }
+
+    pub fn commit(&mut self) -> Result<()> {
+        self.screen.flush()
+    }
+
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+
     pub fn get_active_board_cell(&self) -> Coord {
         self.active_cell.unwrap_or_default()
     }
 
+    pub fn set_active_board_cell(&mut self, coord: Coord) {
+        self.active_cell = Some(coord);
+    }
+
     pub fn update_active_board_cell(&mut self, update: fn(&Coord) -> Coord) {
         let active = self.get_active_board_cell();
         let board_size = self.settings.get_board_size();
@@ -153,59 +506,349 @@ impl<W: Write> CxTerm<W> {
         self.active_cell = Some(next);
     }
 
+    // Wait for the next input event, or None if the terminal was resized or
+    // `deadline` passed first
+    fn poll_input_event(&mut self, deadline: Option<Instant>) -> Result<Option<input::InputEvent>> {
+        loop {
+            if let Some(event) = self.input.poll_event()? {
+                return Ok(Some(event));
+            }
+            let before = self.terminal_size;
+            self.check_terminal_size()?;
+            if self.terminal_size != before {
+                return Ok(None);
+            }
+            let wait = match deadline {
+                Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                    Some(left) if left > Duration::from_millis(0) => left.min(POLL_INTERVAL),
+                    _ => return Ok(None),
+                },
+                None => POLL_INTERVAL,
+            };
+            thread::sleep(wait);
+        }
+    }
+
     pub fn get_input_event(&mut self) -> Result<input::InputEvent> {
-        self.reader.next().ok_or(Error::InvalidGameInput)
+        loop {
+            if let Some(event) = self.poll_input_event(None)? {
+                return Ok(event);
+            }
+        }
     }
 
-    pub fn get_game_action(&mut self) -> Result<GameAction> {
-        let mut action: Option<GameAction> = None;
-        while action.is_none() {
-            let active_cell = self.get_active_board_cell();
-            let term_coord = self.settings.cell_coord_to_term_coord(&active_cell);
-            self.blink_cursor(&term_coord)?;
-            self.commit()?;
+    // Every key binding in a box over the middle of the screen
+    fn show_help(&mut self) -> Result<()> {
+        let lines = self.keymap.help_lines();
+        self.show_text("Keys", &lines)
+    }
+
+    /// `lines` of text in a box over the middle of the screen, until a key is
+    /// pressed
+    pub fn show_text(&mut self, title: &str, text: &[String]) -> Result<()> {
+        let mut lines = vec![title.to_string(), String::new()];
+        lines.extend(text.iter().cloned());
+        lines.push(String::new());
+        lines.push("Press any key to close.".to_string());
 
-            match self.get_input_event() {
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Left)) => {
-                    self.update_active_board_cell(|coord| *coord - Coord { x: 1, y: 0 });
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 4;
+        let height = lines.len() + 2;
+        let terminal_size = self.terminal_size.unwrap_or_default();
+        let origin = Coord {
+            x: terminal_size.x.saturating_sub(width) / 2,
+            y: terminal_size.y.saturating_sub(height) / 2,
+        };
+
+        let border = "─".repeat(width - 2);
+        let mut rows = vec![format!("╭{}╮", border)];
+        for line in &lines {
+            rows.push(format!("│ {:<width$} │", line, width = width - 4));
+        }
+        rows.push(format!("╰{}╯", border));
+        for (num, row) in rows.iter().enumerate() {
+            self.screen.move_to(&(origin + Coord { x: 0, y: num }))?;
+            self.screen.print_styled(row, &self.theme.grid)?;
+        }
+        self.hide_cursor()?;
+        self.commit()?;
+
+        self.get_input_event()?;
+        self.redraw_all()
+    }
+
+    // The input line, with the cursor left at the end of it
+    fn draw_typed(&mut self) -> Result<Coord> {
+        let origin = self.settings.get_input_origin();
+        match self.typed {
+            Some(ref typed) => {
+                let line = format!("Move: {}", typed);
+                let end = origin
+                    + Coord {
+                        x: line.chars().count(),
+                        y: 0,
+                    };
+                self.draw_line(&origin, &line)?;
+                Ok(end)
+            }
+            None => {
+                self.clear_line(&origin)?;
+                Ok(origin)
+            }
+        }
+    }
+
+    // Keys go to the input line while it's open. Returns the move once
+    // a valid cell has been entered.
+    fn handle_typed_key(&mut self, key: input::KeyEvent) -> Result<Option<Coord>> {
+        let typed = match self.typed {
+            Some(ref mut typed) => typed,
+            None => return Ok(None),
+        };
+        match key {
+            input::KeyEvent::Char(ch) if !ch.is_control() => typed.push(ch),
+            input::KeyEvent::Backspace => {
+                typed.pop();
+            }
+            input::KeyEvent::Esc => self.typed = None,
+            input::KeyEvent::Enter => {
+                let board_size = self.settings.get_board_size();
+                match typed.parse::<Coord>() {
+                    Ok(coord) if coord.x < board_size && coord.y < board_size => {
+                        self.typed = None;
+                        return Ok(Some(coord));
+                    }
+                    _ => {
+                        let message =
+                            format!("'{}' isn't on the board, try e.g. b2 or 1,1.", typed);
+                        self.write_msglog(&message)?;
+                    }
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Right)) => {
-                    self.update_active_board_cell(|coord| *coord + Coord { x: 1, y: 0 });
+            }
+            _ => (),
+        }
+        Ok(None)
+    }
+
+    /// How to step through a recorded game
+    pub fn get_replay_action(&mut self) -> Result<ReplayAction> {
+        loop {
+            let key = match self.get_input_event()? {
+                input::InputEvent::Keyboard(key) => key,
+                _ => continue,
+            };
+            let action = self.keymap.action(&key);
+            if action == Some(KeyAction::Quit) || action == Some(KeyAction::Menu) {
+                return Ok(ReplayAction::Quit);
+            }
+            if !self.settings.fits_terminal() {
+                continue;
+            }
+            match (action, key) {
+                (Some(KeyAction::Left), _) | (Some(KeyAction::Up), _) => {
+                    return Ok(ReplayAction::Back)
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Up)) => {
-                    self.update_active_board_cell(|coord| *coord - Coord { x: 0, y: 1 });
+                (Some(KeyAction::Right), _)
+                | (Some(KeyAction::Down), _)
+                | (Some(KeyAction::Select), _) => return Ok(ReplayAction::Forward),
+                (_, input::KeyEvent::Home) => return Ok(ReplayAction::Start),
+                (_, input::KeyEvent::End) => return Ok(ReplayAction::End),
+                (Some(KeyAction::Help), _) => self.show_help()?,
+                _ => (),
+            }
+        }
+    }
+
+    /// What to do once a game is over: a rematch, back to the menu, or quit
+    pub fn get_end_of_game_action(&mut self) -> Result<GameAction> {
+        self.hide_cursor()?;
+        self.commit()?;
+        loop {
+            let key = match self.get_input_event()? {
+                input::InputEvent::Keyboard(key) => key,
+                _ => continue,
+            };
+            match self.keymap.action(&key) {
+                Some(KeyAction::Rematch) if self.settings.fits_terminal() => {
+                    return Ok(GameAction::Rematch)
+                }
+                Some(KeyAction::Menu) if self.settings.fits_terminal() => {
+                    return Ok(GameAction::Menu)
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Down)) => {
-                    self.update_active_board_cell(|coord| *coord + Coord { x: 0, y: 1 });
+                Some(KeyAction::Help) if self.settings.fits_terminal() => self.show_help()?,
+                Some(KeyAction::Quit) => return Ok(GameAction::Quit),
+                _ => (),
+            }
+        }
+    }
+
+    /// How the player offered a swap answers: with a side to play, or two
+    /// more stones if that's on offer. Like get_game_action, returns Tick
+    /// after `timeout`.
+    pub fn get_swap_action(
+        &mut self,
+        offer: SwapOffer,
+        timeout: Option<Duration>,
+    ) -> Result<GameAction> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        self.hide_cursor()?;
+        self.commit()?;
+        loop {
+            let key = match self.poll_input_event(deadline)? {
+                Some(input::InputEvent::Keyboard(key)) => key,
+                Some(_) => continue,
+                None if deadline.map(|deadline| Instant::now() >= deadline) == Some(true) => {
+                    return Ok(GameAction::Tick)
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Enter)) => {
-                    action = Some(GameAction::TakeTurn(active_cell));
+                None => continue,
+            };
+            let fits = self.settings.fits_terminal();
+            let answer = match self.keymap.action(&key) {
+                Some(KeyAction::ChooseX) => SwapAnswer::Play(RRRCell::X),
+                Some(KeyAction::ChooseO) => SwapAnswer::Play(RRRCell::O),
+                Some(KeyAction::PlaceTwo) if offer.can_place_two => SwapAnswer::PlaceTwo,
+                Some(KeyAction::Help) if fits => {
+                    self.show_help()?;
+                    continue;
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Char(' '))) => {
-                    action = Some(GameAction::TakeTurn(active_cell));
+                Some(KeyAction::Menu) if fits => return Ok(GameAction::Menu),
+                Some(KeyAction::Quit) => return Ok(GameAction::Quit),
+                _ => continue,
+            };
+            if fits {
+                return Ok(GameAction::AnswerSwap(answer));
+            }
+        }
+    }
+
+    /// The next thing the player to move wants to do. With a `timeout`, gives
+    /// up waiting after that long and returns Tick, e.g. to update clocks.
+    pub fn get_game_action(&mut self, timeout: Option<Duration>) -> Result<GameAction> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let mut action: Option<GameAction> = None;
+        while action.is_none() {
+            let active_cell = self.get_active_board_cell();
+            if self.settings.fits_terminal() {
+                self.redraw_board(Some(active_cell))?;
+                let input_end = self.draw_typed()?;
+                if self.typed.is_some() {
+                    self.blink_cursor(&input_end)?;
+                } else {
+                    let term_coord = self.settings.cell_coord_to_term_coord(&active_cell);
+                    self.blink_cursor(&term_coord)?;
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Char('q'))) => {
-                    action = Some(GameAction::Quit);
+            } else {
+                self.hide_cursor()?;
+            }
+            self.commit()?;
+
+            let event = match self.poll_input_event(deadline)? {
+                Some(event) => event,
+                None => {
+                    if deadline.map(|deadline| Instant::now() >= deadline) == Some(true) {
+                        action = Some(GameAction::Tick);
+                    }
+                    continue;
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Char('Q'))) => {
-                    action = Some(GameAction::Quit);
+            };
+            // Nothing but quitting makes sense until the board fits again
+            if !self.settings.fits_terminal() {
+                if let input::InputEvent::Keyboard(key) = event {
+                    if self.keymap.action(&key) == Some(KeyAction::Quit) {
+                        action = Some(GameAction::Quit);
+                    }
                 }
-                Ok(input::InputEvent::Keyboard(input::KeyEvent::Esc)) => {
-                    action = Some(GameAction::Quit);
+                continue;
+            }
+
+            if self.typed.is_some() {
+                if let input::InputEvent::Keyboard(key) = event {
+                    if let Some(coord) = self.handle_typed_key(key)? {
+                        action = Some(GameAction::TakeTurn(coord));
+                    }
                 }
-                Ok(input::InputEvent::Mouse(input::MouseEvent::Press(_, term_x, term_y))) => {
+                continue;
+            }
+
+            match event {
+                input::InputEvent::Keyboard(key) => match self.keymap.action(&key) {
+                    Some(KeyAction::Left) => {
+                        self.update_active_board_cell(|coord| *coord - Coord { x: 1, y: 0 });
+                    }
+                    Some(KeyAction::Right) => {
+                        self.update_active_board_cell(|coord| *coord + Coord { x: 1, y: 0 });
+                    }
+                    Some(KeyAction::Up) => {
+                        self.update_active_board_cell(|coord| *coord - Coord { x: 0, y: 1 });
+                    }
+                    Some(KeyAction::Down) => {
+                        self.update_active_board_cell(|coord| *coord + Coord { x: 0, y: 1 });
+                    }
+                    Some(KeyAction::Select) => {
+                        action = Some(GameAction::TakeTurn(active_cell));
+                    }
+                    Some(KeyAction::Hint) => {
+                        action = Some(GameAction::Hint);
+                    }
+                    Some(KeyAction::Analyze) => {
+                        action = Some(GameAction::ToggleAnalysis);
+                    }
+                    Some(KeyAction::ToggleSymbol) => {
+                        action = Some(GameAction::ToggleSymbol);
+                    }
+                    Some(KeyAction::TypeMove) => {
+                        self.typed = Some(String::new());
+                    }
+                    Some(KeyAction::Help) => {
+                        self.show_help()?;
+                    }
+                    Some(KeyAction::Menu) => {
+                        action = Some(GameAction::Menu);
+                    }
+                    Some(KeyAction::Quit) => {
+                        action = Some(GameAction::Quit);
+                    }
+                    Some(cell_key @ KeyAction::Cell(_)) if self.settings.get_board_size() == 3 => {
+                        action = cell_key.cell().map(GameAction::TakeTurn);
+                    }
+                    _ => {
+                        // Digits that aren't bound to anything start typing a cell
+                        if let input::KeyEvent::Char(ch) = key {
+                            if ch.is_ascii_digit() {
+                                self.typed = Some(ch.to_string());
+                            }
+                        }
+                    }
+                },
+                input::InputEvent::Mouse(input::MouseEvent::Press(_, term_x, term_y)) => {
                     let game_coord = self.settings.term_coord_to_cell_coord(&Coord {
                         x: term_x as usize,
                         y: term_y as usize,
                     });
-                    self.hide_cursor()?;
-                    action = Some(GameAction::TakeTurn(game_coord));
+                    if let Some(game_coord) = game_coord {
+                        self.hide_cursor()?;
+                        action = Some(GameAction::TakeTurn(game_coord));
+                    }
                 }
                 _ => (),
             }
         }
+        self.redraw_board(None)?;
+        self.draw_typed()?;
         self.hide_cursor()?;
         self.commit()?;
         action.ok_or(Error::InvalidGameInput)
     }
 }
+
+// Which part of a scrollbar `length` characters long stands for the `visible`
+// cells starting at `first`, out of `total`
+fn scrollbar_thumb(first: usize, visible: usize, total: usize, length: usize) -> Range<usize> {
+    let start = first * length / total;
+    let end = ((first + visible) * length).div_ceil(total);
+    start..end.max(start + 1).min(length)
+}
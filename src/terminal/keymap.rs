@@ -0,0 +1,418 @@
+use std::fs;
+use std::path::Path;
+
+use crossterm::input::KeyEvent;
+
+use crate::coord::Coord;
+use crate::errors::{Error, Result};
+
+/// Something a key can be bound to during a game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAction {
+    Left,
+    Right,
+    Up,
+    Down,
+    Select,
+    Hint,
+    Analyze,
+    TypeMove,
+    Help,
+    /// Play again once a game is over, with the other side starting
+    Rematch,
+    /// Back to the main menu
+    Menu,
+    /// Pick a side when offered a swap
+    ChooseX,
+    ChooseO,
+    /// Place two more stones instead of picking a side, under swap2
+    PlaceTwo,
+    /// Switch what to place: X or O in Order and Chaos, or the next number
+    /// in numerical tic-tac-toe
+    ToggleSymbol,
+    Quit,
+    /// Play a cell of a 3x3 board, numbered like a number pad: 7 is the top
+    /// left cell and 3 the bottom right
+    Cell(u8),
+}
+
+impl KeyAction {
+    fn all() -> Vec<KeyAction> {
+        let mut actions = vec![
+            KeyAction::Left,
+            KeyAction::Right,
+            KeyAction::Up,
+            KeyAction::Down,
+            KeyAction::Select,
+            KeyAction::Hint,
+            KeyAction::Analyze,
+            KeyAction::TypeMove,
+            KeyAction::Help,
+            KeyAction::Rematch,
+            KeyAction::Menu,
+            KeyAction::ChooseX,
+            KeyAction::ChooseO,
+            KeyAction::PlaceTwo,
+            KeyAction::ToggleSymbol,
+            KeyAction::Quit,
+        ];
+        actions.extend((1..=9).map(KeyAction::Cell));
+        actions
+    }
+
+    /// Name used in the config file
+    fn name(self) -> String {
+        match self {
+            KeyAction::Left => "left".to_string(),
+            KeyAction::Right => "right".to_string(),
+            KeyAction::Up => "up".to_string(),
+            KeyAction::Down => "down".to_string(),
+            KeyAction::Select => "select".to_string(),
+            KeyAction::Hint => "hint".to_string(),
+            KeyAction::Analyze => "analyze".to_string(),
+            KeyAction::TypeMove => "type-move".to_string(),
+            KeyAction::Help => "help".to_string(),
+            KeyAction::Rematch => "rematch".to_string(),
+            KeyAction::Menu => "menu".to_string(),
+            KeyAction::ChooseX => "choose-x".to_string(),
+            KeyAction::ChooseO => "choose-o".to_string(),
+            KeyAction::PlaceTwo => "place-two".to_string(),
+            KeyAction::ToggleSymbol => "toggle-symbol".to_string(),
+            KeyAction::Quit => "quit".to_string(),
+            KeyAction::Cell(num) => format!("cell-{}", num),
+        }
+    }
+
+    fn description(self) -> String {
+        match self {
+            KeyAction::Left => "Move left".to_string(),
+            KeyAction::Right => "Move right".to_string(),
+            KeyAction::Up => "Move up".to_string(),
+            KeyAction::Down => "Move down".to_string(),
+            KeyAction::Select => "Play the selected cell".to_string(),
+            KeyAction::Hint => "Hint".to_string(),
+            KeyAction::Analyze => "Toggle analysis".to_string(),
+            KeyAction::TypeMove => "Type a cell, e.g. b2".to_string(),
+            KeyAction::Help => "This help".to_string(),
+            KeyAction::Rematch => "Rematch, once a game ends".to_string(),
+            KeyAction::Menu => "Back to the main menu".to_string(),
+            KeyAction::ChooseX => "Play X, when offered a swap".to_string(),
+            KeyAction::ChooseO => "Play O, when offered a swap".to_string(),
+            KeyAction::PlaceTwo => "Place two more stones, under swap2".to_string(),
+            KeyAction::ToggleSymbol => {
+                "Switch what to place, in Order and Chaos or numerical games".to_string()
+            }
+            KeyAction::Quit => "Quit".to_string(),
+            KeyAction::Cell(num) => format!("Play cell {} (3x3 only)", num),
+        }
+    }
+
+    /// The board cell a number pad key stands for
+    pub fn cell(self) -> Option<Coord> {
+        match self {
+            KeyAction::Cell(num) if (1..=9).contains(&num) => {
+                let index = (num - 1) as usize;
+                Some(Coord {
+                    x: index % 3,
+                    y: 2 - index / 3,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+const KEY_NAMES: &[(&str, KeyEvent)] = &[
+    ("Left", KeyEvent::Left),
+    ("Right", KeyEvent::Right),
+    ("Up", KeyEvent::Up),
+    ("Down", KeyEvent::Down),
+    ("Enter", KeyEvent::Enter),
+    ("Space", KeyEvent::Char(' ')),
+    ("Comma", KeyEvent::Char(',')),
+    ("Esc", KeyEvent::Esc),
+    ("Tab", KeyEvent::Tab),
+    ("Backspace", KeyEvent::Backspace),
+    ("Home", KeyEvent::Home),
+    ("End", KeyEvent::End),
+    ("PageUp", KeyEvent::PageUp),
+    ("PageDown", KeyEvent::PageDown),
+    ("Insert", KeyEvent::Insert),
+    ("Delete", KeyEvent::Delete),
+];
+
+/// A key by name (case doesn't matter, e.g. "left" or "Esc"), F1 to F12, or
+/// a single character
+pub fn parse_key(name: &str) -> Option<KeyEvent> {
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(KeyEvent::Char(ch));
+    }
+    if let Some((_, key)) = KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+    {
+        return Some(*key);
+    }
+    match name.get(..1) {
+        Some("F") | Some("f") => match name[1..].parse() {
+            Ok(num) if (1..=12).contains(&num) => Some(KeyEvent::F(num)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+pub fn key_name(key: &KeyEvent) -> String {
+    if let Some((name, _)) = KEY_NAMES.iter().find(|(_, named)| named == key) {
+        return name.to_string();
+    }
+    match key {
+        KeyEvent::Char(ch) => ch.to_string(),
+        KeyEvent::F(num) => format!("F{}", num),
+        other => format!("{:?}", other),
+    }
+}
+
+fn keys(names: &[&str]) -> Vec<KeyEvent> {
+    names.iter().filter_map(|name| parse_key(name)).collect()
+}
+
+/// Which keys do what
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyAction, Vec<KeyEvent>)>,
+}
+
+impl Default for Keymap {
+    /// Arrows, vim-style hjkl, and WASD all move, and on a 3x3 board the
+    /// number keys play the cell in the same place on a number pad
+    fn default() -> Self {
+        let mut bindings = vec![
+            (KeyAction::Left, keys(&["Left", "h", "a"])),
+            (KeyAction::Right, keys(&["Right", "l", "d"])),
+            (KeyAction::Up, keys(&["Up", "k", "w"])),
+            (KeyAction::Down, keys(&["Down", "j", "s"])),
+            (KeyAction::Select, keys(&["Enter", "Space"])),
+            (KeyAction::Hint, keys(&["H"])),
+            (KeyAction::Analyze, keys(&["A"])),
+            (KeyAction::TypeMove, keys(&[":"])),
+            (KeyAction::Help, keys(&["?", "F1"])),
+            (KeyAction::Rematch, keys(&["r"])),
+            (KeyAction::Menu, keys(&["m"])),
+            (KeyAction::ChooseX, keys(&["x"])),
+            (KeyAction::ChooseO, keys(&["o"])),
+            (KeyAction::PlaceTwo, keys(&["t"])),
+            (KeyAction::ToggleSymbol, keys(&["Tab"])),
+            (KeyAction::Quit, keys(&["q", "Q", "Esc"])),
+        ];
+        for num in 1..=9 {
+            bindings.push((
+                KeyAction::Cell(num),
+                vec![KeyEvent::Char((b'0' + num) as char)],
+            ));
+        }
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// The defaults, with any actions listed in `config` rebound. Each line
+    /// is an action, '=', and a comma separated list of keys, e.g.
+    /// "left = Left, h". Lines starting with '#' are ignored.
+    pub fn from_config(config: &str) -> Result<Self> {
+        let mut keymap = Keymap::default();
+        for (num, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad_line =
+                |reason: String| Error::BadConfig(format!("line {}: {}", num + 1, reason));
+            let (name, keys) = line
+                .split_once('=')
+                .ok_or_else(|| bad_line("expected 'action = keys'".to_string()))?;
+            let name = name.trim();
+            let action = KeyAction::all()
+                .into_iter()
+                .find(|action| action.name() == name)
+                .ok_or_else(|| bad_line(format!("unknown action '{}'", name)))?;
+            let keys = keys
+                .split(',')
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(|key| parse_key(key).ok_or_else(|| bad_line(format!("unknown key '{}'", key))))
+                .collect::<Result<Vec<_>>>()?;
+            keymap.bind(action, keys);
+        }
+        Ok(keymap)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Keymap::from_config(&fs::read_to_string(path).map_err(Error::loading(path))?)
+    }
+
+    /// The keymap in the config file format
+    pub fn to_config(&self) -> String {
+        let mut config = String::from("# ricracroe key bindings: action = key, key, ...\n");
+        for (action, keys) in &self.bindings {
+            config.push_str(&format!(
+                "{} = {}\n",
+                action.name(),
+                self.key_list(keys, ", ")
+            ));
+        }
+        config
+    }
+
+    // Replace the keys for `action`, taking them away from anything else
+    fn bind(&mut self, action: KeyAction, keys: Vec<KeyEvent>) {
+        for (_, bound) in self.bindings.iter_mut() {
+            bound.retain(|key| !keys.contains(key));
+        }
+        if let Some((_, bound)) = self
+            .bindings
+            .iter_mut()
+            .find(|(bound_action, _)| *bound_action == action)
+        {
+            *bound = keys;
+        }
+    }
+
+    pub fn action(&self, key: &KeyEvent) -> Option<KeyAction> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(key))
+            .map(|(action, _)| *action)
+    }
+
+    fn key_list(&self, keys: &[KeyEvent], separator: &str) -> String {
+        keys.iter()
+            .map(key_name)
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+
+    /// The first key bound to `action`, for short instructions
+    pub fn describe(&self, action: KeyAction) -> String {
+        self.bindings
+            .iter()
+            .find(|(bound_action, _)| *bound_action == action)
+            .and_then(|(_, keys)| keys.first())
+            .map(|key| format!("'{}'", key_name(key)))
+            .unwrap_or_else(|| "(unbound)".to_string())
+    }
+
+    /// A line per action with the keys bound to it, for the help screen
+    pub fn help_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (action, keys) in &self.bindings {
+            // The number pad cells would take up a line each
+            if let KeyAction::Cell(_) = action {
+                continue;
+            }
+            lines.push(format!(
+                "{:<24}{}",
+                action.description(),
+                self.key_list(keys, " ")
+            ));
+        }
+        let cell_keys: Vec<String> = self
+            .bindings
+            .iter()
+            .filter(|(action, _)| action.cell().is_some())
+            .map(|(_, keys)| self.key_list(keys, "/"))
+            .collect();
+        lines.push(format!(
+            "{:<24}{}",
+            "Play cells (3x3 only)",
+            cell_keys.join(" ")
+        ));
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_error(config: &str) -> String {
+        match Keymap::from_config(config) {
+            Err(Error::BadConfig(reason)) => reason,
+            Err(e) => panic!("expected a config error, got {}", e),
+            Ok(_) => panic!("expected '{}' to be refused", config),
+        }
+    }
+
+    #[test]
+    fn bad_config_lines_are_refused() {
+        assert_eq!(
+            config_error("# keys\n\nleft Left"),
+            "line 3: expected 'action = keys'"
+        );
+        assert_eq!(
+            config_error("jump = Space"),
+            "line 1: unknown action 'jump'"
+        );
+        assert_eq!(
+            config_error("left = Left, F13"),
+            "line 1: unknown key 'F13'"
+        );
+        assert_eq!(
+            config_error("left = h\nright = Shift"),
+            "line 2: unknown key 'Shift'"
+        );
+    }
+
+    #[test]
+    fn rebinding_takes_keys_from_other_actions() {
+        let keymap = Keymap::from_config("# vim only\nhint = h, ?\ncell-5 = Space\n").unwrap();
+        assert_eq!(keymap.action(&KeyEvent::Char('h')), Some(KeyAction::Hint));
+        assert_eq!(keymap.action(&KeyEvent::Char('?')), Some(KeyAction::Hint));
+        assert_eq!(
+            keymap.action(&KeyEvent::Char(' ')),
+            Some(KeyAction::Cell(5))
+        );
+        assert_eq!(keymap.action(&KeyEvent::Char('H')), None);
+        assert_eq!(keymap.action(&KeyEvent::Char('5')), None);
+        // Whatever else was bound stays bound
+        assert_eq!(keymap.action(&KeyEvent::Left), Some(KeyAction::Left));
+        assert_eq!(keymap.action(&KeyEvent::Enter), Some(KeyAction::Select));
+        assert_eq!(keymap.describe(KeyAction::Help), "'F1'");
+    }
+
+    #[test]
+    fn config_round_trips() {
+        let keymap = Keymap::from_config("quit = Esc\nleft = Left, Comma").unwrap();
+        let config = keymap.to_config();
+        assert!(config.contains("left = Left, Comma\n"));
+        assert_eq!(Keymap::from_config(&config).unwrap().to_config(), config);
+        assert_eq!(
+            Keymap::from_config("").unwrap().to_config(),
+            Keymap::default().to_config()
+        );
+    }
+
+    #[test]
+    fn keys_parse_by_name() {
+        assert_eq!(parse_key("x"), Some(KeyEvent::Char('x')));
+        assert_eq!(parse_key("esc"), Some(KeyEvent::Esc));
+        assert_eq!(parse_key("PAGEUP"), Some(KeyEvent::PageUp));
+        assert_eq!(parse_key("f12"), Some(KeyEvent::F(12)));
+        assert_eq!(parse_key("F0"), None);
+        assert_eq!(parse_key("Fx"), None);
+        assert_eq!(parse_key(""), None);
+        for name in &["Space", "Tab", "F3", "q"] {
+            assert_eq!(key_name(&parse_key(name).unwrap()), *name);
+        }
+    }
+
+    #[test]
+    fn number_keys_match_a_number_pad() {
+        assert_eq!(KeyAction::Cell(7).cell(), Some(Coord { x: 0, y: 0 }));
+        assert_eq!(KeyAction::Cell(5).cell(), Some(Coord { x: 1, y: 1 }));
+        assert_eq!(KeyAction::Cell(3).cell(), Some(Coord { x: 2, y: 2 }));
+        assert_eq!(KeyAction::Cell(0).cell(), None);
+        assert_eq!(KeyAction::Select.cell(), None);
+    }
+}
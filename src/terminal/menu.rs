@@ -0,0 +1,197 @@
+/// One line of a menu: either something to pick, or a setting with a list
+/// of choices to step through
+#[derive(Debug, Clone)]
+pub struct MenuItem {
+    pub label: String,
+    pub choices: Vec<String>,
+    pub choice: usize,
+    pub enabled: bool,
+}
+
+impl MenuItem {
+    pub fn action(label: &str) -> Self {
+        MenuItem {
+            label: label.to_string(),
+            choices: Vec::new(),
+            choice: 0,
+            enabled: true,
+        }
+    }
+
+    pub fn setting(label: &str, choices: Vec<String>, choice: usize) -> Self {
+        MenuItem {
+            label: label.to_string(),
+            choice: choice.min(choices.len().saturating_sub(1)),
+            choices,
+            enabled: true,
+        }
+    }
+
+    /// Shown, but can't be picked
+    pub fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+
+    pub fn is_setting(&self) -> bool {
+        !self.choices.is_empty()
+    }
+
+    fn text(&self, label_width: usize) -> String {
+        if self.is_setting() {
+            format!(
+                "{:<width$}  ◀ {} ▶",
+                self.label,
+                self.choices[self.choice],
+                width = label_width
+            )
+        } else {
+            self.label.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Menu {
+    pub title: String,
+    pub items: Vec<MenuItem>,
+    pub selected: usize,
+}
+
+/// What happened in a menu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuAction {
+    /// An action item was picked
+    Chosen(usize),
+    /// A setting was changed, in case other items depend on it
+    Changed(usize),
+    Back,
+}
+
+impl Menu {
+    pub fn new(title: &str, items: Vec<MenuItem>) -> Self {
+        let selected = items.iter().position(|item| item.enabled).unwrap_or(0);
+        Menu {
+            title: title.to_string(),
+            items,
+            selected,
+        }
+    }
+
+    /// Start with `selected` selected, if it can be
+    pub fn with_selected(mut self, selected: usize) -> Self {
+        if self.items.get(selected).map(|item| item.enabled) == Some(true) {
+            self.selected = selected;
+        }
+        self
+    }
+
+    /// Move the selection `step` items down (or up, if negative), skipping
+    /// over disabled items and wrapping around the ends
+    pub fn move_selection(&mut self, step: isize) {
+        let count = self.items.len() as isize;
+        let mut index = self.selected as isize;
+        for _ in 0..count {
+            index = (index + step).rem_euclid(count);
+            if self.items[index as usize].enabled {
+                self.selected = index as usize;
+                return;
+            }
+        }
+    }
+
+    /// Step the selected setting through its choices, wrapping around the
+    /// ends. Returns whether there was a setting to change.
+    pub fn change_choice(&mut self, step: isize) -> bool {
+        let item = &mut self.items[self.selected];
+        if !item.is_setting() || !item.enabled {
+            return false;
+        }
+        let count = item.choices.len() as isize;
+        item.choice = (item.choice as isize + step).rem_euclid(count) as usize;
+        true
+    }
+
+    fn label_width(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.is_setting())
+            .map(|item| item.label.chars().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Width of the widest line with any choice made, so the menu doesn't
+    /// shift around as settings change
+    pub fn width(&self) -> usize {
+        let label_width = self.label_width();
+        self.items
+            .iter()
+            .map(
+                |item| match item.choices.iter().map(|c| c.chars().count()).max() {
+                    // Label, then the choice between "  ◀ " and " ▶"
+                    Some(choice_width) => label_width + choice_width + 6,
+                    None => item.label.chars().count(),
+                },
+            )
+            .chain(std::iter::once(self.title.chars().count()))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Text of each item, with the settings' values lined up
+    pub fn lines(&self) -> Vec<String> {
+        let label_width = self.label_width();
+        self.items
+            .iter()
+            .map(|item| item.text(label_width))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choices(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn selection_skips_disabled_items_and_wraps() {
+        let mut menu = Menu::new(
+            "Menu",
+            vec![
+                MenuItem::action("Gone").disabled(),
+                MenuItem::action("First"),
+                MenuItem::action("Gone too").disabled(),
+                MenuItem::action("Last"),
+            ],
+        );
+        assert_eq!(menu.selected, 1);
+        menu.move_selection(1);
+        assert_eq!(menu.selected, 3);
+        menu.move_selection(1);
+        assert_eq!(menu.selected, 1);
+        menu.move_selection(-1);
+        assert_eq!(menu.selected, 3);
+        assert_eq!(menu.with_selected(0).selected, 3);
+    }
+
+    #[test]
+    fn settings_step_through_their_choices() {
+        let mut menu = Menu::new(
+            "Setup",
+            vec![
+                MenuItem::setting("Size", choices(&["3", "4", "5"]), 0),
+                MenuItem::action("Start"),
+            ],
+        );
+        assert!(menu.change_choice(-1));
+        assert_eq!(menu.items[0].choice, 2);
+        menu.move_selection(1);
+        assert!(!menu.change_choice(1));
+        assert_eq!(menu.lines(), vec!["Size  ◀ 5 ▶", "Start"]);
+        assert_eq!(menu.width(), 11);
+    }
+}
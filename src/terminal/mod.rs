@@ -1,8 +1,20 @@
-use log::debug;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use log::{debug, error};
+
+use crate::ai::{self, AiLevel, AiPlayer, RRRPlayer};
+use crate::analysis;
 use crate::coord::Coord;
 use crate::errors::Result;
-use crate::ricracroe::{RRRGame, RRROutcome};
+use crate::record::GameRecord;
+use crate::render::{BoardRenderer, UiState};
+use crate::ricracroe::{
+    format_clock, Opening, RRRCell, RRRError, RRRGame, RRROutcome, RRRRules, StonesPerTurn,
+    SwapAnswer, SwapOffer, TimeControl, Variant,
+};
+use crate::stats::{GameResult, Stats};
 
 mod settings;
 use settings::RenderSettings;
@@ -10,85 +22,1427 @@ use settings::RenderSettings;
 mod cxterm;
 use cxterm::CxTerm;
 
+pub mod backend;
+
+pub mod keymap;
+
+mod menu;
+use backend::{CrosstermInput, CrosstermScreen, InputSource, Screen};
+use keymap::{KeyAction, Keymap};
+use menu::{Menu, MenuAction, MenuItem};
+
+pub mod theme;
+use theme::Theme;
+
 pub enum GameAction {
     TakeTurn(Coord),
+    Hint,
+    ToggleAnalysis,
+    Rematch,
+    Menu,
+    Quit,
+    /// Nothing happened for a while
+    Tick,
+    AnswerSwap(SwapAnswer),
+    /// Switch what to place, in variants where players choose
+    ToggleSymbol,
+}
+
+/// Ways to step through a recorded game
+pub enum ReplayAction {
+    Back,
+    Forward,
+    Start,
+    End,
     Quit,
 }
 
-const INSTRUCTIONS: &str = r#"Press 'Q', 'q', or <Esc> to quit.
-To make a move:
-1. Mouse click in square, or
-2. Arrows to move and <Space> or <Enter> to select."#;
+fn instructions(keymap: &Keymap) -> String {
+    format!(
+        "Press {} to quit, {} for the menu, {} for all the keys,\n\
+         {} for a hint, {} to analyze. Click a square to play there,\n\
+         or move with {}/{}/{}/{} and play with {}, or type\n\
+         {} and a cell such as b2 or 1,1 then 'Enter'.",
+        keymap.describe(KeyAction::Quit),
+        keymap.describe(KeyAction::Menu),
+        keymap.describe(KeyAction::Help),
+        keymap.describe(KeyAction::Hint),
+        keymap.describe(KeyAction::Analyze),
+        keymap.describe(KeyAction::Left),
+        keymap.describe(KeyAction::Down),
+        keymap.describe(KeyAction::Up),
+        keymap.describe(KeyAction::Right),
+        keymap.describe(KeyAction::Select),
+        keymap.describe(KeyAction::TypeMove),
+    )
+}
+
+fn analysis_legend(keymap: &Keymap) -> String {
+    format!(
+        "Analysis on, press {} again to turn it off.\n\
+         Each open cell shows how many moves until the game ends, colored\n\
+         by whether playing there wins, draws, or loses ('?' is unclear).",
+        keymap.describe(KeyAction::Analyze)
+    )
+}
+
+/// Everything about how to play, besides where
+pub struct GameOptions {
+    /// What the new game screen starts out with
+    pub rules: RRRRules,
+    pub time_control: Option<TimeControl>,
+    /// Percentage of moves computer players make at random
+    pub blunder_percent: u32,
+    pub opening: Opening,
+    pub stones: StonesPerTurn,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    pub renderer: Box<dyn BoardRenderer>,
+    /// Results of games played by any of its profiles are added to it
+    pub stats: Stats,
+    /// Where to save a record of every game, if anywhere
+    pub record_dir: Option<PathBuf>,
+}
 
-pub fn play_game() -> Result<()> {
-    let board_size: usize = 3;
-    let mut game = RRRGame::new_anysize(board_size);
+pub fn play_game(options: GameOptions) -> Result<()> {
+    play_game_on(
+        CrosstermScreen::new(std::io::stdout())?,
+        CrosstermInput::new(),
+        options,
+    )
+}
 
-    let mut term = CxTerm::new(RenderSettings::new(2, 4, board_size), std::io::stdout())?;
+/// Play on any screen, taking input from anywhere, e.g. a virtual screen
+/// driven by scripted input
+pub fn play_game_on<S: Screen, I: InputSource>(
+    screen: S,
+    input: I,
+    options: GameOptions,
+) -> Result<()> {
+    let GameOptions {
+        rules,
+        time_control,
+        blunder_percent,
+        opening,
+        stones,
+        theme,
+        keymap,
+        renderer,
+        mut stats,
+        record_dir,
+    } = options;
+    let mut term = CxTerm::new(
+        RenderSettings::new(2, 4, rules.size, renderer),
+        theme,
+        keymap,
+        screen,
+        input,
+    )?;
     debug!("Resetting display");
     term.reset_display()?;
 
+    let mut setup = GameSetup {
+        rules,
+        players: [PlayerKind::Human, PlayerKind::Human],
+        time_control,
+        blunder_percent,
+        opening,
+        stones,
+    };
+    loop {
+        // Games are resumed from their records, so there have to be some
+        let mut resume = MenuItem::action("Resume a saved game");
+        if record_dir.is_none() {
+            resume = resume.disabled();
+        }
+        let mut menu = Menu::new(
+            "Ric Rac Roe",
+            vec![
+                MenuItem::action("New game"),
+                resume,
+                MenuItem::action("Player stats"),
+                MenuItem::action("Quit"),
+            ],
+        );
+        match term.run_menu(&mut menu)? {
+            MenuAction::Chosen(0) => {
+                if !setup_game(&mut term, &mut setup, stats.profiles())? {
+                    continue;
+                }
+                let game = new_game(&setup, RRRCell::X);
+                if play_games(&mut term, &setup, game, &mut stats, &record_dir, None)? {
+                    return Ok(());
+                }
+            }
+            MenuAction::Chosen(1) => {
+                let dir = match record_dir.as_deref() {
+                    Some(dir) => dir,
+                    None => continue,
+                };
+                let (path, record) = match GameRecord::newest_unfinished(dir)? {
+                    Some(found) => found,
+                    None => {
+                        let lines =
+                            vec!["There aren't any unfinished games to resume.".to_string()];
+                        term.show_text("Resume a saved game", &lines)?;
+                        continue;
+                    }
+                };
+                debug!("Resuming {}", path.display());
+                let game = resumed_game(&record)?;
+                setup = resumed_setup(&record, &game, stats.profiles());
+                if play_games(
+                    &mut term,
+                    &setup,
+                    game,
+                    &mut stats,
+                    &record_dir,
+                    Some(&path),
+                )? {
+                    return Ok(());
+                }
+            }
+            MenuAction::Chosen(2) => {
+                let mut lines = stats.overview();
+                if stats.profiles().is_empty() {
+                    lines = vec![
+                        "No players yet. Add one with:".to_string(),
+                        format!("{} stats --add-player NAME", clap::crate_name!()),
+                    ];
+                }
+                term.show_text("Player stats", &lines)?;
+            }
+            MenuAction::Chosen(3) | MenuAction::Back => return Ok(()),
+            _ => (),
+        }
+    }
+}
+
+// Play `game`, and then rematches with the players taking turns to go first,
+// until someone heads back to the menu. A game resumed from a record is saved
+// back over it. Returns whether to quit altogether.
+fn play_games<S: Screen, I: InputSource>(
+    term: &mut CxTerm<S, I>,
+    setup: &GameSetup,
+    mut game: RRRGame,
+    stats: &mut Stats,
+    record_dir: &Option<PathBuf>,
+    mut resumed_from: Option<&Path>,
+) -> Result<bool> {
+    let mut score = Score::default();
+    loop {
+        let first = game.rematch_first();
+        let action = run_game(
+            term,
+            setup,
+            game,
+            &mut score,
+            stats,
+            record_dir.as_deref(),
+            resumed_from,
+        )?;
+        match action {
+            GameAction::Rematch => {
+                game = new_game(setup, first);
+                resumed_from = None;
+            }
+            GameAction::Quit => return Ok(true),
+            _ => return Ok(false),
+        }
+    }
+}
+
+// A new game under `setup`, with `first` moving first
+fn new_game(setup: &GameSetup, first: RRRCell) -> RRRGame {
+    let mut game = RRRGame::new_with_rules(setup.rules)
+        .starting_with(first)
+        .with_opening(setup.opening)
+        .with_stones(setup.stones);
+    if let Some(control) = setup.time_control {
+        game = game.with_time_control(control);
+    }
+    game
+}
+
+// The game in `record` as it was left. Swap answers aren't recorded, so the
+// opening rule is only kept to show and save again, and records don't say
+// how long anyone had left, so the clocks start over.
+fn resumed_game(record: &GameRecord) -> Result<RRRGame> {
+    let mut game = record.game_after(record.moves.len())?;
+    if let Some(control) = record.time_control {
+        game = game.with_time_control(control);
+        if let Some(time_left) = record.time_left {
+            game = game.with_time_left(time_left);
+        }
+    }
+    Ok(game)
+}
+
+// Who played in `record`, and how, going by the names they were saved under.
+// Those are for the sides they ended up on, and `game` says who started on
+// which.
+fn resumed_setup(record: &GameRecord, game: &RRRGame, profiles: &[String]) -> GameSetup {
+    let name = |seat| match game.side_of(seat) {
+        RRRCell::O => &record.o,
+        _ => &record.x,
+    };
+    let (x, x_blunders) = player_kind(name(RRRCell::X), profiles);
+    let (o, o_blunders) = player_kind(name(RRRCell::O), profiles);
+    GameSetup {
+        rules: record.rules,
+        players: [x, o],
+        time_control: record.time_control,
+        blunder_percent: x_blunders.or(o_blunders).unwrap_or(0),
+        opening: record.opening,
+        stones: record.stones,
+    }
+}
+
+// The player saved as `name` by GameSetup::player_name(), and for a computer,
+// how often it blunders
+fn player_kind(name: &str, profiles: &[String]) -> (PlayerKind, Option<u32>) {
+    let computer = name
+        .strip_prefix("Computer (")
+        .and_then(|rest| rest.strip_suffix(')'));
+    if let Some(computer) = computer {
+        let mut parts = computer.split(", ");
+        if let Some(Ok(level)) = parts.next().map(str::parse::<AiLevel>) {
+            let blunders = parts
+                .next()
+                .and_then(|part| part.strip_suffix("% blunders"))
+                .and_then(|percent| percent.parse().ok());
+            return (PlayerKind::Computer(level), Some(blunders.unwrap_or(0)));
+        }
+    }
+    if profiles.iter().any(|profile| profile == name) {
+        (PlayerKind::Profile(name.to_string()), None)
+    } else {
+        (PlayerKind::Human, None)
+    }
+}
+
+pub const MAX_BOARD_SIZE: usize = 19;
+
+// Shortest line that can win. Anything less makes for a silly game.
+const MIN_WIN_LENGTH: usize = 3;
+
+/// Who plays one side of a game
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlayerKind {
+    /// Someone without a profile, whose results aren't kept
+    Human,
+    /// Someone whose results go in their profile's stats
+    Profile(String),
+    Computer(AiLevel),
+}
+
+impl PlayerKind {
+    fn all(profiles: &[String]) -> Vec<PlayerKind> {
+        let mut kinds = vec![PlayerKind::Human];
+        kinds.extend(profiles.iter().cloned().map(PlayerKind::Profile));
+        kinds.extend(
+            AiLevel::all()
+                .iter()
+                .map(|level| PlayerKind::Computer(*level)),
+        );
+        kinds
+    }
+}
+
+impl fmt::Display for PlayerKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlayerKind::Human => write!(f, "Human"),
+            PlayerKind::Profile(name) => write!(f, "{}", name),
+            PlayerKind::Computer(level) => write!(f, "Computer ({})", level),
+        }
+    }
+}
+
+/// Everything picked on the new game screen
+#[derive(Debug, Clone)]
+pub struct GameSetup {
+    pub rules: RRRRules,
+    /// X, then O
+    pub players: [PlayerKind; 2],
+    pub time_control: Option<TimeControl>,
+    /// Percentage of moves computer players make at random
+    pub blunder_percent: u32,
+    pub opening: Opening,
+    pub stones: StonesPerTurn,
+}
+
+impl GameSetup {
+    // The computer player for `kind`, if it is one
+    fn computer(&self, kind: &PlayerKind) -> Option<AiPlayer> {
+        match kind {
+            PlayerKind::Human | PlayerKind::Profile(_) => None,
+            PlayerKind::Computer(level) => {
+                Some(AiPlayer::new(*level).with_blunders(self.blunder_percent))
+            }
+        }
+    }
+
+    // What to call a player in stats and game records
+    fn player_name(&self, kind: &PlayerKind) -> String {
+        match self.computer(kind) {
+            Some(computer) => format!("Computer ({})", computer.name()),
+            None => kind.to_string(),
+        }
+    }
+}
+
+// Sizes of the middle of the board to keep the first move out of, offered
+// on the new game screen where they fit
+const OFF_CENTRE_SIZES: &[usize] = &[1, 3, 5];
+
+// The opening rules that can be played under `rules`, with `current` in
+// there too if it can
+fn opening_choices(rules: &RRRRules, current: Opening) -> Vec<Opening> {
+    let mut choices = vec![Opening::Standard, Opening::Swap, Opening::Swap2];
+    choices.extend(
+        OFF_CENTRE_SIZES
+            .iter()
+            .map(|middle| Opening::OffCentre(*middle)),
+    );
+    if !choices.contains(&current) {
+        choices.push(current);
+    }
+    choices.retain(|opening| opening.fits(rules));
+    choices
+}
+
+fn opening_label(opening: Opening) -> String {
+    match opening {
+        Opening::Standard => "Standard".to_string(),
+        Opening::Swap => "Swap after the first move".to_string(),
+        Opening::Swap2 => "Swap2".to_string(),
+        Opening::OffCentre(middle) => {
+            format!("First move outside the middle {0}x{0}", middle)
+        }
+    }
+}
+
+// The choices of stones a turn, with `current` in there too if it isn't one
+// of the usual ones
+fn stones_choices(current: StonesPerTurn) -> Vec<StonesPerTurn> {
+    let mut choices = vec![
+        StonesPerTurn::default(),
+        StonesPerTurn::connect6(),
+        StonesPerTurn { first: 2, then: 2 },
+    ];
+    if !choices.contains(&current) {
+        choices.push(current);
+    }
+    choices
+}
+
+fn stones_label(stones: StonesPerTurn) -> String {
+    if stones == StonesPerTurn::connect6() {
+        "1, then 2 (Connect6)".to_string()
+    } else if stones.first == stones.then {
+        stones.then.to_string()
+    } else {
+        format!("{}, then {}", stones.first, stones.then)
+    }
+}
+
+// Blunder rates offered on the new game screen
+const BLUNDER_PERCENTS: &[u32] = &[0, 10, 25, 50];
+
+// The choices of blunder rate, with `current` in its place among them even
+// if it isn't one of the usual ones
+fn blunder_choices(current: u32) -> Vec<u32> {
+    let mut choices = BLUNDER_PERCENTS.to_vec();
+    if !choices.contains(&current) {
+        choices.push(current);
+        choices.sort_unstable();
+    }
+    choices
+}
+
+fn blunder_label(percent: u32) -> String {
+    match percent {
+        0 => "Never".to_string(),
+        percent => format!("{}% of moves", percent),
+    }
+}
+
+// Time limits offered on the new game screen, besides none at all
+fn time_control_presets() -> Vec<TimeControl> {
+    let fischer = |total, increment| TimeControl::Fischer {
+        total: Duration::from_secs(total),
+        increment: Duration::from_secs(increment),
+    };
+    let per_move = |secs| TimeControl::PerMove(Duration::from_secs(secs));
+    vec![
+        fischer(60, 0),
+        fischer(180, 2),
+        fischer(300, 5),
+        fischer(600, 0),
+        per_move(5),
+        per_move(10),
+        per_move(30),
+    ]
+}
+
+// The choices of time limit, with `current` in there too even if it isn't
+// one of the presets
+fn time_control_choices(current: Option<TimeControl>) -> Vec<Option<TimeControl>> {
+    let mut choices = vec![None];
+    choices.extend(time_control_presets().into_iter().map(Some));
+    if !choices.contains(&current) {
+        choices.push(current);
+    }
+    choices
+}
+
+fn format_seconds(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 60 && secs.is_multiple_of(60) {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} s", secs)
+    }
+}
+
+fn time_control_label(control: Option<TimeControl>) -> String {
+    match control {
+        None => "Untimed".to_string(),
+        Some(TimeControl::Fischer { total, increment }) if increment.as_secs() == 0 => {
+            format_seconds(total)
+        }
+        Some(TimeControl::Fischer { total, increment }) => {
+            format!("{} + {}", format_seconds(total), format_seconds(increment))
+        }
+        Some(TimeControl::PerMove(per_move)) => format!("{} per move", format_seconds(per_move)),
+    }
+}
+
+fn clock_text(game: &RRRGame) -> String {
+    match (game.time_left(RRRCell::X), game.time_left(RRRCell::O)) {
+        (Some(x), Some(o)) => format!("X {}  O {}", format_clock(x), format_clock(o)),
+        _ => String::new(),
+    }
+}
+
+// How long until the clock of the player to move shows a different time
+fn clock_tick(game: &RRRGame) -> Option<Duration> {
+    let left = game.time_left(game.get_turn())?;
+    let into_second = Duration::from_nanos(u64::from(left.subsec_nanos()));
+    if into_second == Duration::from_secs(0) {
+        Some(Duration::from_secs(1))
+    } else {
+        Some(into_second)
+    }
+}
+
+/// Games won by each side, and drawn, over a run of rematches
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Score {
+    pub x_wins: usize,
+    pub o_wins: usize,
+    pub draws: usize,
+}
+
+impl Score {
+    fn record(&mut self, outcome: &RRROutcome) {
+        match outcome.winner() {
+            Some(RRRCell::X) => self.x_wins += 1,
+            Some(_) => self.o_wins += 1,
+            None => self.draws += 1,
+        }
+    }
+}
+
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Score\nX wins {:>5}\nO wins {:>5}\nDraws  {:>5}",
+            self.x_wins, self.o_wins, self.draws
+        )
+    }
+}
+
+fn variant_label(variant: Variant) -> String {
+    match variant {
+        Variant::Standard => "Standard".to_string(),
+        Variant::Misere => "Misère".to_string(),
+        Variant::OrderAndChaos => "Order and Chaos".to_string(),
+        Variant::Numerical => "Numerical".to_string(),
+    }
+}
+
+// Lines of the new game screen
+const SETUP_VARIANT: usize = 0;
+const SETUP_SIZE: usize = 1;
+const SETUP_WIN_LENGTH: usize = 2;
+const SETUP_OPENING: usize = 3;
+const SETUP_STONES: usize = 4;
+const SETUP_X: usize = 5;
+const SETUP_O: usize = 6;
+const SETUP_BLUNDERS: usize = 7;
+const SETUP_TIME: usize = 8;
+const SETUP_START: usize = 9;
+
+fn setup_menu(setup: &GameSetup, profiles: &[String]) -> Menu {
+    let sizes: Vec<usize> = (MIN_WIN_LENGTH..=MAX_BOARD_SIZE).collect();
+    let win_lengths: Vec<usize> = (MIN_WIN_LENGTH..=setup.rules.size).collect();
+    let player_kinds = PlayerKind::all(profiles);
+    let time_controls = time_control_choices(setup.time_control);
+    let blunder_percents = blunder_choices(setup.blunder_percent);
+    let openings = opening_choices(&setup.rules, setup.opening);
+    let stones = stones_choices(setup.stones);
+    let player_setting = |label: &str, kind: &PlayerKind| {
+        MenuItem::setting(
+            label,
+            player_kinds.iter().map(PlayerKind::to_string).collect(),
+            player_kinds.iter().position(|k| k == kind).unwrap_or(0),
+        )
+    };
+    Menu::new(
+        "New game",
+        vec![
+            MenuItem::setting(
+                "Variant",
+                Variant::all().iter().cloned().map(variant_label).collect(),
+                Variant::all()
+                    .iter()
+                    .position(|variant| *variant == setup.rules.variant)
+                    .unwrap_or(0),
+            ),
+            MenuItem::setting(
+                "Board size",
+                sizes.iter().map(|size| format!("{0}x{0}", size)).collect(),
+                sizes
+                    .iter()
+                    .position(|size| *size == setup.rules.size)
+                    .unwrap_or(0),
+            ),
+            MenuItem::setting(
+                "In a row to win",
+                win_lengths.iter().map(usize::to_string).collect(),
+                win_lengths
+                    .iter()
+                    .position(|k| *k == setup.rules.win_length)
+                    .unwrap_or(0),
+            ),
+            MenuItem::setting(
+                "Opening",
+                openings.iter().cloned().map(opening_label).collect(),
+                openings
+                    .iter()
+                    .position(|opening| *opening == setup.opening)
+                    .unwrap_or(0),
+            ),
+            MenuItem::setting(
+                "Stones a turn",
+                stones.iter().cloned().map(stones_label).collect(),
+                stones
+                    .iter()
+                    .position(|stones| *stones == setup.stones)
+                    .unwrap_or(0),
+            ),
+            player_setting("X", &setup.players[0]),
+            player_setting("O", &setup.players[1]),
+            MenuItem::setting(
+                "Computer blunders",
+                blunder_percents
+                    .iter()
+                    .cloned()
+                    .map(blunder_label)
+                    .collect(),
+                blunder_percents
+                    .iter()
+                    .position(|percent| *percent == setup.blunder_percent)
+                    .unwrap_or(0),
+            ),
+            MenuItem::setting(
+                "Time limit",
+                time_controls
+                    .iter()
+                    .cloned()
+                    .map(time_control_label)
+                    .collect(),
+                time_controls
+                    .iter()
+                    .position(|control| *control == setup.time_control)
+                    .unwrap_or(0),
+            ),
+            MenuItem::action("Start game"),
+            MenuItem::action("Back"),
+        ],
+    )
+}
+
+// Read the settings back out of the new game screen
+fn update_setup(setup: &mut GameSetup, menu: &Menu, profiles: &[String]) {
+    let choice = |item: usize| menu.items[item].choice;
+    let size = MIN_WIN_LENGTH + choice(SETUP_SIZE);
+    let win_length = MIN_WIN_LENGTH + choice(SETUP_WIN_LENGTH);
+    let player_kinds = PlayerKind::all(profiles);
+    let time_controls = time_control_choices(setup.time_control);
+    let blunder_percents = blunder_choices(setup.blunder_percent);
+    let opening = opening_choices(&setup.rules, setup.opening)[choice(SETUP_OPENING)];
+    let variant = Variant::all()[choice(SETUP_VARIANT)];
+    let rules = if variant == Variant::OrderAndChaos && setup.rules.variant != variant {
+        // Switching to Order and Chaos starts out on its usual board
+        RRRRules::order_and_chaos()
+    } else if variant == Variant::Numerical {
+        // There's no other board for numerical tic-tac-toe
+        RRRRules::numerical()
+    } else {
+        RRRRules {
+            size,
+            // Keep as much of the old win length as still fits the new size
+            win_length: win_length.min(size),
+            variant,
+        }
+    };
+    *setup = GameSetup {
+        rules,
+        players: [
+            player_kinds[choice(SETUP_X)].clone(),
+            player_kinds[choice(SETUP_O)].clone(),
+        ],
+        time_control: time_controls[choice(SETUP_TIME)],
+        blunder_percent: blunder_percents[choice(SETUP_BLUNDERS)],
+        opening,
+        stones: stones_choices(setup.stones)[choice(SETUP_STONES)],
+    };
+    // A smaller board might not have room for the opening any more
+    if !setup.opening.fits(&setup.rules) {
+        setup.opening = Opening::Standard;
+    }
+}
+
+// Let the players change the setup. Returns whether to start a game.
+fn setup_game<S: Screen, I: InputSource>(
+    term: &mut CxTerm<S, I>,
+    setup: &mut GameSetup,
+    profiles: &[String],
+) -> Result<bool> {
+    // Rules from the command line that the menu can't show start over from
+    // the defaults
+    if setup.rules.size > MAX_BOARD_SIZE || setup.rules.win_length < MIN_WIN_LENGTH {
+        setup.rules = RRRRules::default();
+    }
+    let mut selected = SETUP_START;
+    loop {
+        let mut menu = setup_menu(setup, profiles).with_selected(selected);
+        let action = term.run_menu(&mut menu)?;
+        update_setup(setup, &menu, profiles);
+        selected = menu.selected;
+        match action {
+            MenuAction::Chosen(SETUP_START) => return Ok(true),
+            MenuAction::Changed(_) => (),
+            _ => return Ok(false),
+        }
+    }
+}
+
+// Who ended up playing X and O, which a swap can change
+fn players_by_side<'a>(setup: &'a GameSetup, game: &RRRGame) -> [&'a PlayerKind; 2] {
+    let player = |side| match game.seat_of(side) {
+        RRRCell::O => &setup.players[1],
+        _ => &setup.players[0],
+    };
+    [player(RRRCell::X), player(RRRCell::O)]
+}
+
+// Keep the result of a finished game, if any profiles played in it. Anyone
+// playing themselves is just practicing.
+fn record_result(stats: &mut Stats, setup: &GameSetup, game: &RRRGame) -> Result<()> {
+    let [x, o] = players_by_side(setup, game);
+    let has_profile = |kind: &PlayerKind| matches!(kind, PlayerKind::Profile(_));
+    if !(has_profile(x) || has_profile(o)) || x == o {
+        return Ok(());
+    }
+    match GameResult::new(game, &setup.player_name(x), &setup.player_name(o)) {
+        Some(record) => stats.record(record),
+        None => Ok(()),
+    }
+}
+
+// Save a record of the game, if it got started and there's somewhere to
+// keep it: over the record it was resumed from, if it was
+fn save_record(
+    record_dir: Option<&Path>,
+    resumed_from: Option<&Path>,
+    setup: &GameSetup,
+    game: &RRRGame,
+) -> Result<()> {
+    if game.moves.is_empty() {
+        return Ok(());
+    }
+    let [x, o] = players_by_side(setup, game);
+    let record = GameRecord::new(game, &setup.player_name(x), &setup.player_name(o));
+    let path = match (resumed_from, record_dir) {
+        (Some(path), _) => {
+            record.save(path)?;
+            path.to_path_buf()
+        }
+        (None, Some(dir)) => record.save_to_dir(dir)?,
+        (None, None) => return Ok(()),
+    };
+    debug!("Saved the game to {}", path.display());
+    Ok(())
+}
+
+// What's going on, for the status line. `piece` is what the player to move
+// is set to place, where they choose.
+fn turn_message(game: &RRRGame, keymap: &Keymap, piece: RRRCell) -> String {
+    let seat = game.seat_name(game.seat_to_move());
+    if game.swap_offer().is_some() {
+        format!("The {} player picks a side.", seat)
+    } else if game.placing_opening() {
+        format!(
+            "The {} player places {} for the opening.",
+            seat,
+            game.get_turn()
+        )
+    } else if game.board.get_rules().variant == Variant::Numerical {
+        format!(
+            "It's {}'s turn: {} ({} has {}). {} picks.",
+            game.get_turn(),
+            number_picker(&game.board.pieces(game.get_turn()), Some(piece)),
+            game.get_turn().opponent(),
+            number_picker(&game.board.pieces(game.get_turn().opponent()), None),
+            keymap.describe(KeyAction::ToggleSymbol)
+        )
+    } else if game.board.get_rules().variant.chooses_pieces() {
+        format!(
+            "It's {}'s turn, placing {}. {} switches.",
+            game.board.get_rules().variant.player_label(game.get_turn()),
+            piece,
+            keymap.describe(KeyAction::ToggleSymbol)
+        )
+    } else if game.mid_turn() {
+        format!("{}: place {} more", game.get_turn(), game.stones_left())
+    } else if !game.stones.is_single() {
+        format!(
+            "It's {}'s turn to place {}.",
+            game.get_turn(),
+            game.stones_left()
+        )
+    } else {
+        format!("It's {}'s turn.", game.get_turn())
+    }
+}
+
+// Numbers left to place, like "1 3 [5] 7" with `picked` marked
+fn number_picker(numbers: &[RRRCell], picked: Option<RRRCell>) -> String {
+    numbers
+        .iter()
+        .map(|&number| match picked {
+            Some(picked) if picked == number => format!("[{}]", number),
+            _ => number.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// What comes after `piece` among what the player to move can place, going
+// back round to the start
+fn next_piece(game: &RRRGame, piece: RRRCell) -> RRRCell {
+    let pieces = game.board.pieces(game.get_turn());
+    let next = match pieces.iter().position(|&other| other == piece) {
+        Some(at) => (at + 1) % pieces.len(),
+        None => 0,
+    };
+    pieces.get(next).cloned().unwrap_or(piece)
+}
+
+fn swap_prompt(keymap: &Keymap, offer: SwapOffer) -> String {
+    let mut prompt = format!(
+        "Press {} to play X or {} to play O",
+        keymap.describe(KeyAction::ChooseX),
+        keymap.describe(KeyAction::ChooseO)
+    );
+    if offer.can_place_two {
+        prompt.push_str(&format!(
+            ",\n\
+             or {} to place two more stones, one of each, and let the\n\
+             other player pick",
+            keymap.describe(KeyAction::PlaceTwo)
+        ));
+    }
+    prompt.push('.');
+    prompt
+}
+
+// Who plays what, once sides have been picked
+fn sides_message(game: &RRRGame) -> String {
+    format!(
+        "The first player plays {} and the second plays {}.",
+        game.side_of(game.first),
+        game.side_of(game.first.opponent())
+    )
+}
+
+fn outcome_message(outcome: &RRROutcome) -> String {
+    match outcome {
+        RRROutcome::Draw => "It's a draw!".to_string(),
+        RRROutcome::XWins { .. } => "X won!".to_string(),
+        RRROutcome::OWins { .. } => "O won!".to_string(),
+        RRROutcome::OrderWins { .. } => "Order won!".to_string(),
+        RRROutcome::ChaosWins => "Chaos won!".to_string(),
+        RRROutcome::LossOnTime { loser } => {
+            format!("{} ran out of time, {} won!", loser, loser.opponent())
+        }
+        RRROutcome::Forfeit { loser } => format!("{} forfeited, {} won!", loser, loser.opponent()),
+    }
+}
+
+// The last move and any winning line, highlighted
+fn game_ui(game: &RRRGame) -> UiState {
+    let mut ui = UiState {
+        last_move: game.last_move,
+        ..UiState::default()
+    };
+    if let Some(winning_cells) = game.outcome.as_ref().and_then(RRROutcome::winning_cells) {
+        ui.winning = winning_cells.to_vec();
+    }
+    ui
+}
+
+// Play `game` out, add the result to `score` and `stats`, and save a record
+// of it in `record_dir`, or over the one it was resumed from. Returns what to
+// do next: Rematch, Menu, or Quit.
+fn run_game<S: Screen, I: InputSource>(
+    term: &mut CxTerm<S, I>,
+    setup: &GameSetup,
+    mut game: RRRGame,
+    score: &mut Score,
+    stats: &mut Stats,
+    record_dir: Option<&Path>,
+    resumed_from: Option<&Path>,
+) -> Result<GameAction> {
+    let mut advisor = AiPlayer::new(AiLevel::Perfect);
+    let mut computers: Vec<Option<AiPlayer>> = setup
+        .players
+        .iter()
+        .map(|kind| setup.computer(kind))
+        .collect();
+    let mut analysis = false;
+    // Evaluations of the position after this many moves, kept until the next
+    // one, as searching again every time round is slow
+    let mut evaluations: Option<(usize, ai::Evaluations)> = None;
+    // What to place next, where the variant lets players choose
+    let mut piece = RRRCell::X;
+
+    let instructions = instructions(term.get_keymap());
+    let analysis_legend = analysis_legend(term.get_keymap());
+
     debug!("Starting game...");
+    term.start_game(setup.rules.size)?;
 
     // We want this to be written once, and not refreshed with each loop
-    term.write_msglog(INSTRUCTIONS)?;
+    term.write_msglog(&instructions)?;
+    term.write_scoreboard(&score.to_string())?;
 
     loop {
         let player = game.get_turn();
         debug!("Player turn: {}", player);
 
         // Redraw board state
-        term.write_title("Welcome to Ric Rac Roe!")?;
+        let mut title = format!("Ric Rac Roe: {}", setup.rules);
+        if setup.opening != Opening::Standard {
+            title = format!("{}, {}", title, opening_label(setup.opening));
+        }
+        if !setup.stones.is_single() {
+            title = format!("{}, {} stones a turn", title, setup.stones);
+        }
+        if setup.time_control.is_some() {
+            title = format!("{}, {}", title, time_control_label(setup.time_control));
+        }
+        term.write_title(&title)?;
 
-        for board_row in 0..board_size {
-            let rendered_board_row: usize = board_row * 2;
-            debug!(
-                "Rendering board row: {} ({})",
-                board_row, rendered_board_row
-            );
-            if board_row == 0 {
-                term.write_rendered_board_row(rendered_board_row, &game.board.render_board_top())?;
-            } else {
-                term.write_rendered_board_row(
-                    rendered_board_row,
-                    &game.board.render_board_row_sep(),
-                )?;
-            }
-            term.write_rendered_board_row(
-                rendered_board_row + 1,
-                &game.board.render_board_row(board_row),
-            )?;
-        }
-        term.write_rendered_board_row(board_size * 2, &game.board.render_board_bottom())?;
-
-        if let Some(outcome) = game.outcome {
+        let mut ui = game_ui(&game);
+        if analysis && !game.over() {
+            let moves = game.moves.len();
+            if evaluations.as_ref().map(|(at, _)| *at) != Some(moves) {
+                evaluations = Some((moves, advisor.evaluate_moves(&game)));
+            }
+            for (coord, eval) in evaluations.iter().flat_map(|(_, evaluated)| evaluated) {
+                ui.annotations.insert(
+                    *coord,
+                    (analysis::cell_glyph(eval), eval.map(|solved| solved.value)),
+                );
+            }
+        }
+        term.draw_board(&game.board, ui)?;
+
+        if let Some(outcome) = game.outcome.clone() {
             // Display game end condition
-            match outcome {
-                RRROutcome::Draw => term.write_status("It's a draw!")?,
-                RRROutcome::XWins { .. } => term.write_status("X won!")?,
-                RRROutcome::OWins { .. } => term.write_status("O won!")?,
+            term.write_clocks(&clock_text(&game))?;
+            term.write_status(&outcome_message(&outcome))?;
+            score.record(&outcome);
+            term.write_scoreboard(&score.to_string())?;
+            let keymap = term.get_keymap();
+            let mut prompt = format!(
+                "Press {} for a rematch with {} starting, {} for the menu,\n\
+                 or {} to quit.",
+                keymap.describe(KeyAction::Rematch),
+                game.rematch_first(),
+                keymap.describe(KeyAction::Menu),
+                keymap.describe(KeyAction::Quit),
+            );
+            // Not being able to save shouldn't stop anyone playing
+            if let Err(e) = record_result(stats, setup, &game) {
+                prompt.push_str(&format!("\nCouldn't save the result: {}", e));
             }
-            term.write_msglog("Press any key to exit.")?;
-            term.commit()?;
-            term.get_input_event()?;
-            return Ok(());
-        } else {
-            // Display game turn state
-            term.write_status(&format!("It's {}'s turn.", player))?;
+            if let Err(e) = save_record(record_dir, resumed_from, setup, &game) {
+                prompt.push_str(&format!("\nCouldn't save the game: {}", e));
+            }
+            term.write_msglog(&prompt)?;
+            return term.get_end_of_game_action();
+        }
+
+        let offer = game.swap_offer();
+        let computer = match game.seat_to_move() {
+            RRRCell::O => &mut computers[1],
+            _ => &mut computers[0],
+        };
+        if let Some(computer) = computer {
+            term.write_clocks(&clock_text(&game))?;
+            term.write_status(&format!("{} is thinking...", player))?;
             term.commit()?;
+            if offer.is_some() {
+                game.answer_swap(computer.choose_side(&game))?;
+                term.write_msglog(&sides_message(&game))?;
+                continue;
+            }
+            if let Some((coord, piece)) = computer.choose_placement(&game) {
+                match game.place(&coord, piece) {
+                    // Thinking too long loses, which shows next time around
+                    Ok(_) | Err(RRRError::OutOfTime(_)) => continue,
+                    // Not worth ending the game over, so it's left to whoever
+                    // is at the keyboard
+                    Err(e) => {
+                        error!(
+                            "{} chose {}, which was refused: {}",
+                            computer.name(),
+                            coord,
+                            e
+                        );
+                        term.write_msglog(&format!(
+                            "The computer can't play in {} ({}),\nso it's up to you this turn.",
+                            coord.algebraic(),
+                            e
+                        ))?;
+                    }
+                }
+            }
+        }
+
+        // Numbers get used up, and the other player has different ones
+        let pieces = game.board.pieces(player);
+        if !pieces.contains(&piece) {
+            piece = pieces.first().cloned().unwrap_or(piece);
+        }
 
-            match term.get_game_action()? {
-                GameAction::TakeTurn(coord) => {
-                    if let Err(e) = game.take_turn(&coord) {
-                        term.write_msglog(&format!("{} cannot play in {} ({})", player, coord, e))?;
-                        term.commit()?;
-                    } else {
-                        term.clear_msglog()?;
-                        term.commit()?;
+        // Display game turn state
+        term.write_status(&turn_message(&game, term.get_keymap(), piece))?;
+        if let Some(offer) = offer {
+            term.write_msglog(&swap_prompt(term.get_keymap(), offer))?;
+        }
+        term.commit()?;
+
+        // Keep the clocks up to date while waiting, until someone runs out
+        let action = loop {
+            term.write_clocks(&clock_text(&game))?;
+            let action = match offer {
+                Some(offer) => term.get_swap_action(offer, clock_tick(&game))?,
+                None => term.get_game_action(clock_tick(&game))?,
+            };
+            match action {
+                GameAction::Tick if !game.check_clock() => continue,
+                action => break action,
+            }
+        };
+        match action {
+            GameAction::TakeTurn(coord) => {
+                let played = if setup.rules.variant.chooses_pieces() {
+                    game.place(&coord, piece)
+                } else {
+                    game.take_turn(&coord)
+                };
+                match played {
+                    // The status line says so
+                    Ok(_) | Err(RRRError::OutOfTime(_)) => term.clear_msglog()?,
+                    Err(e) => term.write_msglog(&format!(
+                        "{} cannot play in {} ({})",
+                        player,
+                        coord.algebraic(),
+                        e
+                    ))?,
+                }
+                term.commit()?;
+            }
+            GameAction::Hint => match ai::suggest_move(&mut advisor, &game) {
+                Some(hint) => {
+                    term.set_active_board_cell(hint.coord);
+                    if let Some(hinted) = hint.piece {
+                        piece = hinted;
                     }
+                    term.write_msglog(&format!("Hint for {}: {}", player, hint))?;
+                    term.commit()?;
+                }
+                None => {
+                    term.write_msglog("No hint available.")?;
+                    term.commit()?;
+                }
+            },
+            GameAction::ToggleAnalysis => {
+                analysis = !analysis;
+                if analysis && !advisor.can_evaluate(&game) {
+                    analysis = false;
+                    term.write_msglog(&format!(
+                        "Too many open cells to analyze yet. It works once there\n\
+                         are {} or fewer.",
+                        ai::FULL_SEARCH_MAX_CELLS
+                    ))?;
+                } else if analysis {
+                    term.write_msglog(&analysis_legend)?;
+                } else {
+                    term.write_msglog(&instructions)?;
+                }
+                term.commit()?;
+            }
+            GameAction::AnswerSwap(answer) => {
+                game.answer_swap(answer)?;
+                match answer {
+                    SwapAnswer::Play(_) => term.write_msglog(&sides_message(&game))?,
+                    SwapAnswer::PlaceTwo => term.clear_msglog()?,
+                }
+                term.commit()?;
+            }
+            GameAction::ToggleSymbol => piece = next_piece(&game, piece),
+            GameAction::Rematch | GameAction::Tick => (),
+            action @ GameAction::Menu | action @ GameAction::Quit => {
+                // Games left partway are worth keeping too
+                if let Err(e) = save_record(record_dir, resumed_from, setup, &game) {
+                    error!("Couldn't save the game: {}", e);
                 }
-                GameAction::Quit => return Ok(()),
+                return Ok(action);
             }
         }
     }
 }
+
+/// Step through a recorded game a move at a time
+pub fn replay(
+    record: GameRecord,
+    theme: Theme,
+    keymap: Keymap,
+    renderer: Box<dyn BoardRenderer>,
+) -> Result<()> {
+    replay_on(
+        CrosstermScreen::new(std::io::stdout())?,
+        CrosstermInput::new(),
+        record,
+        theme,
+        keymap,
+        renderer,
+    )
+}
+
+pub fn replay_on<S: Screen, I: InputSource>(
+    screen: S,
+    input: I,
+    record: GameRecord,
+    theme: Theme,
+    keymap: Keymap,
+    renderer: Box<dyn BoardRenderer>,
+) -> Result<()> {
+    let size = record.rules.size;
+    let mut term = CxTerm::new(
+        RenderSettings::new(2, 4, size, renderer),
+        theme,
+        keymap,
+        screen,
+        input,
+    )?;
+    term.reset_display()?;
+    term.start_game(size)?;
+    term.write_title(&format!(
+        "{} (X) vs {} (O), {}",
+        record.x, record.o, record.rules
+    ))?;
+    let keymap = term.get_keymap();
+    let help = format!(
+        "Press {} and {} to step back and forward through the moves,\n\
+         'Home' and 'End' to go to the start and the end, or {} to quit.",
+        keymap.describe(KeyAction::Left),
+        keymap.describe(KeyAction::Right),
+        keymap.describe(KeyAction::Quit),
+    );
+    term.write_msglog(&help)?;
+
+    let total = record.moves.len();
+    let mut shown = 0;
+    loop {
+        let game = record.game_after(shown)?;
+        term.draw_board(&game.board, game_ui(&game))?;
+        let mut status = match game.last_move {
+            Some(coord) if shown > 0 => {
+                // Whoever was to move before it, however many stones a turn
+                let mover = record.game_after(shown - 1)?.get_turn();
+                match record.pieces.get(shown - 1) {
+                    Some(piece) => format!(
+                        "Move {} of {}: {} placed {} at {}.",
+                        shown,
+                        total,
+                        record.rules.variant.player_label(mover),
+                        piece,
+                        coord.algebraic()
+                    ),
+                    None => format!(
+                        "Move {} of {}: {} played {}.",
+                        shown,
+                        total,
+                        mover,
+                        coord.algebraic()
+                    ),
+                }
+            }
+            _ => format!("Start of the game, {} to move first.", record.first),
+        };
+        if let Some(ref outcome) = game.outcome {
+            status = format!("{} {}", status, outcome_message(outcome));
+        } else if shown == total {
+            status = format!("{} The game wasn't finished.", status);
+        }
+        term.write_status(&status)?;
+        term.hide_cursor()?;
+        term.commit()?;
+
+        shown = match term.get_replay_action()? {
+            ReplayAction::Back => shown.saturating_sub(1),
+            ReplayAction::Forward => (shown + 1).min(total),
+            ReplayAction::Start => 0,
+            ReplayAction::End => total,
+            ReplayAction::Quit => return Ok(()),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::backend::{parse_input_event, ScriptedInput, VirtualScreen};
+    use super::*;
+    use crate::errors::Error;
+    use crate::render::renderer_by_name;
+
+    fn options(rules: RRRRules) -> GameOptions {
+        GameOptions {
+            rules,
+            time_control: None,
+            blunder_percent: 0,
+            opening: Opening::Standard,
+            stones: StonesPerTurn::default(),
+            theme: Theme::monochrome(),
+            keymap: Keymap::default(),
+            renderer: renderer_by_name("unicode").unwrap(),
+            stats: Stats::default(),
+            record_dir: None,
+        }
+    }
+
+    // Play through `script`, a space separated list of input events, on a
+    // `width` by `height` screen, and return how it ended and what was left on
+    // screen
+    fn play(
+        width: usize,
+        height: usize,
+        options: GameOptions,
+        script: &str,
+    ) -> (Result<()>, String) {
+        let mut screen = VirtualScreen::new(Coord {
+            x: width,
+            y: height,
+        });
+        let events = script
+            .split_whitespace()
+            .map(|event| parse_input_event(event).unwrap());
+        let result = play_game_on(&mut screen, ScriptedInput::new(events), options);
+        (result, screen.contents())
+    }
+
+    fn records_in(dir: &Path) -> Vec<PathBuf> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect()
+    }
+
+    #[test]
+    fn running_out_of_input_stops_the_game() {
+        let (result, screen) = play(80, 24, options(RRRRules::default()), "enter enter space");
+        assert!(matches!(result, Err(Error::EndOfInput)));
+        assert!(screen.contains("1 │X│ │ │"));
+        assert!(screen.contains("It's O's turn."));
+    }
+
+    #[test]
+    fn quitting_from_the_menu() {
+        let (result, screen) = play(80, 24, options(RRRRules::default()), "q");
+        assert!(result.is_ok());
+        assert!(screen.contains("New game"));
+        assert!(screen.contains("Resume a saved game"));
+    }
+
+    #[test]
+    fn number_keys_play_a_game_to_the_end() {
+        let (result, screen) = play(
+            80,
+            24,
+            options(RRRRules::default()),
+            "enter enter 7 4 8 5 9 q",
+        );
+        assert!(result.is_ok());
+        assert!(screen.contains("1 │X│X│X│"));
+        assert!(screen.contains("2 │O│O│ │"));
+        assert!(screen.contains("X won!"));
+        assert!(screen.contains("X wins     1"));
+        assert!(screen.contains("Press 'r' for a rematch with O starting"));
+    }
+
+    #[test]
+    fn rematches_keep_score() {
+        let (_, screen) = play(
+            80,
+            24,
+            options(RRRRules::default()),
+            "enter enter 7 4 8 5 9 r 7 4 8 5 9",
+        );
+        assert!(screen.contains("O won!"));
+        assert!(screen.contains("X wins     1"));
+        assert!(screen.contains("O wins     1"));
+    }
+
+    #[test]
+    fn typed_and_clicked_moves() {
+        // The board's top left cell is at column 13, row 7
+        let (_, screen) = play(
+            80,
+            24,
+            options(RRRRules::default()),
+            "enter enter : b 2 enter click:13,7",
+        );
+        assert!(screen.contains("1 │O│ │ │"));
+        assert!(screen.contains("2 │ │X│ │"));
+        assert!(screen.contains("It's X's turn."));
+    }
+
+    #[test]
+    fn small_terminals_say_how_much_room_is_needed() {
+        let (_, screen) = play(30, 10, options(RRRRules::default()), "enter enter");
+        assert!(screen.contains("Terminal too small, need 22x17"));
+    }
+
+    #[test]
+    fn order_and_chaos_fits_a_standard_terminal() {
+        let (_, screen) = play(80, 24, options(RRRRules::order_and_chaos()), "enter enter");
+        assert!(!screen.contains("Terminal too small"));
+        assert!(screen.contains("a b c d e f"));
+        assert!(screen.contains("It's Order (X)'s turn"));
+    }
+
+    #[test]
+    fn resuming_finishes_the_saved_game() {
+        let dir = TempDir::new("ricracroe-games").unwrap();
+        let with_records = || GameOptions {
+            record_dir: Some(dir.path().to_path_buf()),
+            ..options(RRRRules::default())
+        };
+
+        let (_, screen) = play(80, 24, with_records(), "down enter");
+        assert!(screen.contains("There aren't any unfinished games to resume."));
+
+        let (result, _) = play(80, 24, with_records(), "enter enter 5 7 m q");
+        assert!(result.is_ok());
+        let saved = records_in(dir.path());
+        assert_eq!(saved.len(), 1);
+
+        let (_, screen) = play(80, 24, with_records(), "down enter");
+        assert!(screen.contains("1 │O│ │ │"));
+        assert!(screen.contains("2 │ │X│ │"));
+        assert!(screen.contains("It's X's turn."));
+
+        let (_, screen) = play(80, 24, with_records(), "down enter 8 9 2");
+        assert!(screen.contains("X won!"));
+        // Saved back over the same file
+        assert_eq!(records_in(dir.path()), saved);
+        let record = GameRecord::load(&saved[0]).unwrap();
+        assert_eq!(record.moves.len(), 5);
+        assert!(record.game_after(5).unwrap().over());
+    }
+
+    #[test]
+    fn swap2_offers_a_choice_of_sides() {
+        let options = GameOptions {
+            opening: Opening::Swap2,
+            ..options(RRRRules::default())
+        };
+        let (_, screen) = play(80, 24, options, "enter enter 5 1 9");
+        assert!(screen.contains("The second player picks a side."));
+        let prompt: Vec<&str> = screen.lines().rev().take(3).map(str::trim).collect();
+        assert_eq!(
+            prompt,
+            [
+                "other player pick.",
+                "or 't' to place two more stones, one of each, and let the",
+                "Press 'x' to play X or 'o' to play O,",
+            ]
+        );
+    }
+
+    #[test]
+    fn analysis_waits_until_it_can_see_to_the_end() {
+        let (_, screen) = play(80, 24, options(RRRRules::default()), "enter enter A");
+        assert!(screen.contains("Analysis on"));
+        assert!(screen.contains("1 │9│9│9│"));
+
+        let (_, screen) = play(80, 24, options(RRRRules::standard(5)), "enter enter A");
+        assert!(screen.contains("Too many open cells to analyze yet."));
+        assert!(!screen.contains("Analysis on"));
+    }
+
+    #[test]
+    fn resumed_games_keep_their_clocks_and_sides() {
+        let mut game = RRRGame::new()
+            .with_opening(Opening::Swap)
+            .with_time_control("300+5".parse().unwrap());
+        game.take_turn(&"b2".parse().unwrap()).unwrap();
+        // The second player takes X
+        game.answer_swap(SwapAnswer::Play(RRRCell::X)).unwrap();
+        let mut record = GameRecord::new(&game, "bob", "alice");
+        record.time_left = Some([Duration::from_secs(100), Duration::from_secs(200)]);
+        let record: GameRecord = record.to_string().parse().unwrap();
+
+        let resumed = resumed_game(&record).unwrap();
+        assert_eq!(resumed.side_of(RRRCell::X), RRRCell::O);
+        assert_eq!(resumed.swap_offer(), None);
+        assert_eq!(
+            resumed.time_left(RRRCell::X),
+            Some(Duration::from_secs(100))
+        );
+        let o_left = resumed.time_left(RRRCell::O).unwrap();
+        assert!(o_left <= Duration::from_secs(200) && o_left > Duration::from_secs(199));
+
+        let profiles = ["alice".to_string(), "bob".to_string()];
+        let setup = resumed_setup(&record, &resumed, &profiles);
+        assert_eq!(
+            setup.players,
+            [
+                PlayerKind::Profile("alice".to_string()),
+                PlayerKind::Profile("bob".to_string()),
+            ]
+        );
+    }
+}
@@ -1,67 +1,405 @@
-use crate::coord::Coord;
+use crate::coord::{column_name, Coord};
+use crate::render::BoardRenderer;
+
+pub const MAX_MSGLOG_LINES: usize = 4;
+
+// Boards bigger than this many cells across can be shown a piece at a time,
+// but any less and the terminal is too small to play in
+const MIN_VIEWPORT_CELLS: usize = 3;
+
+// Space left around the board when the terminal is too small for the usual
+// padding: none around the edge, and a line between the board and the text
+const COMPACT_GAME_PADDING: usize = 0;
+const COMPACT_BOARD_PADDING: usize = 1;
+
+// Width of the longest messages, so centering the board doesn't push them
+// off the edge of the terminal
+const TEXT_WIDTH: usize = 64;
+
+// Room to the right of the board for the session score
+pub const SCOREBOARD_WIDTH: usize = 12;
 
 pub struct RenderSettings {
     game_padding: usize,
     board_size: usize,
     board_padding: usize,
+    renderer: Box<dyn BoardRenderer>,
+    // Unknown until the terminal has been asked, in which case nothing is centered
+    terminal_size: Option<Coord>,
+    // The part of the board that's on screen: its top left cell, and how many
+    // cells across and down it is
+    viewport_origin: Coord,
+    viewport_cells: Coord,
+    // Whether the board is too wide or too tall to show all at once
+    scroll_x: bool,
+    scroll_y: bool,
+    // Whether the padding has been squeezed to make room for the board
+    compact: bool,
 }
 
 impl RenderSettings {
-    pub fn new(game_padding: usize, board_padding: usize, board_size: usize) -> Self {
+    pub fn new(
+        game_padding: usize,
+        board_padding: usize,
+        board_size: usize,
+        renderer: Box<dyn BoardRenderer>,
+    ) -> Self {
         RenderSettings {
             game_padding,
             board_size,
             board_padding,
+            renderer,
+            terminal_size: None,
+            viewport_origin: Coord::default(),
+            viewport_cells: Coord {
+                x: board_size,
+                y: board_size,
+            },
+            scroll_x: false,
+            scroll_y: false,
+            compact: false,
         }
     }
 
-    pub fn get_board_size(&self) -> usize {
-        self.board_size
+    /// Lay out a board of a different size, e.g. for a new game
+    pub fn set_board_size(&mut self, board_size: usize) {
+        self.board_size = board_size;
+        self.viewport_origin = Coord::default();
+        self.viewport_cells = Coord {
+            x: board_size,
+            y: board_size,
+        };
+        self.scroll_x = false;
+        self.scroll_y = false;
+        self.fit_viewport();
+    }
+
+    pub fn set_terminal_size(&mut self, terminal_size: Coord) {
+        self.terminal_size = Some(terminal_size);
+        self.fit_viewport();
     }
 
-    pub fn term_coord_to_cell_coord(&self, term_coord: &Coord) -> Coord {
-        let term_rel_coord: Coord = *term_coord - self.get_board_origin();
+    // Room for the row and column labels: row numbers are right aligned, and
+    // column letters are written downwards, one letter per line
+    pub fn get_label_size(&self) -> Coord {
         Coord {
-            x: term_rel_coord.x.saturating_sub(1) / 2,
-            y: term_rel_coord.y.saturating_sub(1) / 2,
+            x: self.board_size.to_string().len() + 1,
+            y: column_name(self.board_size.saturating_sub(1)).len(),
         }
     }
 
-    pub fn cell_coord_to_term_coord(&self, cell_coord: &Coord) -> Coord {
-        let term_rel_coord = Coord {
-            x: cell_coord.x.saturating_mul(2).saturating_add(1),
-            y: cell_coord.y.saturating_mul(2).saturating_add(1),
+    // Padding around the edge and around the board, squeezed or not
+    fn paddings(&self, compact: bool) -> (usize, usize) {
+        if compact {
+            (COMPACT_GAME_PADDING, COMPACT_BOARD_PADDING)
+        } else {
+            (self.game_padding, self.board_padding)
+        }
+    }
+
+    fn get_game_padding(&self) -> usize {
+        self.paddings(self.compact).0
+    }
+
+    fn get_board_padding(&self) -> usize {
+        self.paddings(self.compact).1
+    }
+
+    // Space between the title and the board, which the labels are drawn in,
+    // below the title's own line
+    fn board_margin(&self, board_padding: usize) -> Coord {
+        let label = self.get_label_size();
+        Coord {
+            x: board_padding.max(label.x),
+            y: board_padding.max(label.y + 1),
+        }
+    }
+
+    fn get_board_margin(&self) -> Coord {
+        self.board_margin(self.get_board_padding())
+    }
+
+    // Size of everything on screen around a board view `view` characters
+    // big, with the padding squeezed or not
+    fn layout_size(&self, view: &Coord, compact: bool) -> Coord {
+        let (game_padding, board_padding) = self.paddings(compact);
+        let margin = self.board_margin(board_padding);
+        Coord {
+            x: game_padding + margin.x + view.x + board_padding + SCOREBOARD_WIDTH + game_padding,
+            y: game_padding + margin.y + view.y + board_padding + 3 + MAX_MSGLOG_LINES,
+        }
+    }
+
+    fn get_layout_size(&self, view: &Coord) -> Coord {
+        self.layout_size(view, self.compact)
+    }
+
+    // Most cells that fit in `space` characters along one axis
+    fn cells_fitting(&self, space: usize, axis: fn(&Coord) -> usize) -> usize {
+        (1..=self.board_size)
+            .rev()
+            .find(|&cells| axis(&self.renderer.rendered_size(cells)) <= space)
+            .unwrap_or(1)
+    }
+
+    // Show as much of the board as fits in the terminal
+    fn fit_viewport(&mut self) {
+        let terminal_size = match self.terminal_size {
+            Some(size) => size,
+            None => return,
+        };
+        // Squeeze the padding rather than scroll a board that would fit
+        // without it
+        let whole = self.layout_size(&self.renderer.rendered_size(self.board_size), false);
+        self.compact = whole.x > terminal_size.x || whole.y > terminal_size.y;
+        let layout = self.get_layout_size(&Coord::default());
+        let space = Coord {
+            x: terminal_size.x.saturating_sub(layout.x),
+            y: terminal_size.y.saturating_sub(layout.y),
+        };
+        // Each scrollbar takes a line away from the other axis
+        let full = self.renderer.rendered_size(self.board_size);
+        for _ in 0..2 {
+            self.scroll_x = full.x + self.scroll_y as usize > space.x;
+            self.scroll_y = full.y + self.scroll_x as usize > space.y;
+        }
+        self.viewport_cells = Coord {
+            x: self.cells_fitting(space.x.saturating_sub(self.scroll_y as usize), |size| {
+                size.x
+            }),
+            y: self.cells_fitting(space.y.saturating_sub(self.scroll_x as usize), |size| {
+                size.y
+            }),
+        };
+        self.viewport_origin = Coord {
+            x: self
+                .viewport_origin
+                .x
+                .min(self.board_size - self.viewport_cells.x),
+            y: self
+                .viewport_origin
+                .y
+                .min(self.board_size - self.viewport_cells.y),
         };
-        self.get_board_origin() + term_rel_coord
     }
 
-    pub fn get_rendered_board_height(&self) -> usize {
-        (1 + (self.board_size + 2)) as usize
+    /// Pan the viewport as little as possible to bring `cell` into view
+    pub fn scroll_to(&mut self, cell: &Coord) {
+        let origin = &mut self.viewport_origin;
+        let cells = self.viewport_cells;
+        if cell.x < origin.x {
+            origin.x = cell.x;
+        } else if cell.x >= origin.x + cells.x {
+            origin.x = cell.x + 1 - cells.x;
+        }
+        if cell.y < origin.y {
+            origin.y = cell.y;
+        } else if cell.y >= origin.y + cells.y {
+            origin.y = cell.y + 1 - cells.y;
+        }
     }
 
-    pub fn get_title_origin(&self) -> Coord {
+    /// Top left cell on screen, and how many cells across and down are shown
+    pub fn get_viewport(&self) -> (Coord, Coord) {
+        (self.viewport_origin, self.viewport_cells)
+    }
+
+    /// Whether the board scrolls horizontally and vertically
+    pub fn get_scrolling(&self) -> (bool, bool) {
+        (self.scroll_x, self.scroll_y)
+    }
+
+    /// How far into the rendered board the viewport starts, in characters
+    pub fn get_scroll_offset(&self) -> Coord {
+        self.renderer.cell_offset(&self.viewport_origin)
+            - self.renderer.cell_offset(&Coord::default())
+    }
+
+    /// Size of the part of the rendered board that's on screen, in characters
+    pub fn get_view_size(&self) -> Coord {
         Coord {
-            x: self.game_padding,
-            y: self.game_padding,
+            x: self.renderer.rendered_size(self.viewport_cells.x).x,
+            y: self.renderer.rendered_size(self.viewport_cells.y).y,
         }
     }
 
+    // The view and its scrollbars
+    fn get_board_area_size(&self) -> Coord {
+        self.get_view_size()
+            + Coord {
+                x: self.scroll_y as usize,
+                y: self.scroll_x as usize,
+            }
+    }
+
+    /// Smallest terminal that everything fits in, with the padding squeezed
+    pub fn get_required_size(&self) -> Coord {
+        let cells = self.board_size.min(MIN_VIEWPORT_CELLS);
+        let scrollbar = (cells < self.board_size) as usize;
+        let view = self.renderer.rendered_size(cells);
+        self.layout_size(
+            &Coord {
+                x: view.x + scrollbar,
+                y: view.y + scrollbar,
+            },
+            true,
+        )
+    }
+
+    pub fn fits_terminal(&self) -> bool {
+        match self.terminal_size {
+            Some(size) => {
+                let required = self.get_required_size();
+                size.x >= required.x && size.y >= required.y
+            }
+            None => true,
+        }
+    }
+
+    // Shift everything over so the board ends up centered in the terminal
+    fn get_layout_offset(&self) -> Coord {
+        match self.terminal_size {
+            Some(size) => {
+                let used = self.get_layout_size(&self.get_board_area_size());
+                let used_width = used
+                    .x
+                    .max(self.get_game_padding() + TEXT_WIDTH + self.get_game_padding());
+                Coord {
+                    x: size.x.saturating_sub(used_width) / 2,
+                    y: size.y.saturating_sub(used.y) / 2,
+                }
+            }
+            None => Coord::default(),
+        }
+    }
+
+    pub fn get_renderer(&self) -> &dyn BoardRenderer {
+        self.renderer.as_ref()
+    }
+
+    pub fn get_board_size(&self) -> usize {
+        self.board_size
+    }
+
+    pub fn term_coord_to_cell_coord(&self, term_coord: &Coord) -> Option<Coord> {
+        let origin = self.get_board_origin();
+        if term_coord.x < origin.x || term_coord.y < origin.y {
+            return None;
+        }
+        let offset = *term_coord - origin;
+        let view = self.get_view_size();
+        if offset.x >= view.x || offset.y >= view.y {
+            return None;
+        }
+        self.renderer
+            .cell_at(&(offset + self.get_scroll_offset()), self.board_size)
+    }
+
+    /// Where a cell is on screen; only meaningful for cells in the viewport
+    pub fn cell_coord_to_term_coord(&self, cell_coord: &Coord) -> Coord {
+        self.get_board_origin() + self.renderer.cell_offset(cell_coord) - self.get_scroll_offset()
+    }
+
+    pub fn get_title_origin(&self) -> Coord {
+        self.get_layout_offset()
+            + Coord {
+                x: self.get_game_padding(),
+                y: self.get_game_padding(),
+            }
+    }
+
     pub fn get_board_origin(&self) -> Coord {
-        self.get_title_origin()
+        self.get_title_origin() + self.get_board_margin()
+    }
+
+    /// Beside the board, past its scrollbar if it has one
+    pub fn get_scoreboard_origin(&self) -> Coord {
+        self.get_board_origin()
             + Coord {
-                x: self.board_padding,
-                y: self.board_padding,
+                x: self.get_board_area_size().x + self.get_board_padding(),
+                y: 0,
             }
     }
 
     pub fn get_status_origin(&self) -> Coord {
         Coord {
-            x: self.game_padding,
-            y: self.get_board_origin().y + self.get_rendered_board_height() + self.board_padding,
+            x: self.get_title_origin().x,
+            y: self.get_board_origin().y + self.get_board_area_size().y + self.get_board_padding(),
         }
     }
 
+    /// Where text `width` characters long ends up lined up with the right
+    /// edge of the status line's longest messages
+    pub fn get_clock_origin(&self, width: usize) -> Coord {
+        self.get_status_origin()
+            + Coord {
+                x: TEXT_WIDTH.saturating_sub(width),
+                y: 0,
+            }
+    }
+
+    /// Where moves are typed in
+    pub fn get_input_origin(&self) -> Coord {
+        self.get_status_origin() + Coord { x: 0, y: 1 }
+    }
+
     pub fn get_msglog_origin(&self) -> Coord {
         self.get_status_origin() + Coord { x: 0, y: 3 }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::BoxRenderer;
+
+    #[test]
+    fn layouts_are_centered_in_terminals_they_fit() {
+        let mut settings = RenderSettings::new(2, 4, 3, Box::new(BoxRenderer::unicode()));
+        assert_eq!(settings.get_required_size(), Coord { x: 22, y: 17 });
+        assert!(settings.fits_terminal());
+
+        settings.set_terminal_size(Coord { x: 80, y: 24 });
+        assert!(settings.fits_terminal());
+        assert_eq!(settings.get_title_origin(), Coord { x: 8, y: 2 });
+
+        settings.set_terminal_size(Coord { x: 81, y: 30 });
+        assert!(settings.fits_terminal());
+        assert_eq!(settings.get_title_origin(), Coord { x: 8, y: 5 });
+
+        settings.set_terminal_size(Coord { x: 80, y: 16 });
+        assert!(!settings.fits_terminal());
+    }
+
+    #[test]
+    fn boards_up_to_six_squeeze_into_a_standard_terminal() {
+        for &size in &[4, 6] {
+            let mut settings = RenderSettings::new(2, 4, size, Box::new(BoxRenderer::unicode()));
+            settings.set_terminal_size(Coord { x: 80, y: 24 });
+            assert!(settings.fits_terminal());
+            assert_eq!(settings.get_scrolling(), (false, false));
+        }
+    }
+
+    #[test]
+    fn big_boards_scroll_to_follow_the_cursor() {
+        let mut settings = RenderSettings::new(2, 4, 10, Box::new(BoxRenderer::unicode()));
+        settings.set_terminal_size(Coord { x: 80, y: 24 });
+        assert!(settings.fits_terminal());
+        assert_eq!(settings.get_scrolling(), (false, true));
+        assert_eq!(
+            settings.get_viewport(),
+            (Coord { x: 0, y: 0 }, Coord { x: 10, y: 6 })
+        );
+
+        settings.scroll_to(&Coord { x: 0, y: 9 });
+        assert_eq!(settings.get_viewport().0, Coord { x: 0, y: 4 });
+        settings.scroll_to(&Coord { x: 0, y: 6 });
+        assert_eq!(settings.get_viewport().0, Coord { x: 0, y: 4 });
+        settings.scroll_to(&Coord { x: 0, y: 1 });
+        assert_eq!(settings.get_viewport().0, Coord { x: 0, y: 1 });
+
+        settings.set_terminal_size(Coord { x: 80, y: 17 });
+        assert!(!settings.fits_terminal());
+    }
+}
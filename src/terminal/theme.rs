@@ -0,0 +1,187 @@
+use crossterm::style::{Attribute, Color, ContentStyle};
+
+use crate::render::{Glyph, GlyphRole};
+use crate::ricracroe::RRRCell;
+use crate::solver::SolvedValue;
+
+pub const THEME_NAMES: &[&str] = &["default", "high-contrast", "monochrome"];
+
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub x: ContentStyle,
+    pub o: ContentStyle,
+    pub grid: ContentStyle,
+    // These are layered over the style of whatever is drawn in the cell
+    pub cursor: ContentStyle,
+    pub last_move: ContentStyle,
+    pub winning: ContentStyle,
+    // Analysis mode annotations
+    pub analysis_win: ContentStyle,
+    pub analysis_draw: ContentStyle,
+    pub analysis_loss: ContentStyle,
+    pub analysis_unclear: ContentStyle,
+}
+
+fn fg(color: Color) -> ContentStyle {
+    ContentStyle::new().foreground(color)
+}
+
+fn bg(color: Color) -> ContentStyle {
+    ContentStyle::new().background(color)
+}
+
+fn attr(attribute: Attribute) -> ContentStyle {
+    ContentStyle::new().attribute(attribute)
+}
+
+impl Theme {
+    pub fn default_colors() -> Self {
+        Theme {
+            x: fg(Color::Red).attribute(Attribute::Bold),
+            o: fg(Color::Cyan).attribute(Attribute::Bold),
+            grid: fg(Color::DarkGrey),
+            cursor: bg(Color::DarkGrey),
+            last_move: attr(Attribute::Underlined),
+            winning: bg(Color::DarkGreen),
+            analysis_win: fg(Color::Green),
+            analysis_draw: fg(Color::Yellow),
+            analysis_loss: fg(Color::Red),
+            analysis_unclear: fg(Color::DarkGrey),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            x: fg(Color::Yellow).attribute(Attribute::Bold),
+            o: fg(Color::Cyan).attribute(Attribute::Bold),
+            grid: fg(Color::White),
+            cursor: attr(Attribute::Reverse),
+            last_move: attr(Attribute::Underlined),
+            winning: bg(Color::Magenta).foreground(Color::White),
+            analysis_win: fg(Color::Green).attribute(Attribute::Bold),
+            analysis_draw: fg(Color::Yellow).attribute(Attribute::Bold),
+            analysis_loss: fg(Color::Red).attribute(Attribute::Bold),
+            analysis_unclear: fg(Color::White),
+        }
+    }
+
+    /// No colors at all, only text attributes
+    pub fn monochrome() -> Self {
+        Theme {
+            x: attr(Attribute::Bold),
+            o: ContentStyle::new(),
+            grid: ContentStyle::new(),
+            cursor: attr(Attribute::Reverse),
+            last_move: attr(Attribute::Underlined),
+            winning: attr(Attribute::Bold).attribute(Attribute::Reverse),
+            analysis_win: attr(Attribute::Bold),
+            analysis_draw: ContentStyle::new(),
+            analysis_loss: attr(Attribute::Dim),
+            analysis_unclear: attr(Attribute::Dim),
+        }
+    }
+
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Theme::default_colors()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            "monochrome" => Some(Theme::monochrome()),
+            _ => None,
+        }
+    }
+
+    /// Pick the named theme, or fall back to one that respects NO_COLOR
+    /// (https://no-color.org/) if no theme was asked for
+    pub fn select(name: Option<&str>) -> Self {
+        if let Some(theme) = name.and_then(Theme::by_name) {
+            return theme;
+        }
+        if std::env::var_os("NO_COLOR").is_some() {
+            Theme::monochrome()
+        } else {
+            Theme::default_colors()
+        }
+    }
+
+    fn analysis(&self, value: Option<SolvedValue>) -> &ContentStyle {
+        match value {
+            Some(SolvedValue::Win) => &self.analysis_win,
+            Some(SolvedValue::Draw) => &self.analysis_draw,
+            Some(SolvedValue::Loss) => &self.analysis_loss,
+            None => &self.analysis_unclear,
+        }
+    }
+
+    /// Style for a character of a rendered board
+    pub fn glyph_style(&self, glyph: &Glyph) -> ContentStyle {
+        let mut glyph_style = match glyph.role {
+            GlyphRole::Grid => self.grid.clone(),
+            GlyphRole::Empty => ContentStyle::new(),
+            GlyphRole::Piece(RRRCell::X) | GlyphRole::Number(RRRCell::X) => self.x.clone(),
+            GlyphRole::Piece(RRRCell::O) | GlyphRole::Number(RRRCell::O) => self.o.clone(),
+            GlyphRole::Piece(_) | GlyphRole::Number(_) => ContentStyle::new(),
+            GlyphRole::Annotation(value) => self.analysis(value).clone(),
+        };
+        if glyph.highlights.winning {
+            glyph_style = layer(&glyph_style, &self.winning);
+        }
+        if glyph.highlights.last_move {
+            glyph_style = layer(&glyph_style, &self.last_move);
+        }
+        if glyph.highlights.cursor {
+            glyph_style = layer(&glyph_style, &self.cursor);
+        }
+        glyph_style
+    }
+}
+
+/// Apply `top` over `base`: colors in `top` win, attributes accumulate
+pub fn layer(base: &ContentStyle, top: &ContentStyle) -> ContentStyle {
+    let mut style = base.clone();
+    if top.foreground_color.is_some() {
+        style.foreground_color = top.foreground_color;
+    }
+    if top.background_color.is_some() {
+        style.background_color = top.background_color;
+    }
+    style.attributes.extend(top.attributes.iter().cloned());
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_theme_can_be_picked_by_name() {
+        for name in THEME_NAMES {
+            assert!(Theme::by_name(name).is_some(), "{}", name);
+        }
+        assert!(Theme::by_name("neon").is_none());
+    }
+
+    #[test]
+    fn monochrome_has_no_colors() {
+        let theme = Theme::monochrome();
+        for style in &[
+            &theme.x,
+            &theme.o,
+            &theme.grid,
+            &theme.cursor,
+            &theme.last_move,
+            &theme.winning,
+        ] {
+            assert_eq!(style.foreground_color, None);
+            assert_eq!(style.background_color, None);
+        }
+    }
+
+    #[test]
+    fn layers_keep_colors_from_below_unless_replaced() {
+        let base = fg(Color::Red).background(Color::Blue);
+        let style = layer(&base, &bg(Color::Green).attribute(Attribute::Bold));
+        assert_eq!(style.foreground_color, Some(Color::Red));
+        assert_eq!(style.background_color, Some(Color::Green));
+        assert_eq!(style.attributes, vec![Attribute::Bold]);
+    }
+}
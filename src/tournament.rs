@@ -0,0 +1,349 @@
+use std::fmt;
+use std::rc::Rc;
+use std::time::Duration;
+
+use log::{debug, info, warn};
+
+use crate::ai::{AiLevel, AiPlayer, RRRPlayer};
+use crate::bot::ExternalPlayer;
+use crate::errors::{Error, Result};
+use crate::ricracroe::{RRRCell, RRRGame, RRROutcome, RRRRules};
+use crate::solver::SolverTable;
+
+const BASE_ELO: f64 = 1500.0;
+// Two-sided 95% confidence
+const Z_95: f64 = 1.96;
+// Keep perfect scores from turning into infinite rating differences
+const MIN_SCORE: f64 = 0.001;
+
+/// Something that can be entered in a tournament
+#[derive(Debug, Clone)]
+pub enum Entrant {
+    Engine(AiLevel),
+    /// A program to run that plays over stdin and stdout, see ExternalPlayer
+    Bot(String),
+}
+
+impl fmt::Display for Entrant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Entrant::Engine(level) => write!(f, "{}", level),
+            Entrant::Bot(command) => write!(f, "{}", command),
+        }
+    }
+}
+
+pub struct TournamentSettings {
+    pub rules: RRRRules,
+    pub games_per_pairing: usize,
+    pub engines: Vec<Entrant>,
+    pub table: Option<Rc<SolverTable>>,
+    /// How long bots get to answer each request before they forfeit
+    pub move_timeout: Duration,
+}
+
+impl TournamentSettings {
+    fn make_player(&self, entrant: &Entrant) -> Result<Box<dyn RRRPlayer>> {
+        Ok(match (entrant, &self.table) {
+            (Entrant::Engine(level), Some(table)) => {
+                Box::new(AiPlayer::with_table(*level, Rc::clone(table)))
+            }
+            (Entrant::Engine(level), None) => Box::new(AiPlayer::new(*level)),
+            (Entrant::Bot(command), _) => {
+                Box::new(ExternalPlayer::spawn(command, self.move_timeout)?)
+            }
+        })
+    }
+}
+
+pub struct Standing {
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Standing {
+    fn new(name: String) -> Self {
+        Standing {
+            name,
+            wins: 0,
+            draws: 0,
+            losses: 0,
+        }
+    }
+
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Fraction of available points scored, counting draws as half a point
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// Elo estimate relative to the field, with a 95% confidence interval
+    /// returned as (estimate, lower bound, upper bound)
+    pub fn elo(&self) -> (f64, f64, f64) {
+        let games = self.games() as f64;
+        let score = self.score();
+        if games == 0.0 {
+            return (BASE_ELO, BASE_ELO, BASE_ELO);
+        }
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let margin = Z_95 * (variance / games).sqrt();
+        (
+            BASE_ELO + elo_difference(score),
+            BASE_ELO + elo_difference(score - margin),
+            BASE_ELO + elo_difference(score + margin),
+        )
+    }
+}
+
+fn elo_difference(score: f64) -> f64 {
+    let score = score.clamp(MIN_SCORE, 1.0 - MIN_SCORE);
+    -400.0 * (1.0 / score - 1.0).log10()
+}
+
+/// Play a single game between two engines without any terminal attached.
+/// Whoever gives no move, or one that can't be played, forfeits.
+pub fn play_headless(
+    rules: RRRRules,
+    x: &mut dyn RRRPlayer,
+    o: &mut dyn RRRPlayer,
+) -> Result<RRROutcome> {
+    let mut game = RRRGame::new_with_rules(rules);
+    loop {
+        if let Some(outcome) = game.outcome.clone() {
+            return Ok(outcome);
+        }
+        let player: &mut dyn RRRPlayer = match game.get_turn() {
+            RRRCell::X => x,
+            RRRCell::O => o,
+            RRRCell::Clear | RRRCell::Number(_) => return Err(Error::InvalidGameInput),
+        };
+        let forfeit = RRROutcome::Forfeit {
+            loser: game.get_turn(),
+        };
+        let (coord, piece) = match player.choose_placement(&game) {
+            Some(placement) => placement,
+            None => {
+                warn!("{} gave no move, and forfeits", player.name());
+                return Ok(forfeit);
+            }
+        };
+        debug!(
+            "{} ({}) places {} in {}",
+            player.name(),
+            game.get_turn(),
+            piece,
+            coord
+        );
+        if let Err(e) = game.place(&coord, piece) {
+            warn!(
+                "{} can't play {} in {} ({}), and forfeits",
+                player.name(),
+                piece,
+                coord.algebraic(),
+                e
+            );
+            return Ok(forfeit);
+        }
+    }
+}
+
+/// Round-robin every engine against every other one, alternating who plays X
+pub fn run(settings: &TournamentSettings) -> Result<Vec<Standing>> {
+    let mut standings: Vec<Standing> = settings
+        .engines
+        .iter()
+        .enumerate()
+        .map(|(num, entrant)| Standing::new(format!("#{} {}", num + 1, entrant)))
+        .collect();
+
+    for first in 0..settings.engines.len() {
+        for second in (first + 1)..settings.engines.len() {
+            let mut first_player = settings.make_player(&settings.engines[first])?;
+            let mut second_player = settings.make_player(&settings.engines[second])?;
+            for game_num in 0..settings.games_per_pairing {
+                // Alternate who gets the first move
+                let (x_idx, o_idx) = if game_num % 2 == 0 {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                let outcome = if x_idx == first {
+                    play_headless(settings.rules, &mut *first_player, &mut *second_player)?
+                } else {
+                    play_headless(settings.rules, &mut *second_player, &mut *first_player)?
+                };
+                info!(
+                    "{} (X) vs {} (O): {}",
+                    standings[x_idx].name, standings[o_idx].name, outcome
+                );
+                match outcome.winner() {
+                    Some(RRRCell::X) => {
+                        standings[x_idx].wins += 1;
+                        standings[o_idx].losses += 1;
+                    }
+                    Some(_) => {
+                        standings[o_idx].wins += 1;
+                        standings[x_idx].losses += 1;
+                    }
+                    None => {
+                        standings[x_idx].draws += 1;
+                        standings[o_idx].draws += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    standings.sort_by(|a, b| {
+        b.score()
+            .partial_cmp(&a.score())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    Ok(standings)
+}
+
+pub fn format_results(standings: &[Standing]) -> String {
+    // Bots are named by their command, which can be long
+    let width = standings
+        .iter()
+        .map(|standing| standing.name.chars().count())
+        .fold(16, usize::max);
+    let mut table = format!(
+        "{:<width$} {:>6} {:>6} {:>6} {:>6}  {:>6}  {:>15}\n",
+        "Engine",
+        "Games",
+        "Wins",
+        "Draws",
+        "Losses",
+        "Elo",
+        "95% CI",
+        width = width
+    );
+    for standing in standings {
+        let (elo, low, high) = standing.elo();
+        table.push_str(&format!(
+            "{:<width$} {:>6} {:>6} {:>6} {:>6}  {:>6.0}  {:>6.0} - {:<6.0}\n",
+            standing.name,
+            standing.games(),
+            standing.wins,
+            standing.draws,
+            standing.losses,
+            elo,
+            low,
+            high,
+            width = width
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coord::Coord;
+
+    fn standing(wins: usize, draws: usize, losses: usize) -> Standing {
+        Standing {
+            wins,
+            draws,
+            losses,
+            ..Standing::new("test".to_string())
+        }
+    }
+
+    #[test]
+    fn even_scores_rate_the_same_as_the_field() {
+        assert_eq!(standing(0, 0, 0).elo(), (BASE_ELO, BASE_ELO, BASE_ELO));
+        let (elo, low, high) = standing(3, 4, 3).elo();
+        assert!((elo - BASE_ELO).abs() < 1e-9);
+        assert!(low < elo && elo < high);
+        assert!((standing(3, 4, 3).score() - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn perfect_scores_stay_finite() {
+        let (elo, low, high) = standing(10, 0, 0).elo();
+        assert!(elo.is_finite() && low.is_finite() && high.is_finite());
+        assert!(elo > BASE_ELO + 1000.0);
+        assert!(standing(0, 0, 10).elo().0 < BASE_ELO - 1000.0);
+        assert!((elo_difference(0.75) - 190.8).abs() < 0.1);
+    }
+
+    #[test]
+    fn entrants_play_each_other() {
+        let settings = TournamentSettings {
+            rules: RRRRules::default(),
+            games_per_pairing: 2,
+            engines: vec![
+                Entrant::Engine(AiLevel::Perfect),
+                Entrant::Engine(AiLevel::Random),
+            ],
+            table: None,
+            move_timeout: Duration::from_secs(5),
+        };
+        let standings = run(&settings).unwrap();
+        assert_eq!(standings.len(), 2);
+        let perfect = standings.iter().find(|s| s.name == "#1 perfect").unwrap();
+        assert_eq!(perfect.games(), 2);
+        assert_eq!(perfect.losses, 0);
+        assert!(format_results(&standings).contains("#2 random"));
+
+        let broken = TournamentSettings {
+            engines: vec![
+                Entrant::Engine(AiLevel::Random),
+                Entrant::Bot("/nonexistent/ricracroe-bot".to_string()),
+            ],
+            ..settings
+        };
+        assert!(run(&broken).is_err());
+    }
+
+    // Always tries the top left cell, which only works once
+    struct Stubborn;
+
+    impl RRRPlayer for Stubborn {
+        fn name(&self) -> String {
+            "stubborn".to_string()
+        }
+
+        fn choose_move(&mut self, _: &RRRGame) -> Option<Coord> {
+            Some(Coord { x: 0, y: 0 })
+        }
+    }
+
+    #[test]
+    fn bad_moves_forfeit_the_game() {
+        let rules = RRRRules::default();
+        let mut random = AiPlayer::new(AiLevel::Random);
+        let outcome = play_headless(rules, &mut Stubborn, &mut random).unwrap();
+        assert_eq!(outcome, RRROutcome::Forfeit { loser: RRRCell::X });
+        assert_eq!(outcome.winner(), Some(RRRCell::O));
+
+        // A bot that echoes its requests back never gives a move, but the
+        // tournament carries on
+        let settings = TournamentSettings {
+            rules,
+            games_per_pairing: 2,
+            engines: vec![
+                Entrant::Engine(AiLevel::Random),
+                Entrant::Bot("cat".to_string()),
+            ],
+            table: None,
+            move_timeout: Duration::from_secs(5),
+        };
+        let standings = run(&settings).unwrap();
+        let bot = standings.iter().find(|s| s.name == "#2 cat").unwrap();
+        assert_eq!((bot.games(), bot.losses), (2, 2));
+    }
+}